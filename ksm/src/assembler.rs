@@ -0,0 +1,776 @@
+use std::collections::{hash_map::Entry, HashMap};
+use std::path::{Path, PathBuf};
+
+use kvm::{native_id, DebugInfo, Instruction, Program, SourceFile};
+
+use crate::{
+    ast::{Line, Operand, OperandValue, Statement, StatementKind},
+    diagnostic::Diagnostic,
+    lexer::Lexer,
+    macros::Macro,
+    parser::Parser,
+    token::Span,
+};
+
+/// Assembles a ksm program, resolving `.include`s relative to the current
+/// directory.
+///
+/// Errors don't stop the assembler, every one of them is returned in the
+/// order they appear in the source.
+pub fn assemble(source: &str) -> Result<Program, Vec<Diagnostic>> {
+    Assembler::default().assemble(source, None)
+}
+
+/// Assembles a ksm program read from `path`, resolving `.include`s relative
+/// to it and naming the file every error is in.
+pub fn assemble_file(source: &str, path: &Path) -> Result<Program, Vec<Diagnostic>> {
+    Assembler::default().assemble(source, Some(path))
+}
+
+/// Same as `assemble` or `assemble_file`, calling the natives of
+/// `callnative` by the names in `natives`, which lists every native of the
+/// vm running the program in the order of their ids, as `Kvm::get_natives`
+/// does. The other functions only know the default natives by name.
+pub fn assemble_with_natives(
+    source: &str,
+    path: Option<&Path>,
+    natives: &[&str],
+) -> Result<Program, Vec<Diagnostic>> {
+    let assembler = Assembler {
+        natives: Some(natives.iter().map(|name| name.to_string()).collect()),
+        ..Assembler::default()
+    };
+    assembler.assemble(source, path)
+}
+
+/// What a label, `.const` or `.string` name stands for.
+#[derive(Debug)]
+enum Symbol {
+    Label(u32),
+    Constant(i64),
+    Str(String),
+}
+
+impl Symbol {
+    fn kind(&self) -> &'static str {
+        match self {
+            Symbol::Label(_) => "label",
+            Symbol::Constant(_) => "constant",
+            Symbol::Str(_) => "string",
+        }
+    }
+}
+
+/// A parsed line along with the file it comes from and the macro uses it was
+/// expanded from, outermost first.
+#[derive(Debug, Clone)]
+struct SourceLine {
+    line: Line,
+    file: Option<String>,
+    expansions: Vec<Expansion>,
+    /// Where its instructions come from, which is the macro use for lines
+    /// of a macro.
+    origin: Origin,
+}
+
+/// A line of one of the files of `DebugInfo::sources`.
+#[derive(Debug, Clone, Copy)]
+struct Origin {
+    file: u32,
+    line: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Expansion {
+    name: String,
+    file: Option<String>,
+    span: Span,
+}
+
+impl SourceLine {
+    /// Moves `diagnostic` to this line's file. Errors inside a macro point
+    /// at its outermost use, with a note for every line it went through.
+    fn locate(&self, diagnostic: Diagnostic) -> Diagnostic {
+        let diagnostic = diagnostic.in_file(self.file.as_deref());
+        let Some(outermost) = self.expansions.first() else {
+            return diagnostic;
+        };
+
+        let mut located =
+            Diagnostic::new(outermost.span, diagnostic.message).in_file(outermost.file.as_deref());
+
+        let inner = self.expansions[1..]
+            .iter()
+            .map(|expansion| (expansion.file.as_deref(), expansion.span))
+            .chain([(diagnostic.file.as_deref(), diagnostic.span)]);
+
+        for (expansion, (file, span)) in self.expansions.iter().zip(inner) {
+            located = located.with_note(
+                Diagnostic::new(span, format!("in macro `{}`", expansion.name)).in_file(file),
+            );
+        }
+
+        located.notes.extend(diagnostic.notes);
+        located
+    }
+}
+
+/// Assembles in two passes: the first one defines every label, constant and
+/// string, the second one builds the instructions from them, so names can be
+/// used before they are defined. Includes and macros are expanded before
+/// both.
+#[derive(Default)]
+struct Assembler {
+    symbols: HashMap<String, Symbol>,
+    macros: HashMap<String, Macro>,
+    /// How many macros were expanded, to give each expansion its labels.
+    expansions: usize,
+    diagnostics: Vec<Diagnostic>,
+    /// The files being included, to detect cycles.
+    includes: Vec<PathBuf>,
+    /// Every file loaded so far, for the debug info.
+    sources: Vec<SourceFile>,
+    /// Names of the natives by id, the default natives when `None`.
+    natives: Option<Vec<String>>,
+}
+
+impl Assembler {
+    fn assemble(mut self, source: &str, path: Option<&Path>) -> Result<Program, Vec<Diagnostic>> {
+        if let Some(path) = path {
+            self.includes
+                .push(path.canonicalize().unwrap_or(path.to_path_buf()));
+        }
+
+        let mut lines = Vec::new();
+        self.load(source, path, 0, &mut lines);
+
+        let entry = self.define_symbols(&lines);
+
+        let mut instructions = Vec::new();
+        let mut lines_info = Vec::new();
+        let mut files = Vec::new();
+
+        for source_line in &lines {
+            let Some(statement) = &source_line.line.statement else {
+                continue;
+            };
+            if statement.kind == StatementKind::Directive {
+                continue;
+            }
+
+            match self.decode(statement) {
+                Ok(inst) => {
+                    instructions.push(inst);
+                    lines_info.push(source_line.origin.line);
+                    files.push(source_line.origin.file);
+                }
+                Err(diagnostic) => self.diagnostics.push(source_line.locate(diagnostic)),
+            }
+        }
+
+        let labels = self
+            .symbols
+            .iter()
+            .filter_map(|(name, symbol)| match symbol {
+                Symbol::Label(addr) => Some((name.clone(), *addr)),
+                _ => None,
+            })
+            .collect();
+
+        let mut program = Program::new(instructions);
+        program.debug_info = Some(DebugInfo {
+            lines: lines_info,
+            files,
+            sources: std::mem::take(&mut self.sources),
+            labels,
+        });
+
+        if let Some((operand, source_line)) = entry {
+            match self.target("entry", &operand) {
+                Ok(addr) => program.entry = addr,
+                Err(diagnostic) => self.diagnostics.push(source_line.locate(diagnostic)),
+            }
+        }
+
+        if !self.diagnostics.is_empty() {
+            self.diagnostics
+                .sort_by_key(|diagnostic| (diagnostic.file.clone(), diagnostic.span));
+            return Err(self.diagnostics);
+        }
+
+        Ok(program)
+    }
+
+    /// Parses `source` into `lines`, collecting macro definitions and
+    /// replacing every `.include` and macro use with the lines it stands for.
+    /// `included_at` is the line of the assembled file that includes it, 0
+    /// for the assembled file itself.
+    fn load(
+        &mut self,
+        source: &str,
+        path: Option<&Path>,
+        included_at: u32,
+        lines: &mut Vec<SourceLine>,
+    ) {
+        let file = path.map(|path| path.display().to_string());
+        let idx = self.sources.len() as u32;
+        self.sources.push(SourceFile {
+            name: file.clone().unwrap_or_default(),
+            included_at,
+        });
+
+        let mut parser = Parser::new(Lexer::new(source));
+        let parsed = parser.parse_program();
+        self.diagnostics.extend(
+            parser
+                .errors
+                .into_iter()
+                .map(|diagnostic| diagnostic.in_file(file.as_deref())),
+        );
+
+        let mut defining: Option<Macro> = None;
+
+        for mut line in parsed {
+            let directive = match &line.statement {
+                Some(statement) if statement.kind == StatementKind::Directive => {
+                    Some(statement.name.as_str())
+                }
+                _ => None,
+            };
+
+            match (directive, defining.as_mut()) {
+                (Some("endmacro"), Some(_)) => {
+                    let mut definition = defining.take().unwrap();
+                    if line.label.is_some() {
+                        line.statement = None;
+                        definition.body.push(line);
+                    }
+                    self.define_macro(definition);
+                }
+                (Some("macro"), Some(_)) => self.diagnostics.push(
+                    Diagnostic::new(
+                        line.statement.unwrap().span,
+                        "macros can't be defined inside other macros",
+                    )
+                    .in_file(file.as_deref()),
+                ),
+                (_, Some(definition)) => definition.body.push(line),
+                (Some("macro"), None) => {
+                    let statement = line.statement.take().unwrap();
+                    match Macro::new(&statement, file.as_deref()) {
+                        Ok(definition) => defining = Some(definition),
+                        Err(diagnostic) => {
+                            self.diagnostics.push(diagnostic.in_file(file.as_deref()))
+                        }
+                    }
+                    if line.label.is_some() {
+                        self.emit(source_line(line, file.clone(), idx), lines);
+                    }
+                }
+                (Some("endmacro"), None) => self.diagnostics.push(
+                    Diagnostic::new(line.statement.unwrap().span, "`.endmacro` without `.macro`")
+                        .in_file(file.as_deref()),
+                ),
+                _ => self.emit(source_line(line, file.clone(), idx), lines),
+            }
+        }
+
+        if let Some(definition) = defining {
+            self.diagnostics.push(
+                Diagnostic::new(
+                    definition.span,
+                    format!("macro `{}` has no `.endmacro`", definition.name),
+                )
+                .in_file(file.as_deref()),
+            );
+        }
+    }
+
+    fn define_macro(&mut self, definition: Macro) {
+        if arity(&definition.name).is_some() {
+            self.diagnostics.push(
+                Diagnostic::new(
+                    definition.span,
+                    format!("macro `{}` would shadow an instruction", definition.name),
+                )
+                .in_file(definition.file.as_deref()),
+            );
+            return;
+        }
+
+        match self.macros.entry(definition.name.clone()) {
+            Entry::Occupied(entry) => self.diagnostics.push(
+                Diagnostic::new(
+                    definition.span,
+                    format!("macro `{}` is already defined", definition.name),
+                )
+                .in_file(definition.file.as_deref())
+                .with_note(defined_here(entry.get())),
+            ),
+            Entry::Vacant(entry) => {
+                entry.insert(definition);
+            }
+        }
+    }
+
+    /// Adds `source_line` to `lines`, unless it's an include or a macro use,
+    /// which are replaced with the lines they stand for.
+    fn emit(&mut self, mut source_line: SourceLine, lines: &mut Vec<SourceLine>) {
+        let statement = match source_line.line.statement.take() {
+            Some(statement)
+                if (statement.kind == StatementKind::Directive && statement.name == "include")
+                    || (statement.kind == StatementKind::Instruction
+                        && self.macros.contains_key(&statement.name)) =>
+            {
+                statement
+            }
+            statement => {
+                source_line.line.statement = statement;
+                lines.push(source_line);
+                return;
+            }
+        };
+
+        // a label in front of an include or a macro names its first
+        // instruction
+        if source_line.line.label.is_some() {
+            lines.push(source_line.clone());
+        }
+
+        let result = match statement.kind {
+            StatementKind::Directive => self.include(&statement, &source_line, lines),
+            StatementKind::Instruction => self.expand(&statement, &source_line, lines),
+        };
+
+        if let Err(diagnostic) = result {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn include(
+        &mut self,
+        statement: &Statement,
+        from: &SourceLine,
+        lines: &mut Vec<SourceLine>,
+    ) -> Result<(), Diagnostic> {
+        let [Operand {
+            value: OperandValue::Str(name),
+            ..
+        }] = statement.operands.as_slice()
+        else {
+            return Err(from.locate(Diagnostic::new(
+                statement.span,
+                "`.include` expects a file name",
+            )));
+        };
+
+        let path = match from.file.as_deref().map(Path::new).and_then(Path::parent) {
+            Some(dir) => dir.join(name),
+            None => PathBuf::from(name),
+        };
+        let source = std::fs::read_to_string(&path).map_err(|err| {
+            from.locate(Diagnostic::new(
+                statement.span,
+                format!("could not include `{}`: {}", path.display(), err),
+            ))
+        })?;
+
+        let canonical = path.canonicalize().unwrap_or(path.clone());
+        if let Some(start) = self.includes.iter().position(|p| *p == canonical) {
+            let cycle: Vec<String> = self.includes[start..]
+                .iter()
+                .chain([&canonical])
+                .map(|p| p.display().to_string())
+                .collect();
+
+            return Err(from.locate(Diagnostic::new(
+                statement.span,
+                format!("include cycle: {}", cycle.join(" -> ")),
+            )));
+        }
+
+        self.includes.push(canonical);
+        let included_at = match from.origin.file {
+            0 => from.origin.line,
+            file => self.sources[file as usize].included_at,
+        };
+        self.load(&source, Some(&path), included_at, lines);
+        self.includes.pop();
+
+        Ok(())
+    }
+
+    fn expand(
+        &mut self,
+        statement: &Statement,
+        from: &SourceLine,
+        lines: &mut Vec<SourceLine>,
+    ) -> Result<(), Diagnostic> {
+        let name = &statement.name;
+        let definition = self.macros[name].clone();
+
+        if from
+            .expansions
+            .iter()
+            .any(|expansion| expansion.name == *name)
+        {
+            return Err(from.locate(Diagnostic::new(
+                statement.span,
+                format!("macro `{}` expands itself", name),
+            )));
+        }
+
+        if statement.operands.len() != definition.params.len() {
+            return Err(from
+                .locate(Diagnostic::new(
+                    statement.span,
+                    format!(
+                        "macro `{}` expects {} argument(s), got {}",
+                        name,
+                        definition.params.len(),
+                        statement.operands.len()
+                    ),
+                ))
+                .with_note(defined_here(&definition)));
+        }
+
+        self.expansions += 1;
+
+        let mut expansions = from.expansions.clone();
+        expansions.push(Expansion {
+            name: name.clone(),
+            file: from.file.clone(),
+            span: statement.span,
+        });
+
+        for line in definition.expand(&statement.operands, self.expansions) {
+            self.emit(
+                SourceLine {
+                    line,
+                    file: definition.file.clone(),
+                    expansions: expansions.clone(),
+                    origin: from.origin,
+                },
+                lines,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// First pass: gives every label the address of the instruction following
+    /// it and defines the `.const` and `.string` names. Returns the operand of
+    /// `.entry`, which can only be resolved once every label is known.
+    fn define_symbols<'l>(&mut self, lines: &'l [SourceLine]) -> Option<(Operand, &'l SourceLine)> {
+        let mut address = 0;
+        let mut entry = None;
+
+        for source_line in lines {
+            let line = &source_line.line;
+
+            if let Some(label) = &line.label {
+                if let Err(diagnostic) =
+                    self.define(&label.name, Symbol::Label(address), label.span)
+                {
+                    self.diagnostics.push(source_line.locate(diagnostic));
+                }
+            }
+
+            let Some(statement) = &line.statement else {
+                continue;
+            };
+
+            if statement.kind == StatementKind::Instruction {
+                address += 1;
+                continue;
+            }
+
+            let operands: Vec<_> = statement.operands.iter().map(|o| &o.value).collect();
+            let result = match (statement.name.as_str(), operands.as_slice()) {
+                ("const", [OperandValue::Identifier(name), OperandValue::Number(n)]) => {
+                    self.define(name, Symbol::Constant(*n), statement.span)
+                }
+                ("const", _) => Err(Diagnostic::new(
+                    statement.span,
+                    "`.const` expects a name and a number",
+                )),
+                ("string", [OperandValue::Identifier(name), OperandValue::Str(s)]) => {
+                    self.define(name, Symbol::Str(s.clone()), statement.span)
+                }
+                ("string", _) => Err(Diagnostic::new(
+                    statement.span,
+                    "`.string` expects a name and a string",
+                )),
+                ("entry", [_]) if entry.is_some() => Err(Diagnostic::new(
+                    statement.span,
+                    "the entry point is already defined",
+                )),
+                ("entry", [_]) => {
+                    entry = Some((statement.operands[0].clone(), source_line));
+                    Ok(())
+                }
+                ("entry", _) => Err(Diagnostic::new(statement.span, "`.entry` expects a label")),
+                (name, _) => Err(Diagnostic::new(
+                    statement.span,
+                    format!("unknown directive `.{}`", name),
+                )),
+            };
+
+            if let Err(diagnostic) = result {
+                self.diagnostics.push(source_line.locate(diagnostic));
+            }
+        }
+
+        entry
+    }
+
+    fn define(&mut self, name: &str, symbol: Symbol, span: Span) -> Result<(), Diagnostic> {
+        match self.symbols.entry(name.to_string()) {
+            Entry::Occupied(_) => Err(Diagnostic::new(
+                span,
+                format!("{} `{}` is already defined", symbol.kind(), name),
+            )),
+            Entry::Vacant(entry) => {
+                entry.insert(symbol);
+                Ok(())
+            }
+        }
+    }
+
+    /// Second pass: builds the instruction of a statement.
+    fn decode(&self, statement: &Statement) -> Result<Instruction, Diagnostic> {
+        let name = statement.name.as_str();
+        let operands = &statement.operands;
+
+        let Some(arity) = arity(name) else {
+            return Err(Diagnostic::new(
+                statement.span,
+                format!("unknown instruction `{}`", name),
+            ));
+        };
+
+        if operands.len() < arity {
+            return Err(Diagnostic::new(
+                statement.span,
+                format!("missing operand for `{}`", name),
+            ));
+        }
+        if let Some(extra) = operands.get(arity) {
+            return Err(Diagnostic::new(
+                extra.span,
+                format!("too many operands for `{}`", name),
+            ));
+        }
+
+        let inst = match name {
+            "halt" => Instruction::Halt,
+            "add" => Instruction::Add,
+            "sub" => Instruction::Sub,
+            "div" => Instruction::Div,
+            "mul" => Instruction::Mul,
+            "mod" => Instruction::Mod,
+            "neg" => Instruction::Neg,
+            "eq" => Instruction::Eq,
+            "ne" => Instruction::Ne,
+            "lt" => Instruction::Lt,
+            "gt" => Instruction::Gt,
+            "le" => Instruction::Le,
+            "ge" => Instruction::Ge,
+            "not" => Instruction::Not,
+            "and" => Instruction::And,
+            "or" => Instruction::Or,
+            "pop" => Instruction::Pop,
+            "swap" => Instruction::Swap,
+            "over" => Instruction::Over,
+            "rot" => Instruction::Rot,
+            "print" => Instruction::Print,
+            "input" => Instruction::Input,
+            // kept for programs written before strings lived on the stack
+            "printstr" | "printstack" => Instruction::Print,
+            "pushnull" => Instruction::PushNull,
+            "pushbool" => match &operands[0].value {
+                OperandValue::Identifier(b) if b == "true" => Instruction::PushBool(true),
+                OperandValue::Identifier(b) if b == "false" => Instruction::PushBool(false),
+                OperandValue::Identifier(b) => {
+                    return Err(Diagnostic::new(
+                        operands[0].span,
+                        format!("invalid boolean `{}`", b),
+                    ))
+                }
+                _ => return Err(expected(name, "a boolean", &operands[0])),
+            },
+            "pushstr" => Instruction::PushStr(self.string(name, &operands[0])?),
+            "push" => Instruction::Push(self.int(name, &operands[0])?),
+            "jmpif" => Instruction::JmpIf(self.target(name, &operands[0])?),
+            "jmpifnot" => Instruction::JmpIfNot(self.target(name, &operands[0])?),
+            "jmp" => Instruction::Jmp(self.target(name, &operands[0])?),
+            "call" => Instruction::Call(
+                self.target(name, &operands[0])?,
+                self.word(name, &operands[1])?,
+            ),
+            "dup" => Instruction::Dup(self.word(name, &operands[0])?),
+            "ret" => Instruction::Ret,
+            "loadlocal" => Instruction::LoadLocal(self.word(name, &operands[0])?),
+            "storelocal" => Instruction::StoreLocal(self.word(name, &operands[0])?),
+            "loadglobal" => Instruction::LoadGlobal(self.word(name, &operands[0])?),
+            "storeglobal" => Instruction::StoreGlobal(self.word(name, &operands[0])?),
+            "newarray" => Instruction::NewArray(self.word(name, &operands[0])?),
+            "arrayget" => Instruction::ArrayGet,
+            "arrayset" => Instruction::ArraySet,
+            "arraylen" => Instruction::ArrayLen,
+            "concat" => Instruction::Concat,
+            "closure" => Instruction::Closure(
+                self.target(name, &operands[0])?,
+                self.word(name, &operands[1])?,
+            ),
+            "callclosure" => Instruction::CallClosure(self.word(name, &operands[0])?),
+            "callnative" => Instruction::CallNative(
+                self.native(name, &operands[0])?,
+                self.word(name, &operands[1])?,
+            ),
+            _ => unreachable!("{} has an arity", name),
+        };
+
+        Ok(inst)
+    }
+
+    /// The value of a number, constant or label operand. `undefined` names
+    /// what an unknown identifier was expected to be.
+    fn number(&self, name: &str, operand: &Operand, undefined: &str) -> Result<i64, Diagnostic> {
+        match &operand.value {
+            OperandValue::Number(n) => Ok(*n),
+            OperandValue::Identifier(identifier) => match self.symbols.get(identifier) {
+                Some(Symbol::Constant(n)) => Ok(*n),
+                Some(Symbol::Label(addr)) => Ok(*addr as i64),
+                Some(Symbol::Str(_)) => Err(expected(name, "a number", operand)),
+                None => Err(Diagnostic::new(
+                    operand.span,
+                    format!("undefined {} `{}`", undefined, identifier),
+                )),
+            },
+            OperandValue::Str(_) => Err(expected(name, "a number", operand)),
+        }
+    }
+
+    fn int(&self, name: &str, operand: &Operand) -> Result<i32, Diagnostic> {
+        let n = self.number(name, operand, "constant")?;
+
+        i32::try_from(n).map_err(|_| {
+            Diagnostic::new(
+                operand.span,
+                format!("`{}` expects a 32 bit integer, got {}", name, n),
+            )
+        })
+    }
+
+    fn word(&self, name: &str, operand: &Operand) -> Result<u32, Diagnostic> {
+        let n = self.number(name, operand, "constant")?;
+        word(name, operand, n)
+    }
+
+    fn target(&self, name: &str, operand: &Operand) -> Result<u32, Diagnostic> {
+        let n = self.number(name, operand, "label")?;
+        word(name, operand, n)
+    }
+
+    /// A known native by its name, or any native by its id, which can be
+    /// named with `.const`.
+    fn native(&self, name: &str, operand: &Operand) -> Result<u32, Diagnostic> {
+        match &operand.value {
+            OperandValue::Identifier(identifier) if !self.symbols.contains_key(identifier) => {
+                let id = match &self.natives {
+                    Some(natives) => natives
+                        .iter()
+                        .position(|native| native == identifier)
+                        .map(|id| id as u32),
+                    None => native_id(identifier),
+                };
+                id.ok_or_else(|| {
+                    Diagnostic::new(operand.span, format!("undefined native `{}`", identifier))
+                })
+            }
+            _ => self.word(name, operand),
+        }
+    }
+
+    fn string(&self, name: &str, operand: &Operand) -> Result<String, Diagnostic> {
+        match &operand.value {
+            OperandValue::Str(s) => Ok(s.clone()),
+            OperandValue::Identifier(identifier) => match self.symbols.get(identifier) {
+                Some(Symbol::Str(s)) => Ok(s.clone()),
+                Some(_) => Err(expected(name, "a string", operand)),
+                None => Err(Diagnostic::new(
+                    operand.span,
+                    format!("undefined string `{}`", identifier),
+                )),
+            },
+            OperandValue::Number(_) => Err(expected(name, "a string", operand)),
+        }
+    }
+}
+
+/// A line read from `file`, the `idx`th entry of `DebugInfo::sources`.
+fn source_line(line: Line, file: Option<String>, idx: u32) -> SourceLine {
+    let origin = Origin {
+        file: idx,
+        line: match (&line.statement, &line.label) {
+            (Some(statement), _) => statement.span.line,
+            (None, Some(label)) => label.span.line,
+            (None, None) => 0,
+        },
+    };
+
+    SourceLine {
+        line,
+        file,
+        expansions: Vec::new(),
+        origin,
+    }
+}
+
+fn defined_here(definition: &Macro) -> Diagnostic {
+    Diagnostic::new(
+        definition.span,
+        format!("macro `{}` is defined here", definition.name),
+    )
+    .in_file(definition.file.as_deref())
+}
+
+/// How many operands an instruction takes, `None` when there's no such
+/// instruction.
+fn arity(name: &str) -> Option<usize> {
+    let arity = match name {
+        "halt" | "add" | "sub" | "div" | "mul" | "mod" | "neg" | "eq" | "ne" | "lt" | "gt"
+        | "le" | "ge" | "not" | "and" | "or" | "pop" | "swap" | "over" | "rot" | "print"
+        | "input" | "printstr" | "printstack" | "pushnull" | "ret" | "arrayget" | "arrayset"
+        | "arraylen" | "concat" => 0,
+        "push" | "pushstr" | "pushbool" | "jmp" | "jmpif" | "jmpifnot" | "dup" | "loadlocal"
+        | "storelocal" | "loadglobal" | "storeglobal" | "newarray" | "callclosure" => 1,
+        "call" | "closure" | "callnative" => 2,
+        _ => return None,
+    };
+
+    Some(arity)
+}
+
+fn expected(name: &str, what: &str, operand: &Operand) -> Diagnostic {
+    let found = match &operand.value {
+        OperandValue::Number(n) => format!("number `{}`", n),
+        OperandValue::Str(_) => "a string".to_string(),
+        OperandValue::Identifier(identifier) => format!("`{}`", identifier),
+    };
+
+    Diagnostic::new(
+        operand.span,
+        format!("`{}` expects {}, found {}", name, what, found),
+    )
+}
+
+fn word(name: &str, operand: &Operand, n: i64) -> Result<u32, Diagnostic> {
+    u32::try_from(n).map_err(|_| {
+        Diagnostic::new(
+            operand.span,
+            format!("`{}` expects a non-negative operand, got {}", name, n),
+        )
+    })
+}
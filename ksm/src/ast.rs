@@ -0,0 +1,44 @@
+use crate::token::Span;
+
+/// A line of a ksm program that isn't empty or only a comment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub label: Option<Label>,
+    pub statement: Option<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
+    Instruction,
+    Directive,
+}
+
+/// An instruction or a directive with its operands, not yet checked against
+/// what the mnemonic expects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub name: String,
+    pub operands: Vec<Operand>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OperandValue {
+    Number(i64),
+    Str(String),
+    /// A label reference or a boolean.
+    Identifier(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Operand {
+    pub value: OperandValue,
+    pub span: Span,
+}
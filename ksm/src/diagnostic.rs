@@ -0,0 +1,56 @@
+use std::fmt::Display;
+
+use crate::token::Span;
+
+/// An error found while assembling, pointing at where it happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The file the error is in, `None` for a source that wasn't read from a
+    /// file.
+    pub file: Option<String>,
+    pub span: Span,
+    pub message: String,
+    /// Other places related to the error, like the macro it was expanded
+    /// from.
+    pub notes: Vec<Diagnostic>,
+}
+
+impl Diagnostic {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            file: None,
+            span,
+            message: message.into(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn in_file(mut self, file: Option<&str>) -> Self {
+        self.file = file.map(str::to_string);
+        self
+    }
+
+    pub fn with_note(mut self, note: Diagnostic) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    fn location(&self) -> String {
+        match &self.file {
+            Some(file) => format!("{}:{}:{}", file, self.span.line, self.span.column),
+            None => format!("{}:{}", self.span.line, self.span.column),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location(), self.message)?;
+
+        for note in &self.notes {
+            write!(f, "\n  {}: note: {}", note.location(), note.message)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use kvm::{native_name, Instruction, Program};
+
+/// Longest encoded instructions, `call` and `closure` with their two
+/// operands.
+const MAX_INSTRUCTION_BYTES: usize = 9;
+
+/// Turns instructions back into ksm source, naming every jump and call target
+/// `L0`, `L1`, ... in address order so the output can be assembled again.
+pub fn disassemble(instructions: &[Instruction]) -> String {
+    let labels = labels(instructions, 0);
+    let mut source = String::new();
+
+    for (addr, inst) in instructions.iter().enumerate() {
+        if let Some(label) = labels.get(&(addr as u32)) {
+            source.push_str(&format!("{}:\n", label));
+        }
+
+        source.push_str(&format!("    {}\n", mnemonic(inst, &labels)));
+    }
+
+    source
+}
+
+/// Like [`disassemble`], starting every instruction with a comment holding
+/// its index, its byte offset in the .kvm file and its bytes. Assembling the
+/// output without debug info gives back the same file.
+pub fn disassemble_program(program: &Program) -> String {
+    let instructions = &program.instructions;
+    let labels = labels(instructions, program.entry);
+    let encoded = program.encode();
+    let mut source = String::new();
+
+    if program.entry != 0 {
+        match labels.get(&program.entry) {
+            Some(label) => source.push_str(&format!(".entry {}\n", label)),
+            None => source.push_str(&format!(".entry {}\n", program.entry)),
+        }
+    }
+
+    for (addr, (inst, range)) in instructions.iter().zip(&encoded.instructions).enumerate() {
+        if let Some(label) = labels.get(&(addr as u32)) {
+            source.push_str(&format!("{}:\n", label));
+        }
+
+        source.push_str(&format!(
+            "/* {:>4}  {:#06x}  {:<width$} */    {}\n",
+            addr,
+            range.start,
+            hex(&encoded.bytes[range.clone()]),
+            mnemonic(inst, &labels),
+            width = MAX_INSTRUCTION_BYTES * 3 - 1,
+        ));
+    }
+
+    source
+}
+
+/// Bytes as space separated hex pairs.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Names every jump and call target, and the entry point, in address order.
+fn labels(instructions: &[Instruction], entry: u32) -> BTreeMap<u32, String> {
+    let mut labels: BTreeMap<u32, String> = BTreeMap::new();
+
+    let targets = instructions.iter().filter_map(|inst| match inst {
+        Instruction::Jmp(addr)
+        | Instruction::JmpIf(addr)
+        | Instruction::JmpIfNot(addr)
+        | Instruction::Call(addr, _)
+        | Instruction::Closure(addr, _) => Some(*addr),
+        _ => None,
+    });
+
+    // targets outside of the program are kept as plain addresses
+    for addr in targets.chain((entry != 0).then_some(entry)) {
+        if (addr as usize) < instructions.len() {
+            labels.insert(addr, String::new());
+        }
+    }
+
+    for (idx, label) in labels.values_mut().enumerate() {
+        *label = format!("L{}", idx);
+    }
+
+    labels
+}
+
+fn mnemonic(inst: &Instruction, labels: &BTreeMap<u32, String>) -> String {
+    match inst {
+        Instruction::Jmp(target) | Instruction::JmpIf(target) | Instruction::JmpIfNot(target) => {
+            match labels.get(target) {
+                Some(label) => format!("{} {}", inst.name(), label),
+                None => inst.to_string(),
+            }
+        }
+        Instruction::Call(target, n) | Instruction::Closure(target, n) => {
+            match labels.get(target) {
+                Some(label) => format!("{} {} {}", inst.name(), label, n),
+                None => inst.to_string(),
+            }
+        }
+        Instruction::CallNative(id, argc) => match native_name(*id) {
+            Some(name) => format!("callnative {} {}", name, argc),
+            None => inst.to_string(),
+        },
+        inst => inst.to_string(),
+    }
+}
@@ -1,89 +1,160 @@
-use kvm::Instruction;
+use crate::{
+    diagnostic::Diagnostic,
+    token::{Span, Token},
+};
 
+/// Splits a ksm source into tokens, leaving their meaning to the parser.
 #[derive(Debug)]
 pub struct Lexer<'l> {
     input: &'l str,
     current_position: usize,
     read_position: usize,
     current_char: Option<char>,
+    current_span: Span,
+    token_position: usize,
+    token_span: Span,
 }
 
 impl<'l> Lexer<'l> {
-    pub fn new(input: &'l str) -> Lexer<'_> {
+    pub fn new(input: &'l str) -> Lexer<'l> {
         Lexer {
             input,
             current_position: 0,
-            read_position: 1,
-            current_char: input.chars().nth(0),
+            read_position: input.chars().next().map_or(0, char::len_utf8),
+            current_char: input.chars().next(),
+            current_span: Span { line: 1, column: 1 },
+            token_position: 0,
+            token_span: Span { line: 1, column: 1 },
         }
     }
 
-    fn read_string(&mut self) -> String {
-        self.read_char();
-        if self.current_char.unwrap() != '"' {
-            panic!(
-                "Unexpected start of string, expected: '\"', got: {:?}",
-                self.current_char
-            );
-        }
+    /// Where the last token returned by the lexer starts.
+    pub fn span(&self) -> Span {
+        self.token_span
+    }
+
+    fn error(&self, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(self.span(), message)
+    }
+
+    /// Text from the start of the current token up to the current char.
+    fn literal(&self) -> String {
+        self.input[self.token_position..self.current_position].to_string()
+    }
 
+    fn read_string(&mut self) -> Result<String, Diagnostic> {
         let mut str = String::new();
         self.read_char();
 
         while let Some(c) = self.current_char {
             if c == '"' {
+                self.read_char();
+                return Ok(str);
+            }
+            if c == '\n' {
                 break;
             }
+            if c == '\\' {
+                self.read_char();
+                let escaped = match self.current_char {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('t') => '\t',
+                    Some(c) if c != '\n' => {
+                        return Err(self.error(format!("unknown escape `\\{}`", c)))
+                    }
+                    _ => break,
+                };
+                str.push(escaped);
+                self.read_char();
+                continue;
+            }
             str.push(c);
             self.read_char();
         }
 
-        self.read_char();
-
-        // TODO: handle strings without relying on ""
-        str.push('"');
-        str.insert(0, '"');
-
-        str
+        Err(self.error("unterminated string"))
     }
 
-    fn read_number(&mut self) -> i32 {
-        self.skip_whitespaces();
-        let start_pos = self.current_position;
+    /// Reads a decimal or `0x` prefixed hexadecimal number, optionally
+    /// negative.
+    fn read_number(&mut self) -> Result<i64, Diagnostic> {
+        let negative = self.current_char == Some('-');
+        if negative {
+            self.read_char();
+        }
+
+        let mut radix = 10;
+        if self.current_char == Some('0')
+            && matches!(self.peek_char(self.read_position), Some('x' | 'X'))
+        {
+            radix = 16;
+            self.read_char();
+            self.read_char();
+        }
 
+        let mut digits = String::new();
         while let Some(c) = self.current_char {
-            if c.is_ascii_digit() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                digits.push(c);
                 self.read_char();
                 continue;
             }
             break;
         }
 
-        self.input[start_pos..self.current_position]
-            .parse::<i32>()
-            .unwrap()
+        match i64::from_str_radix(&digits, radix) {
+            Ok(n) if negative => Ok(-n),
+            Ok(n) => Ok(n),
+            Err(_) => Err(self.error(format!("invalid number `{}`", self.literal()))),
+        }
+    }
+
+    fn read_comment(&mut self) -> Result<String, Diagnostic> {
+        self.read_char();
+        self.read_char();
+
+        let mut comment = String::new();
+
+        while let Some(ch) = self.current_char {
+            if ch == '*' && self.peek_char(self.read_position) == Some('/') {
+                self.read_char();
+                self.read_char();
+                return Ok(comment.trim().to_string());
+            }
+            comment.push(ch);
+            self.read_char();
+        }
+
+        Err(self.error("unterminated comment"))
     }
 
     fn peek_char(&self, pos: usize) -> Option<char> {
-        self.input.chars().nth(pos)
+        self.input.get(pos..).and_then(|rest| rest.chars().next())
     }
 
+    /// Moves to the next char, keeping track of its line and column.
     fn read_char(&mut self) {
-        if self.read_position > self.input.len() {
-            self.current_char = None;
-            return;
+        match self.current_char {
+            Some('\n') => {
+                self.current_span.line += 1;
+                self.current_span.column = 1;
+            }
+            Some(_) => self.current_span.column += 1,
+            None => return,
         }
 
-        let ch = self.input.chars().nth(self.read_position);
-
-        self.current_char = ch;
+        self.current_char = self.peek_char(self.read_position);
         self.current_position = self.read_position;
-        self.read_position += 1;
+        self.read_position += self.current_char.map_or(0, char::len_utf8);
     }
 
-    fn skip_whitespaces(&mut self) {
+    /// Skips whitespace up to the end of the line, newlines are tokens.
+    fn skip_blanks(&mut self) {
         while let Some(c) = self.current_char {
-            if c.is_whitespace() {
+            if c.is_whitespace() && c != '\n' {
                 self.read_char();
                 continue;
             }
@@ -91,32 +162,20 @@ impl<'l> Lexer<'l> {
         }
     }
 
-    fn skip_comments(&mut self) {
-        while let (Some(ch), Some(next_ch)) =
-            (self.current_char, self.peek_char(self.read_position))
-        {
-            if ch == '*' && next_ch == '/' {
-                break;
-            }
-            self.read_char();
-        }
-        self.read_char();
-        self.read_char();
-    }
-
     fn read_identifier(&mut self) -> String {
-        let start_pos = self.current_position;
+        let mut identifier = String::new();
 
+        // labels can contain digits and underscores after their first letter
         while let Some(c) = self.current_char {
-            if c.is_letter() {
+            if c.is_letter() || c.is_ascii_digit() || c == '_' {
+                identifier.push(c);
                 self.read_char();
                 continue;
             }
             break;
         }
 
-        let identifier = &self.input[start_pos..self.current_position];
-        identifier.to_string()
+        identifier
     }
 }
 
@@ -131,47 +190,48 @@ impl IsLetter for char {
 }
 
 impl Iterator for Lexer<'_> {
-    type Item = Instruction;
+    type Item = Result<Token, Diagnostic>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.skip_whitespaces();
+        self.skip_blanks();
         let ch = self.current_char?;
+        self.token_position = self.current_position;
+        self.token_span = self.current_span;
 
-        let inst: Option<Instruction> = match ch {
-            '/' => {
-                if self.peek_char(self.read_position).unwrap() == '*' {
-                    self.skip_comments();
-                }
-                return self.next()
-            },
-            c => {
-                if c.is_letter() {
-                    let identifier = self.read_identifier();
-                    match identifier.as_str() {
-                        "halt" => Some(Instruction::Halt),
-                        "add" => Some(Instruction::Add),
-                        "sub" => Some(Instruction::Sub),
-                        "div" => Some(Instruction::Div),
-                        "mul" => Some(Instruction::Mul),
-                        "eq" => Some(Instruction::Eq),
-                        "printstr" => Some(Instruction::PrintStr),
-                        "printstack" => Some(Instruction::PrintStack),
-                        "pushstr" => Some(Instruction::PushStr(self.read_string())),
-                        "push" => Some(Instruction::Push(self.read_number())),
-                        "jmpif" => Some(Instruction::JmpIf(self.read_number() as u32)),
-                        "jmp" => Some(Instruction::Jmp(self.read_number() as u32)),
-                        "dup" => Some(Instruction::Dup(self.read_number() as u32)),
-                        _ => {
-                            panic!("Invalid instruction: {}", identifier);
-                        }
-                    }
+        let next_ch = self.peek_char(self.read_position);
+
+        let token = match ch {
+            '\n' => {
+                self.read_char();
+                Ok(Token::Newline)
+            }
+            '/' if next_ch == Some('*') => self.read_comment().map(Token::Comment),
+            '"' => self.read_string().map(Token::Str),
+            '.' if next_ch.is_some_and(|c| c.is_letter()) => {
+                self.read_char();
+                Ok(Token::Directive(self.read_identifier()))
+            }
+            c if c.is_letter() => {
+                let identifier = self.read_identifier();
+
+                if self.current_char == Some(':') {
+                    self.read_char();
+                    Ok(Token::Label(identifier))
                 } else {
-                    panic!("Invalid instruction: {}", c);
+                    Ok(Token::Identifier(identifier))
                 }
             }
+            c if c.is_ascii_digit()
+                || (c == '-' && next_ch.is_some_and(|c| c.is_ascii_digit())) =>
+            {
+                self.read_number().map(Token::Number)
+            }
+            c => {
+                self.read_char();
+                Err(self.error(format!("unexpected character `{}`", c)))
+            }
         };
 
-        self.read_char();
-        inst
+        Some(token)
     }
 }
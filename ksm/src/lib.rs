@@ -0,0 +1,9 @@
+pub mod assembler;
+pub mod ast;
+pub mod diagnostic;
+pub mod disassembler;
+pub mod lexer;
+pub mod listing;
+pub mod macros;
+pub mod parser;
+pub mod token;
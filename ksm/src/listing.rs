@@ -0,0 +1,69 @@
+use std::collections::BTreeMap;
+
+use kvm::Program;
+
+use crate::disassembler::hex;
+
+/// Width of the bytes column, enough for the longest instruction.
+const BYTES_WIDTH: usize = 26;
+
+/// Lays out `source` next to the bytecode assembled from it: every line
+/// starts with the index, byte offset and bytes of the instructions it
+/// produced, matched through the debug info of `program`. Lines producing
+/// more than one instruction, like macro uses and includes, get a row for
+/// every extra one.
+pub fn listing(source: &str, program: &Program) -> String {
+    let encoded = program.encode();
+    let mut by_line: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+
+    if let Some(debug_info) = &program.debug_info {
+        for addr in 0..debug_info.lines.len() {
+            if let Some(line) = debug_info.assembled_line(addr) {
+                by_line.entry(line).or_default().push(addr);
+            }
+        }
+    }
+
+    let code = |addr: usize| {
+        let range = &encoded.instructions[addr];
+        format!(
+            "{:>4}  {:#06x}  {:<width$}",
+            addr,
+            range.start,
+            hex(&encoded.bytes[range.clone()]),
+            width = BYTES_WIDTH
+        )
+    };
+    let no_code = format!("{:>4}  {:6}  {:<width$}", "", "", "", width = BYTES_WIDTH);
+
+    let mut listing = format!(
+        "{:>4}  {:6}  {:<width$}  {:>4}  source\n",
+        "addr",
+        "offset",
+        "bytes",
+        "line",
+        width = BYTES_WIDTH
+    );
+
+    for (idx, text) in source.lines().enumerate() {
+        let line = idx as u32 + 1;
+        let addrs = by_line.get(&line).map(Vec::as_slice).unwrap_or_default();
+
+        let first = addrs.first().map(|addr| code(*addr));
+        let row = format!(
+            "{}  {:>4}  {}",
+            first.unwrap_or(no_code.clone()),
+            line,
+            text
+        );
+        listing.push_str(row.trim_end());
+        listing.push('\n');
+
+        for addr in addrs.iter().skip(1) {
+            listing.push_str(code(*addr).trim_end());
+            listing.push('\n');
+        }
+    }
+
+    listing
+}
@@ -0,0 +1,97 @@
+use std::collections::HashSet;
+
+use crate::{
+    ast::{Label, Line, Operand, OperandValue, Statement},
+    diagnostic::Diagnostic,
+    token::Span,
+};
+
+/// A sequence of lines defined between `.macro name params...` and
+/// `.endmacro`, pasted wherever `name` is used as an instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Line>,
+    /// Where the macro is defined.
+    pub file: Option<String>,
+    pub span: Span,
+}
+
+impl Macro {
+    /// Starts a macro from its `.macro` directive, with an empty body.
+    pub fn new(statement: &Statement, file: Option<&str>) -> Result<Macro, Diagnostic> {
+        let mut names = statement
+            .operands
+            .iter()
+            .map(|operand| match &operand.value {
+                OperandValue::Identifier(name) => Ok(name.clone()),
+                _ => Err(Diagnostic::new(
+                    operand.span,
+                    "`.macro` expects a name followed by parameter names",
+                )),
+            });
+
+        let name = names
+            .next()
+            .ok_or_else(|| Diagnostic::new(statement.span, "`.macro` expects a name"))??;
+        let params: Vec<String> = names.collect::<Result<_, _>>()?;
+
+        let mut seen = HashSet::new();
+        if let Some(param) = params.iter().find(|param| !seen.insert(*param)) {
+            return Err(Diagnostic::new(
+                statement.span,
+                format!("parameter `{}` of macro `{}` is repeated", param, name),
+            ));
+        }
+
+        Ok(Macro {
+            name,
+            params,
+            body: Vec::new(),
+            file: file.map(str::to_string),
+            span: statement.span,
+        })
+    }
+
+    /// The body with every parameter replaced by its argument. Labels defined
+    /// in the body get `id` appended, so every expansion has its own.
+    pub fn expand(&self, args: &[Operand], id: usize) -> Vec<Line> {
+        let locals: HashSet<&str> = self
+            .body
+            .iter()
+            .filter_map(|line| line.label.as_ref())
+            .map(|label| label.name.as_str())
+            .collect();
+        let local = |name: &str| format!("{}.{}", name, id);
+
+        self.body
+            .iter()
+            .map(|line| {
+                let mut line = line.clone();
+
+                if let Some(Label { name, .. }) = &mut line.label {
+                    *name = local(name);
+                }
+
+                for operand in line
+                    .statement
+                    .iter_mut()
+                    .flat_map(|statement| statement.operands.iter_mut())
+                {
+                    let OperandValue::Identifier(name) = &operand.value else {
+                        continue;
+                    };
+
+                    if let Some(idx) = self.params.iter().position(|param| param == name) {
+                        operand.value = args[idx].value.clone();
+                    } else if locals.contains(name.as_str()) {
+                        operand.value = OperandValue::Identifier(local(name));
+                    }
+                }
+
+                line
+            })
+            .collect()
+    }
+}
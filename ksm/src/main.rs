@@ -1,9 +1,8 @@
-use kvm::{Instruction, Kvm};
-use std::{error::Error, fs::File, io::Write};
+use kvm::{optimize_program, verify_from, Program, DEFAULT_NATIVES};
+use std::{error::Error, fs::File, io::Write, path::Path};
 
 use clap::Parser;
-
-mod lexer;
+use ksm::{assembler::assemble_with_natives, disassembler::disassemble_program, listing::listing};
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -16,34 +15,76 @@ struct Args {
 
     #[arg(short, long)]
     disassemble: bool,
+
+    /// Store the source line of every instruction in the output file
+    #[arg(short = 'g', long)]
+    debug_info: bool,
+
+    /// Write the source next to the bytecode assembled from it to this file
+    #[arg(short, long)]
+    listing: Option<String>,
+
+    /// Fold constants, collapse jump chains and remove dead code
+    #[arg(short = 'O', long)]
+    optimize: bool,
+
+    /// Name of a native the host registers after the default ones, to call
+    /// it by name, repeated in the order they are registered
+    #[arg(short, long = "native", value_name = "NAME")]
+    natives: Vec<String>,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
     if args.disassemble {
-        let mut vm = Kvm::new();
-        vm.load_program_from_file(&args.input_file);
-        let instructions = vm.get_instructions();
-        instructions.iter().for_each(|inst| println!("{}", inst));
+        let bytes = std::fs::read(&args.input_file)?;
+        print!("{}", disassemble_program(&Program::from_bytes(&bytes)?));
     } else {
-        let prog_asm = std::fs::read_to_string(args.input_file)?;
+        let prog_asm = std::fs::read_to_string(&args.input_file)?;
+
+        let natives: Vec<&str> = DEFAULT_NATIVES
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(args.natives.iter().map(String::as_str))
+            .collect();
+
+        let path = Path::new(&args.input_file);
 
-        let lexer = lexer::Lexer::new(&prog_asm);
-        let prog_inst: Vec<Instruction> = lexer.collect();
+        let mut program = match assemble_with_natives(&prog_asm, Some(path), &natives) {
+            Ok(program) => program,
+            Err(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    eprintln!("{}", diagnostic);
+                }
+                eprintln!("{} error(s), no output written", diagnostics.len());
+                std::process::exit(1);
+            }
+        };
+        if args.optimize {
+            // the optimizer only supports programs that pass verification
+            if let Err(err) = verify_from(&program.instructions, program.entry as usize) {
+                eprintln!("{}", err);
+                eprintln!("can not optimize an invalid program, no output written");
+                std::process::exit(1);
+            }
+            program = optimize_program(&program);
+        }
+        if let Some(path) = &args.listing {
+            std::fs::write(path, listing(&prog_asm, &program))?;
+        }
+        if !args.debug_info {
+            program.debug_info = None;
+        }
 
         // TODO: handle option without expect
-        save_program_to_file(
-            &prog_inst,
-            &args.output_file.expect("Expected output file!"),
-        );
+        save_program_to_file(&program, &args.output_file.expect("Expected output file!"))?;
     }
 
     Ok(())
 }
 
-fn save_program_to_file(program: &[Instruction], file_path: &str) {
-    let mut file = File::create(file_path).unwrap();
-    let prog_bin: Vec<u8> = program.iter().flat_map(|inst| inst.as_bytes()).collect();
-    file.write_all(prog_bin.as_ref()).unwrap();
+fn save_program_to_file(program: &Program, file_path: &str) -> std::io::Result<()> {
+    let mut file = File::create(file_path)?;
+    file.write_all(program.to_bytes().as_ref())
 }
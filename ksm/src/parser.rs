@@ -0,0 +1,122 @@
+use crate::{
+    ast::{Label, Line, Operand, OperandValue, Statement, StatementKind},
+    diagnostic::Diagnostic,
+    lexer::Lexer,
+    token::{Span, Token},
+};
+
+/// Groups the tokens of every line into an optional label followed by an
+/// optional instruction or directive and its operands.
+#[derive(Debug)]
+pub struct Parser<'p> {
+    pub errors: Vec<Diagnostic>,
+    lexer: Lexer<'p>,
+}
+
+impl<'p> Parser<'p> {
+    pub fn new(lexer: Lexer<'p>) -> Self {
+        Parser {
+            errors: Vec::new(),
+            lexer,
+        }
+    }
+
+    /// Parses every line, skipping the ones with errors after recording them
+    /// in `errors`.
+    pub fn parse_program(&mut self) -> Vec<Line> {
+        let mut lines = Vec::new();
+        let mut tokens = Vec::new();
+        let mut has_errors = false;
+
+        loop {
+            let token = self.lexer.next();
+            let span = self.lexer.span();
+
+            match token {
+                Some(Ok(Token::Comment(_))) => {}
+                Some(Ok(Token::Newline)) | None => {
+                    let line = std::mem::take(&mut tokens);
+
+                    if !has_errors {
+                        match self.parse_line(line) {
+                            Ok(Some(line)) => lines.push(line),
+                            Ok(None) => {}
+                            Err(err) => self.errors.push(err),
+                        }
+                    }
+
+                    if token.is_none() {
+                        break;
+                    }
+                    has_errors = false;
+                }
+                Some(Ok(token)) => tokens.push((token, span)),
+                // only the first error of a line is reported, the following
+                // ones are usually caused by it
+                Some(Err(err)) => {
+                    if !has_errors {
+                        self.errors.push(err);
+                    }
+                    has_errors = true;
+                }
+            }
+        }
+
+        lines
+    }
+
+    fn parse_line(&self, tokens: Vec<(Token, Span)>) -> Result<Option<Line>, Diagnostic> {
+        let mut tokens = tokens.into_iter().peekable();
+
+        let label = match tokens.next_if(|(token, _)| matches!(token, Token::Label(_))) {
+            Some((Token::Label(name), span)) => Some(Label { name, span }),
+            _ => None,
+        };
+
+        let (kind, name, span) = match tokens.next() {
+            None if label.is_none() => return Ok(None),
+            None => {
+                return Ok(Some(Line {
+                    label,
+                    statement: None,
+                }))
+            }
+            Some((Token::Identifier(name), span)) => (StatementKind::Instruction, name, span),
+            Some((Token::Directive(name), span)) => (StatementKind::Directive, name, span),
+            Some((token, span)) => {
+                return Err(Diagnostic::new(
+                    span,
+                    format!("expected an instruction, found {}", token),
+                ))
+            }
+        };
+
+        let operands = tokens
+            .map(|(token, span)| {
+                let value = match token {
+                    Token::Number(n) => OperandValue::Number(n),
+                    Token::Str(s) => OperandValue::Str(s),
+                    Token::Identifier(name) => OperandValue::Identifier(name),
+                    token => {
+                        return Err(Diagnostic::new(
+                            span,
+                            format!("expected an operand, found {}", token),
+                        ))
+                    }
+                };
+
+                Ok(Operand { value, span })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(Line {
+            label,
+            statement: Some(Statement {
+                kind,
+                name,
+                operands,
+                span,
+            }),
+        }))
+    }
+}
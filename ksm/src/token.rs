@@ -0,0 +1,36 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    /// Mnemonics, label references and booleans.
+    Identifier(String),
+    Number(i64),
+    Str(String),
+    /// A label definition, `name:`.
+    Label(String),
+    /// `.name`
+    Directive(String),
+    Comment(String),
+    Newline,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Identifier(name) => write!(f, "`{}`", name),
+            Token::Number(n) => write!(f, "number `{}`", n),
+            Token::Str(_) => write!(f, "a string"),
+            Token::Label(name) => write!(f, "label `{}:`", name),
+            Token::Directive(name) => write!(f, "directive `.{}`", name),
+            Token::Comment(_) => write!(f, "a comment"),
+            Token::Newline => write!(f, "end of line"),
+        }
+    }
+}
+
+/// Position in a ksm source file, both starting at 1.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Span {
+    pub line: u32,
+    pub column: u32,
+}
@@ -0,0 +1,592 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use ksm::{
+    assembler::{assemble, assemble_file, assemble_with_natives},
+    diagnostic::Diagnostic,
+    disassembler::{disassemble, disassemble_program},
+    token::Span,
+};
+use kvm::{Instruction, Kvm, KvmError, SourceFile, Value};
+
+/// Writes `files` to a fresh directory named after the test.
+fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ksm-{}-{}", test, std::process::id()));
+
+    for (name, source) in files {
+        let path = dir.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+
+    dir
+}
+
+#[test]
+fn given_labels_it_should_resolve_jumps_to_their_addresses() {
+    let source = "
+        push 3
+    loop:
+        push 1
+        call dec_2 2
+        dup 0
+        jmpif loop
+        jmp end
+    dec_2:
+        loadlocal 1
+        loadlocal 0
+        sub
+        ret
+    end:
+        halt
+    ";
+
+    let program = assemble(source).unwrap();
+
+    assert_eq!(
+        program.instructions,
+        vec![
+            Instruction::Push(3),
+            Instruction::Push(1),
+            Instruction::Call(6, 2),
+            Instruction::Dup(0),
+            Instruction::JmpIf(1),
+            Instruction::Jmp(10),
+            Instruction::LoadLocal(1),
+            Instruction::LoadLocal(0),
+            Instruction::Sub,
+            Instruction::Ret,
+            Instruction::Halt,
+        ]
+    );
+
+    let debug_info = program.debug_info.unwrap();
+    assert_eq!(debug_info.lines, vec![2, 4, 5, 6, 7, 8, 10, 11, 12, 13, 15]);
+    assert_eq!(
+        debug_info.labels,
+        BTreeMap::from([
+            ("loop".to_string(), 1),
+            ("dec_2".to_string(), 6),
+            ("end".to_string(), 10),
+        ])
+    );
+}
+
+#[test]
+fn given_numeric_targets_it_should_keep_them() {
+    let program = assemble("jmp 2\ncall 2 0\nhalt").unwrap();
+
+    assert_eq!(
+        program.instructions,
+        vec![
+            Instruction::Jmp(2),
+            Instruction::Call(2, 0),
+            Instruction::Halt
+        ]
+    );
+}
+
+#[test]
+fn given_an_undefined_label_it_should_return_an_error() {
+    assert_eq!(
+        assemble("push 1\njmpif done\nhalt"),
+        Err(vec![Diagnostic::new(
+            Span { line: 2, column: 7 },
+            "undefined label `done`"
+        )])
+    );
+}
+
+#[test]
+fn given_a_duplicate_label_it_should_return_an_error() {
+    assert_eq!(
+        assemble("start:\npush 1\nstart:\nhalt"),
+        Err(vec![Diagnostic::new(
+            Span { line: 3, column: 1 },
+            "label `start` is already defined"
+        )])
+    );
+}
+
+#[test]
+fn given_jump_targets_it_should_disassemble_them_as_labels() {
+    let instructions = vec![
+        Instruction::Push(7),
+        Instruction::Call(4, 1),
+        Instruction::Print,
+        Instruction::Jmp(42),
+        Instruction::LoadLocal(0),
+        Instruction::Dup(0),
+        Instruction::JmpIf(4),
+        Instruction::Ret,
+    ];
+
+    let source = disassemble(&instructions);
+
+    assert_eq!(
+        source,
+        "    push 7\n    call L0 1\n    print\n    jmp 42\nL0:\n    loadlocal 0\n    dup 0\n    jmpif L0\n    ret\n"
+    );
+    assert_eq!(assemble(&source).unwrap().instructions, instructions);
+}
+
+#[test]
+fn given_negative_and_hex_literals_it_should_assemble_them() {
+    let program = assemble("push -1\npush 0x1F\npush -0x10\ndup 0X2\nhalt").unwrap();
+
+    assert_eq!(
+        program.instructions,
+        vec![
+            Instruction::Push(-1),
+            Instruction::Push(31),
+            Instruction::Push(-16),
+            Instruction::Dup(2),
+            Instruction::Halt,
+        ]
+    );
+}
+
+#[test]
+fn given_several_errors_it_should_report_all_of_them_with_their_position() {
+    let source = "push 1\nfoo 3\npush\n  dup -2\npush 12ab\npush 4294967296\njmp nowhere\n\
+                  pushbool maybe\n@ 1\npushstr \"abc\nhalt";
+
+    let diagnostics: Vec<String> = assemble(source)
+        .unwrap_err()
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            "2:1: unknown instruction `foo`",
+            "3:1: missing operand for `push`",
+            "4:7: `dup` expects a non-negative operand, got -2",
+            "5:6: invalid number `12ab`",
+            "6:6: `push` expects a 32 bit integer, got 4294967296",
+            "7:5: undefined label `nowhere`",
+            "8:10: invalid boolean `maybe`",
+            "9:1: unexpected character `@`",
+            "10:9: unterminated string",
+        ]
+    );
+}
+
+#[test]
+fn given_an_unterminated_comment_it_should_return_an_error() {
+    assert_eq!(
+        assemble("push 1\n/* the end\nprint\nhalt"),
+        Err(vec![Diagnostic::new(
+            Span { line: 2, column: 1 },
+            "unterminated comment"
+        )])
+    );
+}
+
+#[test]
+fn given_constants_and_strings_it_should_substitute_them() {
+    let source = "
+        .const ANSWER 42
+        .const LOCALS 0x2
+        .string GREETING \"hello\"
+        push ANSWER
+        dup LOCALS
+        pushstr GREETING
+        pushstr \"inline\"
+        push SIZE
+        halt
+        .const SIZE -7
+    ";
+
+    let program = assemble(source).unwrap();
+
+    assert_eq!(
+        program.instructions,
+        vec![
+            Instruction::Push(42),
+            Instruction::Dup(2),
+            Instruction::PushStr("hello".to_string()),
+            Instruction::PushStr("inline".to_string()),
+            Instruction::Push(-7),
+            Instruction::Halt,
+        ]
+    );
+}
+
+#[test]
+fn given_misused_names_it_should_return_errors() {
+    let source = ".const N 1\n.string S \"s\"\n.const N 2\npush S\npushstr N\npush M\n\
+                  .const\n.entry nowhere\n.entry N\n.foo";
+
+    let diagnostics: Vec<String> = assemble(source)
+        .unwrap_err()
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            "3:1: constant `N` is already defined",
+            "4:6: `push` expects a number, found `S`",
+            "5:9: `pushstr` expects a string, found `N`",
+            "6:6: undefined constant `M`",
+            "7:1: `.const` expects a name and a number",
+            "8:8: undefined label `nowhere`",
+            "9:1: the entry point is already defined",
+            "10:1: unknown directive `.foo`",
+        ]
+    );
+}
+
+#[test]
+fn given_an_entry_directive_it_should_set_the_program_start() {
+    let source = "
+        .entry main
+    double:
+        loadlocal 0
+        dup 0
+        add
+        ret
+    main:
+        push 21
+        call double 1
+        halt
+    ";
+
+    let program = assemble(source).unwrap();
+
+    assert_eq!(program.entry, 4);
+
+    let source = disassemble_program(&program);
+
+    assert!(source.starts_with(".entry L1\n"));
+    assert_eq!(assemble(&source).unwrap().entry, 4);
+    assert_eq!(assemble("halt").unwrap().entry, 0);
+}
+
+#[test]
+fn given_includes_it_should_assemble_them_in_place() {
+    let dir = write_files(
+        "include",
+        &[
+            (
+                "main.ksm",
+                ".include \"lib/consts.ksm\"\npush ANSWER\ncall twice 1\nhalt\n.include \"lib/twice.ksm\"",
+            ),
+            ("lib/consts.ksm", ".const ANSWER 21"),
+            (
+                "lib/twice.ksm",
+                "twice:\n    loadlocal 0\n    dup 0\n    add\n    ret",
+            ),
+        ],
+    );
+    let path = dir.join("main.ksm");
+
+    let program = assemble_file(&std::fs::read_to_string(&path).unwrap(), &path).unwrap();
+
+    assert_eq!(
+        program.instructions,
+        vec![
+            Instruction::Push(21),
+            Instruction::Call(3, 1),
+            Instruction::Halt,
+            Instruction::LoadLocal(0),
+            Instruction::Dup(0),
+            Instruction::Add,
+            Instruction::Ret,
+        ]
+    );
+
+    let debug_info = program.debug_info.unwrap();
+    assert_eq!(debug_info.lines, vec![2, 3, 4, 2, 3, 4, 5]);
+    assert_eq!(debug_info.files, vec![0, 0, 0, 2, 2, 2, 2]);
+    assert_eq!(
+        debug_info.sources,
+        vec![
+            SourceFile {
+                name: path.display().to_string(),
+                included_at: 0,
+            },
+            SourceFile {
+                name: dir.join("lib/consts.ksm").display().to_string(),
+                included_at: 1,
+            },
+            SourceFile {
+                name: dir.join("lib/twice.ksm").display().to_string(),
+                included_at: 5,
+            },
+        ]
+    );
+}
+
+#[test]
+fn given_an_include_cycle_it_should_return_an_error() {
+    let dir = write_files(
+        "cycle",
+        &[
+            ("a.ksm", "push 1\n.include \"b.ksm\""),
+            ("b.ksm", "halt\n.include \"a.ksm\""),
+        ],
+    );
+    let a = dir.join("a.ksm");
+    let b = dir.join("b.ksm");
+
+    let diagnostics = assemble_file(&std::fs::read_to_string(&a).unwrap(), &a).unwrap_err();
+
+    let canonical = |path: &PathBuf| path.canonicalize().unwrap().display().to_string();
+    assert_eq!(
+        diagnostics,
+        vec![Diagnostic::new(
+            Span { line: 2, column: 1 },
+            format!(
+                "include cycle: {} -> {} -> {}",
+                canonical(&a),
+                canonical(&b),
+                canonical(&a)
+            )
+        )
+        .in_file(Some(&b.display().to_string()))]
+    );
+}
+
+#[test]
+fn given_a_missing_include_it_should_name_the_including_file() {
+    let dir = write_files("missing", &[("main.ksm", "halt\n.include \"nope.ksm\"")]);
+    let path = dir.join("main.ksm");
+
+    let diagnostics = assemble_file(&std::fs::read_to_string(&path).unwrap(), &path).unwrap_err();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0]
+        .to_string()
+        .starts_with(&format!("{}:2:1: could not include", path.display())));
+}
+
+#[test]
+fn given_a_macro_it_should_expand_it_with_its_arguments() {
+    let source = "
+        .macro countdown from step
+        push from
+    again:
+        push step
+        sub
+        dup 0
+        jmpif again
+        .endmacro
+
+    start:
+        countdown 3 1
+        countdown 10 2
+        jmp start
+    ";
+
+    let program = assemble(source).unwrap();
+
+    assert_eq!(
+        program.instructions,
+        vec![
+            Instruction::Push(3),
+            Instruction::Push(1),
+            Instruction::Sub,
+            Instruction::Dup(0),
+            Instruction::JmpIf(1),
+            Instruction::Push(10),
+            Instruction::Push(2),
+            Instruction::Sub,
+            Instruction::Dup(0),
+            Instruction::JmpIf(6),
+            Instruction::Jmp(0),
+        ]
+    );
+}
+
+#[test]
+fn given_nested_macros_it_should_expand_them_all() {
+    let source = "
+        .macro sum_top
+        dup 1
+        dup 1
+        add
+        .endmacro
+
+        .macro fib_step label
+        sum_top
+        jmp label
+        .endmacro
+
+        push 0
+        push 1
+    loop:
+        fib_step loop
+    ";
+
+    let program = assemble(source).unwrap();
+
+    assert_eq!(
+        program.instructions,
+        vec![
+            Instruction::Push(0),
+            Instruction::Push(1),
+            Instruction::Dup(1),
+            Instruction::Dup(1),
+            Instruction::Add,
+            Instruction::Jmp(2),
+        ]
+    );
+}
+
+#[test]
+fn given_an_error_inside_a_macro_it_should_point_to_the_use_and_the_definition() {
+    let source = ".macro twice value\npush value\npush value\n.endmacro\nhalt\ntwice yes";
+
+    let diagnostics: Vec<String> = assemble(source)
+        .unwrap_err()
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            "6:1: undefined constant `yes`\n  2:6: note: in macro `twice`",
+            "6:1: undefined constant `yes`\n  3:6: note: in macro `twice`",
+        ]
+    );
+}
+
+#[test]
+fn given_misused_macros_it_should_return_errors() {
+    let source = ".macro push\n.endmacro\n.macro loop\nloop\n.endmacro\n\
+                  .macro one a\n.endmacro\n.macro one\n.endmacro\none\nloop\n.endmacro\n\
+                  .macro open";
+
+    let diagnostics: Vec<String> = assemble(source)
+        .unwrap_err()
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            "1:1: macro `push` would shadow an instruction",
+            "8:1: macro `one` is already defined\n  6:1: note: macro `one` is defined here",
+            "10:1: macro `one` expects 1 argument(s), got 0\n  6:1: note: macro `one` is defined here",
+            "11:1: macro `loop` expands itself\n  4:1: note: in macro `loop`",
+            "12:1: `.endmacro` without `.macro`",
+            "13:1: macro `open` has no `.endmacro`",
+        ]
+    );
+}
+
+#[test]
+fn given_comparison_logic_and_stack_mnemonics_it_should_assemble_them() {
+    let source = "lt\ngt\nle\nge\nne\nnot\nand\nor\nmod\nneg\npop\nswap\nover\nrot\n\
+                  end:\njmpifnot end";
+
+    assert_eq!(
+        assemble(source).unwrap().instructions,
+        vec![
+            Instruction::Lt,
+            Instruction::Gt,
+            Instruction::Le,
+            Instruction::Ge,
+            Instruction::Ne,
+            Instruction::Not,
+            Instruction::And,
+            Instruction::Or,
+            Instruction::Mod,
+            Instruction::Neg,
+            Instruction::Pop,
+            Instruction::Swap,
+            Instruction::Over,
+            Instruction::Rot,
+            Instruction::JmpIfNot(14),
+        ]
+    );
+}
+
+#[test]
+fn given_array_and_closure_mnemonics_it_should_assemble_them() {
+    let source = "newarray 2\narrayget\narrayset\narraylen\nconcat\n\
+                  body:\nclosure body 1\ncallclosure 2";
+
+    assert_eq!(
+        assemble(source).unwrap().instructions,
+        vec![
+            Instruction::NewArray(2),
+            Instruction::ArrayGet,
+            Instruction::ArraySet,
+            Instruction::ArrayLen,
+            Instruction::Concat,
+            Instruction::Closure(5, 1),
+            Instruction::CallClosure(2),
+        ]
+    );
+}
+
+#[test]
+fn given_natives_it_should_resolve_them_by_name_or_id() {
+    let source = ".const SUM 4\ncallnative print 1\ncallnative random 0\ncallnative SUM 2\n\
+                  callnative 7 0";
+
+    assert_eq!(
+        assemble(source).unwrap().instructions,
+        vec![
+            Instruction::CallNative(0, 1),
+            Instruction::CallNative(3, 0),
+            Instruction::CallNative(4, 2),
+            Instruction::CallNative(7, 0),
+        ]
+    );
+    assert_eq!(
+        disassemble(&assemble(source).unwrap().instructions),
+        "    callnative print 1\n    callnative random 0\n    callnative 4 2\n    \
+         callnative 7 0\n"
+    );
+    assert_eq!(
+        assemble("callnative sum 2"),
+        Err(vec![Diagnostic::new(
+            Span {
+                line: 1,
+                column: 12
+            },
+            "undefined native `sum`"
+        )])
+    );
+}
+
+#[test]
+fn given_a_native_table_it_should_call_the_natives_by_their_names() {
+    fn sum(_vm: &mut Kvm, args: &[Value]) -> Result<Value, KvmError> {
+        Ok(Value::Int(args.iter().fold(0, |sum, arg| match arg {
+            Value::Int(n) => sum + n,
+            _ => sum,
+        })))
+    }
+
+    let mut vm = Kvm::new();
+    vm.register_native("sum", sum);
+    let natives: Vec<&str> = vm.get_natives().iter().map(|n| n.name.as_str()).collect();
+
+    let program =
+        assemble_with_natives("push 2\npush 3\ncallnative sum 2\nhalt", None, &natives).unwrap();
+    assert_eq!(program.instructions[2], Instruction::CallNative(4, 2));
+
+    vm.load_program(program);
+    vm.execute_program().unwrap();
+    assert_eq!(vm.get_stack(), [Value::Int(5)]);
+
+    assert_eq!(
+        assemble_with_natives("callnative print 1", None, &["sum"]),
+        Err(vec![Diagnostic::new(
+            Span {
+                line: 1,
+                column: 12
+            },
+            "undefined native `print`"
+        )])
+    );
+}
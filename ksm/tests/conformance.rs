@@ -0,0 +1,89 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ksm::assembler::assemble_file;
+use kvm::{optimize_program, KvmBuilder};
+
+/// Collects what the program prints.
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Assembles and runs `path`, feeding it the sibling `.in` file, returning
+/// what it printed followed by the error it stopped with, if any.
+fn run(path: &Path, optimize: bool) -> String {
+    let source = std::fs::read_to_string(path).unwrap();
+    let program = match assemble_file(&source, path) {
+        Ok(program) => program,
+        Err(diagnostics) => panic!("{} does not assemble: {:?}", path.display(), diagnostics),
+    };
+
+    let program = match optimize {
+        true => optimize_program(&program),
+        false => program,
+    };
+
+    let input = std::fs::read(path.with_extension("in")).unwrap_or_default();
+    let output = SharedOutput::default();
+    let mut vm = KvmBuilder::new()
+        .fuel(100_000)
+        .output(output.clone())
+        .input(std::io::Cursor::new(input))
+        .build();
+    vm.load_program(program);
+
+    let result = vm.execute_program();
+
+    let mut printed = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    if let Err(err) = result {
+        printed.push_str(&format!("error: {}\n", err));
+    }
+
+    printed
+}
+
+/// Every `tests/conformance/*.ksm` program has to print its `.out` file, with
+/// or without `ksm -O`, see `kvm/SPEC.md` for the semantics they pin down.
+#[test]
+fn given_the_conformance_programs_they_should_print_the_expected_output() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance");
+    let mut programs: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ksm"))
+        .collect();
+    programs.sort();
+
+    assert!(!programs.is_empty());
+
+    let failures: Vec<String> = programs
+        .iter()
+        .flat_map(|path| [(path, false), (path, true)])
+        .filter_map(|(path, optimize)| {
+            let expected = std::fs::read_to_string(path.with_extension("out")).unwrap();
+            let actual = run(path, optimize);
+
+            (actual != expected).then(|| {
+                format!(
+                    "{}{}:\nexpected:\n{}actual:\n{}",
+                    path.display(),
+                    if optimize { " (optimized)" } else { "" },
+                    expected,
+                    actual
+                )
+            })
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,71 @@
+use ksm::{assembler::assemble, disassembler::disassemble_program, listing::listing};
+use kvm::{strategy::instruction_with_targets, Instruction, Program};
+use proptest::prelude::*;
+
+#[test]
+fn given_a_program_it_should_show_the_index_offset_and_bytes_of_every_instruction() {
+    let mut program = Program::new(vec![
+        Instruction::PushStr("say \"hi\"\n".to_string()),
+        Instruction::Call(3, 1),
+        Instruction::Halt,
+        Instruction::Print,
+        Instruction::Ret,
+    ]);
+    program.entry = 1;
+
+    assert_eq!(
+        disassemble_program(&program),
+        "\
+.entry L0
+/*    0  0x0020  10 00 00 00 00             */    pushstr \"say \\\"hi\\\"\\n\"
+L0:
+/*    1  0x0025  13 03 00 00 00 01 00 00 00 */    call L1 1
+/*    2  0x002e  00                         */    halt
+L1:
+/*    3  0x002f  11                         */    print
+/*    4  0x0030  14                         */    ret
+"
+    );
+}
+
+#[test]
+fn given_a_source_it_should_list_it_next_to_its_bytecode() {
+    let source = ".macro twice\ndup 0\nadd\n.endmacro\n\npush 21 /* half */\ntwice\nhalt";
+    let program = assemble(source).unwrap();
+
+    assert_eq!(
+        listing(source, &program),
+        "\
+addr  offset  bytes                       line  source
+                                             1  .macro twice
+                                             2  dup 0
+                                             3  add
+                                             4  .endmacro
+                                             5
+   0  0x000f  06 15 00 00 00                 6  push 21 /* half */
+   1  0x0014  09 00 00 00 00                 7  twice
+   2  0x0019  01
+   3  0x001a  00                             8  halt
+"
+    );
+}
+
+fn target() -> impl Strategy<Value = u32> + Clone {
+    prop_oneof![0u32..16, any::<u32>()]
+}
+
+proptest! {
+    #[test]
+    fn given_a_disassembled_program_it_should_reassemble_to_the_same_bytes(
+        instructions in prop::collection::vec(instruction_with_targets(target()), 0..16),
+        entry in target(),
+    ) {
+        let mut program = Program::new(instructions);
+        program.entry = entry;
+
+        let mut reassembled = assemble(&disassemble_program(&program)).unwrap();
+        reassembled.debug_info = None;
+
+        prop_assert_eq!(reassembled.to_bytes(), program.to_bytes());
+    }
+}
@@ -0,0 +1,173 @@
+use ksm::{
+    lexer::Lexer,
+    token::{Span, Token},
+};
+
+fn tokens(source: &str) -> Vec<(Token, Span)> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::new();
+
+    while let Some(token) = lexer.next() {
+        tokens.push((token.unwrap(), lexer.span()));
+    }
+
+    tokens
+}
+
+#[test]
+fn given_a_source_it_should_return_the_tokens_with_their_position() {
+    let source = "loop: push -0x10 /* next */\n  pushstr \"a b\"\n.entry main\njmp loop";
+
+    assert_eq!(
+        tokens(source),
+        vec![
+            (
+                Token::Label("loop".to_string()),
+                Span { line: 1, column: 1 }
+            ),
+            (
+                Token::Identifier("push".to_string()),
+                Span { line: 1, column: 7 }
+            ),
+            (
+                Token::Number(-16),
+                Span {
+                    line: 1,
+                    column: 12
+                }
+            ),
+            (
+                Token::Comment("next".to_string()),
+                Span {
+                    line: 1,
+                    column: 18
+                }
+            ),
+            (
+                Token::Newline,
+                Span {
+                    line: 1,
+                    column: 28
+                }
+            ),
+            (
+                Token::Identifier("pushstr".to_string()),
+                Span { line: 2, column: 3 }
+            ),
+            (
+                Token::Str("a b".to_string()),
+                Span {
+                    line: 2,
+                    column: 11
+                }
+            ),
+            (
+                Token::Newline,
+                Span {
+                    line: 2,
+                    column: 16
+                }
+            ),
+            (
+                Token::Directive("entry".to_string()),
+                Span { line: 3, column: 1 }
+            ),
+            (
+                Token::Identifier("main".to_string()),
+                Span { line: 3, column: 8 }
+            ),
+            (
+                Token::Newline,
+                Span {
+                    line: 3,
+                    column: 12
+                }
+            ),
+            (
+                Token::Identifier("jmp".to_string()),
+                Span { line: 4, column: 1 }
+            ),
+            (
+                Token::Identifier("loop".to_string()),
+                Span { line: 4, column: 5 }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn given_invalid_characters_it_should_return_an_error_and_keep_going() {
+    let mut lexer = Lexer::new("@ 12ab 7");
+
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().to_string(),
+        "1:1: unexpected character `@`"
+    );
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().to_string(),
+        "1:3: invalid number `12ab`"
+    );
+    assert_eq!(lexer.next().unwrap(), Ok(Token::Number(7)));
+    assert_eq!(lexer.next(), None);
+}
+
+#[test]
+fn given_escapes_in_a_string_it_should_replace_them() {
+    let mut lexer = Lexer::new(r#""say \"hi\"\n\tc:\\ \r" "\q""#);
+
+    assert_eq!(
+        lexer.next().unwrap(),
+        Ok(Token::Str("say \"hi\"\n\tc:\\ \r".to_string()))
+    );
+    assert_eq!(
+        lexer.next().unwrap().unwrap_err().to_string(),
+        "1:25: unknown escape `\\q`"
+    );
+}
+
+#[test]
+fn given_non_ascii_text_it_should_count_columns_in_chars() {
+    let source = "pushstr \"héllo ✓\" /* ünï */ push\n  ✓";
+
+    let spans: Vec<Span> = {
+        let mut lexer = Lexer::new(source);
+        std::iter::from_fn(|| lexer.next().map(|_| lexer.span())).collect()
+    };
+
+    assert_eq!(
+        spans,
+        vec![
+            Span { line: 1, column: 1 },
+            Span { line: 1, column: 9 },
+            Span {
+                line: 1,
+                column: 19
+            },
+            Span {
+                line: 1,
+                column: 29
+            },
+            Span {
+                line: 1,
+                column: 33
+            },
+            Span { line: 2, column: 3 },
+        ]
+    );
+}
+
+#[test]
+fn given_a_long_source_it_should_track_the_position_of_the_last_token() {
+    let source = "push 1\n".repeat(50_000) + "halt";
+
+    let (token, span) = tokens(&source).pop().unwrap();
+
+    assert_eq!(token, Token::Identifier("halt".to_string()));
+    assert_eq!(
+        span,
+        Span {
+            line: 50_001,
+            column: 1
+        }
+    );
+}
@@ -0,0 +1,87 @@
+use ksm::{
+    ast::{Label, Line, Operand, OperandValue, Statement, StatementKind},
+    lexer::Lexer,
+    parser::Parser,
+    token::Span,
+};
+
+fn parse(source: &str) -> (Vec<Line>, Vec<String>) {
+    let mut parser = Parser::new(Lexer::new(source));
+    let lines = parser.parse_program();
+    let errors = parser.errors.iter().map(|err| err.to_string()).collect();
+
+    (lines, errors)
+}
+
+#[test]
+fn given_lines_it_should_parse_labels_statements_and_operands() {
+    let (lines, errors) = parse("/* start */\n\nmain:\nloop: call square 1\n.entry main\n");
+
+    assert!(errors.is_empty());
+    assert_eq!(
+        lines,
+        vec![
+            Line {
+                label: Some(Label {
+                    name: "main".to_string(),
+                    span: Span { line: 3, column: 1 },
+                }),
+                statement: None,
+            },
+            Line {
+                label: Some(Label {
+                    name: "loop".to_string(),
+                    span: Span { line: 4, column: 1 },
+                }),
+                statement: Some(Statement {
+                    kind: StatementKind::Instruction,
+                    name: "call".to_string(),
+                    operands: vec![
+                        Operand {
+                            value: OperandValue::Identifier("square".to_string()),
+                            span: Span {
+                                line: 4,
+                                column: 12
+                            },
+                        },
+                        Operand {
+                            value: OperandValue::Number(1),
+                            span: Span {
+                                line: 4,
+                                column: 19
+                            },
+                        },
+                    ],
+                    span: Span { line: 4, column: 7 },
+                }),
+            },
+            Line {
+                label: None,
+                statement: Some(Statement {
+                    kind: StatementKind::Directive,
+                    name: "entry".to_string(),
+                    operands: vec![Operand {
+                        value: OperandValue::Identifier("main".to_string()),
+                        span: Span { line: 5, column: 8 },
+                    }],
+                    span: Span { line: 5, column: 1 },
+                }),
+            },
+        ]
+    );
+}
+
+#[test]
+fn given_malformed_lines_it_should_report_one_error_per_line() {
+    let (lines, errors) = parse("12 push\npush 1 done:\npush @ @\nhalt");
+
+    assert_eq!(
+        errors,
+        vec![
+            "1:1: expected an instruction, found number `12`",
+            "2:8: expected an operand, found label `done:`",
+            "3:6: unexpected character `@`",
+        ]
+    );
+    assert_eq!(lines.len(), 1);
+}
@@ -0,0 +1,103 @@
+use std::io::{BufRead, BufReader, Read, Write};
+
+use crate::kvm::Kvm;
+
+/// Limits of a `Kvm` instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KvmConfig {
+    /// Maximum number of values on the operand stack, checked as values are
+    /// pushed rather than allocated up front.
+    pub stack_size: usize,
+    /// Maximum number of nested calls, checked the same way.
+    pub max_call_depth: usize,
+    /// Instructions that can be executed before the program is stopped,
+    /// unlimited if `None`.
+    pub fuel: Option<u64>,
+    /// Bytes the objects on the heap can take before the program fails with
+    /// `KvmError::OutOfMemory`, garbage being collected first.
+    pub heap_size: usize,
+    /// Whether programs go through `verify` before they are executed.
+    pub verify: bool,
+}
+
+impl Default for KvmConfig {
+    fn default() -> Self {
+        KvmConfig {
+            stack_size: 1024,
+            max_call_depth: 256,
+            fuel: None,
+            heap_size: 16 * 1024 * 1024,
+            verify: true,
+        }
+    }
+}
+
+/// Builds a `Kvm`, by default with `KvmConfig::default()` writing to stdout and
+/// reading from stdin.
+pub struct KvmBuilder {
+    config: KvmConfig,
+    output: Box<dyn Write + Send>,
+    input: Box<dyn BufRead + Send>,
+}
+
+impl Default for KvmBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KvmBuilder {
+    pub fn new() -> Self {
+        KvmBuilder {
+            config: KvmConfig::default(),
+            output: Box::new(std::io::stdout()),
+            input: Box::new(BufReader::new(std::io::stdin())),
+        }
+    }
+
+    pub fn config(mut self, config: KvmConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.config.stack_size = stack_size;
+        self
+    }
+
+    pub fn max_call_depth(mut self, max_call_depth: usize) -> Self {
+        self.config.max_call_depth = max_call_depth;
+        self
+    }
+
+    pub fn fuel(mut self, fuel: u64) -> Self {
+        self.config.fuel = Some(fuel);
+        self
+    }
+
+    pub fn heap_size(mut self, heap_size: usize) -> Self {
+        self.config.heap_size = heap_size;
+        self
+    }
+
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.config.verify = verify;
+        self
+    }
+
+    /// Where `print` writes to.
+    pub fn output(mut self, output: impl Write + Send + 'static) -> Self {
+        self.output = Box::new(output);
+        self
+    }
+
+    /// Where `input` reads lines from.
+    pub fn input(mut self, input: impl Read + Send + 'static) -> Self {
+        self.input = Box::new(BufReader::new(input));
+        self
+    }
+
+    pub fn build(self) -> Kvm {
+        Kvm::with_config(self.config, self.output, self.input)
+    }
+}
@@ -0,0 +1,776 @@
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+use crate::{error::KvmError, instruction::Instruction};
+
+/// Every .kvm file starts with these bytes.
+pub const MAGIC: [u8; 4] = *b"KVM\0";
+pub const FORMAT_VERSION: u16 = 1;
+
+const FLAG_DEBUG_INFO: u8 = 0x1;
+const FLAG_ENTRY: u8 = 0x2;
+
+/// Strings referenced by the code section. Each string is stored once and
+/// instructions refer to it by index.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConstantTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u32>,
+}
+
+impl ConstantTable {
+    pub fn new() -> Self {
+        ConstantTable {
+            strings: Vec::new(),
+            indices: HashMap::new(),
+        }
+    }
+
+    pub fn intern(&mut self, s: &str) -> u32 {
+        if let Some(idx) = self.indices.get(s) {
+            return *idx;
+        }
+
+        let idx = self.strings.len() as u32;
+        self.strings.push(s.to_string());
+        self.indices.insert(s.to_string(), idx);
+        idx
+    }
+
+    pub fn get(&self, idx: u32) -> Option<&str> {
+        self.strings.get(idx as usize).map(|s| s.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// Source file and line of every instruction of the code section, and the
+/// labels naming them.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DebugInfo {
+    /// Line of every instruction in the file it comes from.
+    pub lines: Vec<u32>,
+    /// Index in `sources` of the file every instruction comes from.
+    pub files: Vec<u32>,
+    /// The assembled file, then one entry for every `.include`.
+    pub sources: Vec<SourceFile>,
+    pub labels: BTreeMap<String, u32>,
+}
+
+/// A file a program was assembled from.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SourceFile {
+    /// Path of the file, empty for a source that wasn't read from a file.
+    pub name: String,
+    /// Line of the assembled file whose `.include` brought the file in,
+    /// directly or through other included files, 0 for the assembled file.
+    pub included_at: u32,
+}
+
+impl DebugInfo {
+    /// Debug info of a program assembled from a single unnamed source.
+    pub fn new(lines: Vec<u32>) -> Self {
+        DebugInfo {
+            files: vec![0; lines.len()],
+            lines,
+            sources: vec![SourceFile::default()],
+            labels: BTreeMap::new(),
+        }
+    }
+
+    /// Source line of the instruction at `ip`, in the file it comes from.
+    pub fn line(&self, ip: usize) -> Option<u32> {
+        self.lines.get(ip).copied()
+    }
+
+    /// File the instruction at `ip` comes from.
+    pub fn file(&self, ip: usize) -> Option<&SourceFile> {
+        self.files
+            .get(ip)
+            .and_then(|file| self.sources.get(*file as usize))
+    }
+
+    /// Line of the assembled file the instruction at `ip` comes from, which
+    /// is the line of the `.include` for instructions of included files.
+    pub fn assembled_line(&self, ip: usize) -> Option<u32> {
+        match self.files.get(ip) {
+            Some(0) => self.line(ip),
+            _ => self.file(ip).map(|file| file.included_at),
+        }
+    }
+
+    /// First instruction assembled from `line` of the assembled file.
+    pub fn instruction_at_line(&self, line: u32) -> Option<usize> {
+        self.lines
+            .iter()
+            .zip(&self.files)
+            .position(|(l, file)| *l == line && *file == 0)
+    }
+
+    /// Address of the instruction following `label`.
+    pub fn label(&self, label: &str) -> Option<usize> {
+        self.labels.get(label).map(|addr| *addr as usize)
+    }
+}
+
+/// A program as stored in a .kvm file:
+///
+/// ```text
+/// magic      b"KVM\0"
+/// version    u16
+/// flags      u8, bit 0 set when the debug section is present, bit 1 when
+///            the entry point follows
+/// entry      u32 address of the first instruction to run, only when set
+/// constants  u32 count, then a u32 length and the utf-8 bytes of each string
+/// code       u32 length in bytes, then the encoded instructions
+/// debug      u32 count, which must be the number of instructions, then the
+///            u32 source line and u32 file index of every instruction,
+///            followed by a u32 count of files, then the u32 length and
+///            utf-8 bytes of each path and the u32 line it is included at,
+///            and a u32 count of labels, then the u32 length and utf-8
+///            bytes of each name and the u32 address it names
+/// ```
+///
+/// All integers are little endian.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+    pub debug_info: Option<DebugInfo>,
+    /// Address of the first instruction to run.
+    pub entry: u32,
+}
+
+impl Program {
+    pub fn new(instructions: Vec<Instruction>) -> Self {
+        Program {
+            instructions,
+            debug_info: None,
+            entry: 0,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode().bytes
+    }
+
+    /// Encodes the program as a .kvm file, keeping track of where every
+    /// instruction ends up in it.
+    pub fn encode(&self) -> Encoded {
+        let mut constants = ConstantTable::new();
+        let mut code = Vec::new();
+        let mut instructions = Vec::with_capacity(self.instructions.len());
+
+        for inst in &self.instructions {
+            let start = code.len();
+            code.extend(inst.as_bytes(&mut constants));
+            instructions.push(start..code.len());
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend(MAGIC);
+        bytes.extend(FORMAT_VERSION.to_le_bytes());
+
+        let mut flags = 0;
+        if self.debug_info.is_some() {
+            flags |= FLAG_DEBUG_INFO;
+        }
+        if self.entry != 0 {
+            flags |= FLAG_ENTRY;
+        }
+        bytes.push(flags);
+
+        if self.entry != 0 {
+            bytes.extend(self.entry.to_le_bytes());
+        }
+
+        bytes.extend((constants.len() as u32).to_le_bytes());
+        for s in &constants.strings {
+            bytes.extend((s.len() as u32).to_le_bytes());
+            bytes.extend(s.as_bytes());
+        }
+
+        bytes.extend((code.len() as u32).to_le_bytes());
+        let code_offset = bytes.len();
+        bytes.extend(code);
+
+        if let Some(debug_info) = &self.debug_info {
+            bytes.extend((debug_info.lines.len() as u32).to_le_bytes());
+            for (line, file) in debug_info.lines.iter().zip(&debug_info.files) {
+                bytes.extend(line.to_le_bytes());
+                bytes.extend(file.to_le_bytes());
+            }
+
+            bytes.extend((debug_info.sources.len() as u32).to_le_bytes());
+            for source in &debug_info.sources {
+                bytes.extend((source.name.len() as u32).to_le_bytes());
+                bytes.extend(source.name.as_bytes());
+                bytes.extend(source.included_at.to_le_bytes());
+            }
+
+            bytes.extend((debug_info.labels.len() as u32).to_le_bytes());
+            for (label, addr) in &debug_info.labels {
+                bytes.extend((label.len() as u32).to_le_bytes());
+                bytes.extend(label.as_bytes());
+                bytes.extend(addr.to_le_bytes());
+            }
+        }
+
+        Encoded {
+            bytes,
+            instructions: instructions
+                .into_iter()
+                .map(|range| range.start + code_offset..range.end + code_offset)
+                .collect(),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Program, KvmError> {
+        let mut reader = Reader {
+            bytes,
+            offset: 0,
+            end: bytes.len(),
+        };
+
+        if reader.read(MAGIC.len())? != MAGIC {
+            return Err(KvmError::InvalidMagic);
+        }
+
+        let version = u16::from_le_bytes(reader.read_array()?);
+        if version != FORMAT_VERSION {
+            return Err(KvmError::UnsupportedVersion(version));
+        }
+
+        let flags = reader.read_u8()?;
+        let entry = match flags & FLAG_ENTRY {
+            0 => 0,
+            _ => reader.read_u32()?,
+        };
+
+        let mut constants = ConstantTable::new();
+        for _ in 0..reader.read_u32()? {
+            constants.strings.push(reader.read_str()?);
+        }
+
+        let code_len = reader.read_u32()? as usize;
+        let mut code = Reader {
+            bytes,
+            offset: reader.offset,
+            end: reader.offset.saturating_add(code_len),
+        };
+        if code.end > bytes.len() {
+            return Err(KvmError::TruncatedOperand {
+                offset: bytes.len(),
+            });
+        }
+        reader.offset = code.end;
+
+        let mut instructions = Vec::new();
+        while code.offset < code.end {
+            instructions.push(code.read_instruction(&constants)?);
+        }
+
+        let debug_info = match flags & FLAG_DEBUG_INFO {
+            0 => None,
+            _ => Some(reader.read_debug_info()?),
+        };
+
+        if let Some(debug_info) = &debug_info {
+            if debug_info.lines.len() != instructions.len() {
+                return Err(KvmError::InvalidDebugInfo {
+                    lines: debug_info.lines.len(),
+                    instructions: instructions.len(),
+                });
+            }
+            if let Some(ip) = debug_info
+                .files
+                .iter()
+                .position(|file| *file as usize >= debug_info.sources.len())
+            {
+                return Err(KvmError::InvalidSourceFile {
+                    ip,
+                    file: debug_info.files[ip],
+                });
+            }
+        }
+
+        Ok(Program {
+            instructions,
+            debug_info,
+            entry,
+        })
+    }
+}
+
+/// A program encoded as a .kvm file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Encoded {
+    pub bytes: Vec<u8>,
+    /// Where the bytes of every instruction are in `bytes`.
+    pub instructions: Vec<Range<usize>>,
+}
+
+/// Operands an instruction can be followed by in the code section.
+#[derive(Debug, Clone, Copy)]
+enum OperandKind {
+    Bool,
+    Int,
+    Word,
+    Constant,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Bool(bool),
+    Int(i32),
+    Word(u32),
+    Str(String),
+}
+
+impl Operand {
+    fn bool(&self) -> bool {
+        match self {
+            Operand::Bool(b) => *b,
+            operand => unreachable!("expected a bool operand, got {:?}", operand),
+        }
+    }
+
+    fn int(&self) -> i32 {
+        match self {
+            Operand::Int(n) => *n,
+            operand => unreachable!("expected an int operand, got {:?}", operand),
+        }
+    }
+
+    fn word(&self) -> u32 {
+        match self {
+            Operand::Word(n) => *n,
+            operand => unreachable!("expected a word operand, got {:?}", operand),
+        }
+    }
+}
+
+/// How to decode the instruction with the same upcode as `prototype`.
+struct Decoder {
+    prototype: Instruction,
+    operands: &'static [OperandKind],
+    build: fn(Vec<Operand>) -> Instruction,
+}
+
+static DECODERS: [Decoder; 44] = [
+    Decoder {
+        prototype: Instruction::Halt,
+        operands: &[],
+        build: |_| Instruction::Halt,
+    },
+    Decoder {
+        prototype: Instruction::Add,
+        operands: &[],
+        build: |_| Instruction::Add,
+    },
+    Decoder {
+        prototype: Instruction::Sub,
+        operands: &[],
+        build: |_| Instruction::Sub,
+    },
+    Decoder {
+        prototype: Instruction::Div,
+        operands: &[],
+        build: |_| Instruction::Div,
+    },
+    Decoder {
+        prototype: Instruction::Mul,
+        operands: &[],
+        build: |_| Instruction::Mul,
+    },
+    Decoder {
+        prototype: Instruction::Eq,
+        operands: &[],
+        build: |_| Instruction::Eq,
+    },
+    Decoder {
+        prototype: Instruction::Push(0),
+        operands: &[OperandKind::Int],
+        build: |operands| Instruction::Push(operands[0].int()),
+    },
+    Decoder {
+        prototype: Instruction::Jmp(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::Jmp(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::JmpIf(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::JmpIf(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::Dup(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::Dup(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::PushStr(String::new()),
+        operands: &[OperandKind::Constant],
+        build: |mut operands| match operands.pop() {
+            Some(Operand::Str(s)) => Instruction::PushStr(s),
+            operand => unreachable!("expected a string operand, got {:?}", operand),
+        },
+    },
+    Decoder {
+        prototype: Instruction::PushBool(false),
+        operands: &[OperandKind::Bool],
+        build: |operands| Instruction::PushBool(operands[0].bool()),
+    },
+    Decoder {
+        prototype: Instruction::PushNull,
+        operands: &[],
+        build: |_| Instruction::PushNull,
+    },
+    Decoder {
+        prototype: Instruction::Print,
+        operands: &[],
+        build: |_| Instruction::Print,
+    },
+    Decoder {
+        prototype: Instruction::Input,
+        operands: &[],
+        build: |_| Instruction::Input,
+    },
+    Decoder {
+        prototype: Instruction::Call(0, 0),
+        operands: &[OperandKind::Word, OperandKind::Word],
+        build: |operands| Instruction::Call(operands[0].word(), operands[1].word()),
+    },
+    Decoder {
+        prototype: Instruction::Ret,
+        operands: &[],
+        build: |_| Instruction::Ret,
+    },
+    Decoder {
+        prototype: Instruction::LoadLocal(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::LoadLocal(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::StoreLocal(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::StoreLocal(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::LoadGlobal(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::LoadGlobal(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::StoreGlobal(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::StoreGlobal(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::Lt,
+        operands: &[],
+        build: |_| Instruction::Lt,
+    },
+    Decoder {
+        prototype: Instruction::Gt,
+        operands: &[],
+        build: |_| Instruction::Gt,
+    },
+    Decoder {
+        prototype: Instruction::Le,
+        operands: &[],
+        build: |_| Instruction::Le,
+    },
+    Decoder {
+        prototype: Instruction::Ge,
+        operands: &[],
+        build: |_| Instruction::Ge,
+    },
+    Decoder {
+        prototype: Instruction::Ne,
+        operands: &[],
+        build: |_| Instruction::Ne,
+    },
+    Decoder {
+        prototype: Instruction::Not,
+        operands: &[],
+        build: |_| Instruction::Not,
+    },
+    Decoder {
+        prototype: Instruction::And,
+        operands: &[],
+        build: |_| Instruction::And,
+    },
+    Decoder {
+        prototype: Instruction::Or,
+        operands: &[],
+        build: |_| Instruction::Or,
+    },
+    Decoder {
+        prototype: Instruction::Mod,
+        operands: &[],
+        build: |_| Instruction::Mod,
+    },
+    Decoder {
+        prototype: Instruction::Neg,
+        operands: &[],
+        build: |_| Instruction::Neg,
+    },
+    Decoder {
+        prototype: Instruction::Pop,
+        operands: &[],
+        build: |_| Instruction::Pop,
+    },
+    Decoder {
+        prototype: Instruction::Swap,
+        operands: &[],
+        build: |_| Instruction::Swap,
+    },
+    Decoder {
+        prototype: Instruction::Over,
+        operands: &[],
+        build: |_| Instruction::Over,
+    },
+    Decoder {
+        prototype: Instruction::Rot,
+        operands: &[],
+        build: |_| Instruction::Rot,
+    },
+    Decoder {
+        prototype: Instruction::JmpIfNot(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::JmpIfNot(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::NewArray(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::NewArray(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::ArrayGet,
+        operands: &[],
+        build: |_| Instruction::ArrayGet,
+    },
+    Decoder {
+        prototype: Instruction::ArraySet,
+        operands: &[],
+        build: |_| Instruction::ArraySet,
+    },
+    Decoder {
+        prototype: Instruction::ArrayLen,
+        operands: &[],
+        build: |_| Instruction::ArrayLen,
+    },
+    Decoder {
+        prototype: Instruction::Concat,
+        operands: &[],
+        build: |_| Instruction::Concat,
+    },
+    Decoder {
+        prototype: Instruction::Closure(0, 0),
+        operands: &[OperandKind::Word, OperandKind::Word],
+        build: |operands| Instruction::Closure(operands[0].word(), operands[1].word()),
+    },
+    Decoder {
+        prototype: Instruction::CallClosure(0),
+        operands: &[OperandKind::Word],
+        build: |operands| Instruction::CallClosure(operands[0].word()),
+    },
+    Decoder {
+        prototype: Instruction::CallNative(0, 0),
+        operands: &[OperandKind::Word, OperandKind::Word],
+        build: |operands| Instruction::CallNative(operands[0].word(), operands[1].word()),
+    },
+];
+
+impl Instruction {
+    fn operands(&self) -> Vec<Operand> {
+        match self {
+            Instruction::Halt
+            | Instruction::Add
+            | Instruction::Sub
+            | Instruction::Div
+            | Instruction::Mul
+            | Instruction::Eq
+            | Instruction::PushNull
+            | Instruction::Print
+            | Instruction::Input
+            | Instruction::Ret
+            | Instruction::Lt
+            | Instruction::Gt
+            | Instruction::Le
+            | Instruction::Ge
+            | Instruction::Ne
+            | Instruction::Not
+            | Instruction::And
+            | Instruction::Or
+            | Instruction::Mod
+            | Instruction::Neg
+            | Instruction::Pop
+            | Instruction::Swap
+            | Instruction::Over
+            | Instruction::Rot
+            | Instruction::ArrayGet
+            | Instruction::ArraySet
+            | Instruction::ArrayLen
+            | Instruction::Concat => vec![],
+            Instruction::Push(n) => vec![Operand::Int(*n)],
+            Instruction::PushBool(b) => vec![Operand::Bool(*b)],
+            Instruction::PushStr(s) => vec![Operand::Str(s.clone())],
+            Instruction::Call(addr, n)
+            | Instruction::Closure(addr, n)
+            | Instruction::CallNative(addr, n) => {
+                vec![Operand::Word(*addr), Operand::Word(*n)]
+            }
+            Instruction::Jmp(n)
+            | Instruction::JmpIf(n)
+            | Instruction::JmpIfNot(n)
+            | Instruction::Dup(n)
+            | Instruction::LoadLocal(n)
+            | Instruction::StoreLocal(n)
+            | Instruction::LoadGlobal(n)
+            | Instruction::StoreGlobal(n)
+            | Instruction::NewArray(n)
+            | Instruction::CallClosure(n) => vec![Operand::Word(*n)],
+        }
+    }
+
+    /// Encodes the instruction for the code section of a .kvm file, adding
+    /// its string operand to the given constant table.
+    pub fn as_bytes(&self, constants: &mut ConstantTable) -> Vec<u8> {
+        let mut bytes = vec![self.upcode()];
+
+        for operand in self.operands() {
+            match operand {
+                Operand::Bool(b) => bytes.push(b as u8),
+                Operand::Int(n) => bytes.extend(n.to_le_bytes()),
+                Operand::Word(n) => bytes.extend(n.to_le_bytes()),
+                Operand::Str(s) => bytes.extend(constants.intern(&s).to_le_bytes()),
+            }
+        }
+
+        bytes
+    }
+}
+
+/// Reads the bytes of a .kvm file up to `end`, reporting offsets from the
+/// start of the file.
+struct Reader<'b> {
+    bytes: &'b [u8],
+    offset: usize,
+    end: usize,
+}
+
+impl<'b> Reader<'b> {
+    fn read(&mut self, len: usize) -> Result<&'b [u8], KvmError> {
+        if self.end - self.offset < len {
+            return Err(KvmError::TruncatedOperand {
+                offset: self.offset,
+            });
+        }
+
+        let bytes = &self.bytes[self.offset..self.offset + len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], KvmError> {
+        Ok(self.read(N)?.try_into().expect("read returns N bytes"))
+    }
+
+    fn read_u8(&mut self) -> Result<u8, KvmError> {
+        Ok(self.read(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, KvmError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    fn read_str(&mut self) -> Result<String, KvmError> {
+        let len = self.read_u32()? as usize;
+        let offset = self.offset;
+
+        std::str::from_utf8(self.read(len)?)
+            .map(|s| s.to_string())
+            .map_err(|_| KvmError::InvalidUtf8 { offset })
+    }
+
+    fn read_debug_info(&mut self) -> Result<DebugInfo, KvmError> {
+        let mut lines = Vec::new();
+        let mut files = Vec::new();
+        for _ in 0..self.read_u32()? {
+            lines.push(self.read_u32()?);
+            files.push(self.read_u32()?);
+        }
+
+        let mut sources = Vec::new();
+        for _ in 0..self.read_u32()? {
+            sources.push(SourceFile {
+                name: self.read_str()?,
+                included_at: self.read_u32()?,
+            });
+        }
+
+        let mut labels = BTreeMap::new();
+        for _ in 0..self.read_u32()? {
+            labels.insert(self.read_str()?, self.read_u32()?);
+        }
+
+        Ok(DebugInfo {
+            lines,
+            files,
+            sources,
+            labels,
+        })
+    }
+
+    fn read_instruction(&mut self, constants: &ConstantTable) -> Result<Instruction, KvmError> {
+        let offset = self.offset;
+        let upcode = self.read_u8()?;
+
+        let decoder = DECODERS
+            .iter()
+            .find(|decoder| decoder.prototype.upcode() == upcode)
+            .ok_or(KvmError::UnknownOpcode { upcode, offset })?;
+
+        let operands = decoder
+            .operands
+            .iter()
+            .map(|kind| self.read_operand(*kind, constants))
+            .collect::<Result<_, _>>()?;
+
+        Ok((decoder.build)(operands))
+    }
+
+    fn read_operand(
+        &mut self,
+        kind: OperandKind,
+        constants: &ConstantTable,
+    ) -> Result<Operand, KvmError> {
+        Ok(match kind {
+            OperandKind::Bool => {
+                let offset = self.offset;
+                match self.read_u8()? {
+                    0 => Operand::Bool(false),
+                    1 => Operand::Bool(true),
+                    byte => return Err(KvmError::InvalidBool { byte, offset }),
+                }
+            }
+            OperandKind::Int => Operand::Int(i32::from_le_bytes(self.read_array()?)),
+            OperandKind::Word => Operand::Word(self.read_u32()?),
+            OperandKind::Constant => {
+                let offset = self.offset;
+                let idx = self.read_u32()?;
+                let s = constants
+                    .get(idx)
+                    .ok_or(KvmError::InvalidConstant { idx, offset })?;
+                Operand::Str(s.to_string())
+            }
+        })
+    }
+}
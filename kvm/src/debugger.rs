@@ -0,0 +1,280 @@
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+
+use crate::{bytecode::ConstantTable, error::KvmError, instruction::Instruction, kvm::Kvm};
+
+const HELP: &str = "\
+step, s [n]         execute the next n instructions (default 1)
+continue, c         run until a breakpoint or the program halts
+break, b <n>        break before the instruction at index n
+break, b line <n>   break before the first instruction of ksm line n
+break, b <label>    break before the instruction following a ksm label
+delete, d <n>       remove the breakpoint at index n
+breakpoints         list breakpoints
+list, l [n]         disassemble n instructions around ip (default 5)
+stack               print the stack
+frames              print the call frames
+globals             print the globals
+strings             print the string constants of the program
+quit, q             leave the debugger";
+
+/// Interactive debugger driving a `Kvm` one instruction at a time.
+pub struct Debugger {
+    vm: Kvm,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl Debugger {
+    /// Debugs the program loaded in `vm`, which is verified first unless
+    /// disabled in its config, as `Kvm::execute_program` would.
+    pub fn new(mut vm: Kvm) -> Result<Self, KvmError> {
+        vm.verify()?;
+
+        Ok(Debugger {
+            vm,
+            breakpoints: BTreeSet::new(),
+        })
+    }
+
+    pub fn vm(&self) -> &Kvm {
+        &self.vm
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<usize> {
+        &self.breakpoints
+    }
+
+    /// Reads commands from `input` until `quit` or the end of the input.
+    pub fn run(&mut self, input: impl BufRead, output: &mut impl Write) -> Result<(), KvmError> {
+        write!(output, "{}", self.location())?;
+        write!(output, "(kdb) ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            if !self.command(&line?, output)? {
+                break;
+            }
+            write!(output, "(kdb) ")?;
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Executes a single command, returning `false` once the user quits.
+    /// Errors raised by the program are reported to `output` instead of
+    /// ending the session, so its state can still be inspected.
+    pub fn command(&mut self, line: &str, output: &mut impl Write) -> Result<bool, KvmError> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            [] => {}
+            ["step" | "s"] => self.step(1, output)?,
+            ["step" | "s", n] => match n.parse() {
+                Ok(n) => self.step(n, output)?,
+                Err(_) => writeln!(output, "Invalid count: {}", n)?,
+            },
+            ["continue" | "c"] => self.resume(output)?,
+            ["break" | "b", "line", n] => match n.parse() {
+                Ok(n) => self.break_at_line(n, output)?,
+                Err(_) => writeln!(output, "Invalid line: {}", n)?,
+            },
+            ["break" | "b", n] => match n.parse() {
+                Ok(n) if n < self.vm.get_instructions().len() => {
+                    self.breakpoints.insert(n);
+                    writeln!(output, "Breakpoint at {}", n)?;
+                }
+                Ok(_) => writeln!(output, "Invalid instruction index: {}", n)?,
+                Err(_) => self.break_at_label(n, output)?,
+            },
+            ["delete" | "d", n] => match n.parse() {
+                Ok(n) if self.breakpoints.remove(&n) => {
+                    writeln!(output, "Deleted breakpoint at {}", n)?
+                }
+                _ => writeln!(output, "No breakpoint at {}", n)?,
+            },
+            ["breakpoints"] => {
+                if self.breakpoints.is_empty() {
+                    writeln!(output, "No breakpoints")?;
+                }
+                for ip in &self.breakpoints {
+                    writeln!(output, "{}", self.describe(*ip))?;
+                }
+            }
+            ["list" | "l"] => self.list(5, output)?,
+            ["list" | "l", n] => match n.parse() {
+                Ok(n) => self.list(n, output)?,
+                Err(_) => writeln!(output, "Invalid count: {}", n)?,
+            },
+            ["stack"] => {
+                if self.vm.get_stack().is_empty() {
+                    writeln!(output, "[Empty]")?;
+                }
+                for (i, value) in self.vm.get_stack().iter().enumerate().rev() {
+                    writeln!(output, "{:>4}: {}", i, self.vm.get_heap().display(value))?;
+                }
+            }
+            ["frames"] => {
+                if self.vm.get_frames().is_empty() {
+                    writeln!(output, "No active calls")?;
+                }
+                for (i, frame) in self.vm.get_frames().iter().enumerate().rev() {
+                    writeln!(
+                        output,
+                        "#{} return to {}, locals from stack slot {}",
+                        i, frame.return_address, frame.base
+                    )?;
+                }
+            }
+            ["globals"] => {
+                for (i, global) in self.vm.get_globals().iter().enumerate() {
+                    if let Some(value) = global {
+                        writeln!(output, "{}: {}", i, self.vm.get_heap().display(value))?;
+                    }
+                }
+            }
+            ["strings"] => {
+                let mut constants = ConstantTable::new();
+                for inst in self.vm.get_instructions() {
+                    if let Instruction::PushStr(s) = inst {
+                        constants.intern(s);
+                    }
+                }
+                for idx in 0..constants.len() as u32 {
+                    writeln!(
+                        output,
+                        "{}: {:?}",
+                        idx,
+                        constants.get(idx).unwrap_or_default()
+                    )?;
+                }
+            }
+            ["help" | "h"] => writeln!(output, "{}", HELP)?,
+            ["quit" | "q"] => return Ok(false),
+            _ => writeln!(output, "Unknown command: {}, try help", line.trim())?,
+        }
+
+        Ok(true)
+    }
+
+    fn step(&mut self, n: usize, output: &mut impl Write) -> Result<(), KvmError> {
+        for _ in 0..n {
+            if self.vm.is_halted() {
+                break;
+            }
+            if let Err(err) = self.vm.step() {
+                writeln!(output, "Error: {}", err)?;
+                break;
+            }
+        }
+
+        write!(output, "{}", self.location())?;
+        Ok(())
+    }
+
+    fn resume(&mut self, output: &mut impl Write) -> Result<(), KvmError> {
+        // always execute one instruction so continuing from a breakpoint
+        // does not stop on it again
+        loop {
+            if self.vm.is_halted() {
+                break;
+            }
+            if let Err(err) = self.vm.step() {
+                writeln!(output, "Error: {}", err)?;
+                break;
+            }
+            if self.breakpoints.contains(&self.vm.get_ip()) {
+                writeln!(output, "Breakpoint at {}", self.vm.get_ip())?;
+                break;
+            }
+        }
+
+        write!(output, "{}", self.location())?;
+        Ok(())
+    }
+
+    fn break_at_line(&mut self, line: u32, output: &mut impl Write) -> Result<(), KvmError> {
+        match self.vm.get_debug_info() {
+            None => writeln!(
+                output,
+                "The program has no debug info, rebuild it with ksm -g"
+            )?,
+            Some(debug_info) => match debug_info.instruction_at_line(line) {
+                Some(ip) => {
+                    self.breakpoints.insert(ip);
+                    writeln!(output, "Breakpoint at {}", ip)?;
+                }
+                None => writeln!(output, "No instruction on line {}", line)?,
+            },
+        }
+
+        Ok(())
+    }
+
+    fn break_at_label(&mut self, label: &str, output: &mut impl Write) -> Result<(), KvmError> {
+        let len = self.vm.get_instructions().len();
+
+        match self.vm.get_debug_info() {
+            None => writeln!(
+                output,
+                "The program has no debug info, rebuild it with ksm -g"
+            )?,
+            Some(debug_info) => match debug_info.label(label) {
+                Some(ip) if ip < len => {
+                    self.breakpoints.insert(ip);
+                    writeln!(output, "Breakpoint at {}", ip)?;
+                }
+                _ => writeln!(output, "No instruction after label {}", label)?,
+            },
+        }
+
+        Ok(())
+    }
+
+    fn list(&self, n: usize, output: &mut impl Write) -> Result<(), KvmError> {
+        let ip = self.vm.get_ip();
+        let start = ip.saturating_sub(n / 2);
+        let end = (start + n).min(self.vm.get_instructions().len());
+
+        for i in start..end {
+            let marker = if i == ip { "=>" } else { "  " };
+            writeln!(output, "{} {}", marker, self.describe(i))?;
+        }
+
+        Ok(())
+    }
+
+    /// Index, source line and disassembly of the instruction at `ip`. Lines
+    /// of included files are followed by the file they are in.
+    fn describe(&self, ip: usize) -> String {
+        let breakpoint = if self.breakpoints.contains(&ip) {
+            "*"
+        } else {
+            " "
+        };
+        let line = self
+            .vm
+            .get_debug_info()
+            .and_then(|debug_info| {
+                let line = debug_info.line(ip)?;
+                Some(match debug_info.files.get(ip) {
+                    Some(0) | None => format!(" (line {})", line),
+                    Some(_) => format!(" (line {} of {})", line, debug_info.file(ip)?.name),
+                })
+            })
+            .unwrap_or_default();
+
+        match self.vm.get_instructions().get(ip) {
+            Some(inst) => format!("{}{:>4}: {}{}", breakpoint, ip, inst, line),
+            None => format!("{}{:>4}: <end of program>", breakpoint, ip),
+        }
+    }
+
+    fn location(&self) -> String {
+        if self.vm.is_halted() {
+            "Program halted\n".to_string()
+        } else {
+            format!("=> {}\n", self.describe(self.vm.get_ip()))
+        }
+    }
+}
@@ -2,10 +2,65 @@ use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum KvmError {
+    #[error("Could not read program: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not a kvm program, invalid magic number")]
+    InvalidMagic,
+    #[error("Unsupported kvm format version {0}")]
+    UnsupportedVersion(u16),
+    #[error("Truncated program, expected more bytes at offset {offset}")]
+    TruncatedOperand { offset: usize },
+    #[error("Unknown opcode {upcode:#x} at offset {offset}")]
+    UnknownOpcode { upcode: u8, offset: usize },
+    #[error("Invalid utf-8 string constant at offset {offset}")]
+    InvalidUtf8 { offset: usize },
+    #[error("String constant {idx} referenced at offset {offset} does not exist")]
+    InvalidConstant { idx: u32, offset: usize },
+    #[error("Invalid bool operand {byte:#x} at offset {offset}, expected 0 or 1")]
+    InvalidBool { byte: u8, offset: usize },
+    #[error("Debug section has {lines} lines for {instructions} instructions")]
+    InvalidDebugInfo { lines: usize, instructions: usize },
+    #[error("Instruction {ip} comes from file {file}, which is not in the debug section")]
+    InvalidSourceFile { ip: usize, file: u32 },
+    #[error("Entry point {0} is outside of the program")]
+    InvalidEntry(usize),
+    #[error("Instruction {ip} jumps to {target}, outside of the program")]
+    InvalidJumpTarget { ip: usize, target: u32 },
+    #[error("Instruction {ip} ({instruction}) needs {needed} values on the stack but can be reached with {depth}")]
+    Underflow {
+        ip: usize,
+        instruction: String,
+        needed: usize,
+        depth: usize,
+    },
+    #[error("Instruction pointer {0} is outside of the program")]
+    IpOutOfRange(usize),
+    #[error("Ran out of fuel before the program halted")]
+    OutOfFuel,
     #[error("Stack overflow error")]
     StackOverflow,
     #[error("Stack underflow")]
     StackUnderflow,
     #[error("Division by zero")]
     DivisionByZero,
+    #[error("Type mismatch in {instruction}: expected {expected}, found {found}")]
+    TypeMismatch {
+        instruction: &'static str,
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("Call depth exceeded the limit of {0} frames")]
+    CallDepthExceeded(usize),
+    #[error("Return outside of a call")]
+    ReturnOutsideCall,
+    #[error("Global {0} was read before being stored")]
+    UndefinedGlobal(u32),
+    #[error("Index {index} is out of bounds for an array of length {len}")]
+    IndexOutOfBounds { index: i32, len: usize },
+    #[error("Out of memory, the heap is limited to {0} bytes")]
+    OutOfMemory(usize),
+    #[error("Native function {0} is not registered")]
+    UnknownNative(u32),
+    #[error("Native function {name} failed: {message}")]
+    Native { name: String, message: String },
 }
@@ -0,0 +1,197 @@
+use std::mem::{size_of, size_of_val};
+
+use crate::value::Value;
+
+/// Index of an object on the `Heap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjRef(pub(crate) usize);
+
+impl ObjRef {
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Object {
+    Str(String),
+    Array(Vec<Value>),
+    /// A function together with the values it captured, which it receives as
+    /// locals after its arguments.
+    Closure {
+        addr: u32,
+        captured: Vec<Value>,
+    },
+}
+
+impl Object {
+    /// Bytes the object counts for against the heap size limit.
+    pub fn size(&self) -> usize {
+        let payload = match self {
+            Object::Str(s) => s.len(),
+            _ => size_of_val(self.values()),
+        };
+
+        size_of::<Object>() + payload
+    }
+
+    fn values(&self) -> &[Value] {
+        match self {
+            Object::Str(_) => &[],
+            Object::Array(values) => values,
+            Object::Closure { captured, .. } => captured,
+        }
+    }
+}
+
+/// Objects referenced by stack values. Slots of collected objects are reused
+/// by later allocations.
+#[derive(Debug, Default)]
+pub struct Heap {
+    objects: Vec<Option<Object>>,
+    marks: Vec<bool>,
+    free: Vec<usize>,
+    size: usize,
+    collections: usize,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes used by the objects on the heap, reachable or not.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of objects on the heap, reachable or not.
+    pub fn len(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// How many times the garbage collector ran.
+    pub fn collections(&self) -> usize {
+        self.collections
+    }
+
+    pub fn get(&self, obj: ObjRef) -> Option<&Object> {
+        self.objects.get(obj.0).and_then(Option::as_ref)
+    }
+
+    pub(crate) fn get_mut(&mut self, obj: ObjRef) -> Option<&mut Object> {
+        self.objects.get_mut(obj.0).and_then(Option::as_mut)
+    }
+
+    /// The contents of a string value.
+    pub fn string(&self, value: &Value) -> Option<&str> {
+        match value {
+            Value::Str(obj) => match self.get(*obj) {
+                Some(Object::Str(s)) => Some(s),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The elements of an array value.
+    pub fn array(&self, value: &Value) -> Option<&[Value]> {
+        match value {
+            Value::Array(obj) => match self.get(*obj) {
+                Some(Object::Array(values)) => Some(values),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub(crate) fn alloc(&mut self, object: Object) -> ObjRef {
+        self.size += object.size();
+
+        match self.free.pop() {
+            Some(idx) => {
+                self.objects[idx] = Some(object);
+                ObjRef(idx)
+            }
+            None => {
+                self.objects.push(Some(object));
+                self.marks.push(false);
+                ObjRef(self.objects.len() - 1)
+            }
+        }
+    }
+
+    /// Frees every object that can't be reached from `roots`, returning how
+    /// many were freed.
+    pub fn collect<'v>(&mut self, roots: impl IntoIterator<Item = &'v Value>) -> usize {
+        let mut worklist: Vec<ObjRef> = roots.into_iter().filter_map(Value::obj_ref).collect();
+
+        while let Some(obj) = worklist.pop() {
+            if std::mem::replace(&mut self.marks[obj.0], true) {
+                continue;
+            }
+            if let Some(object) = &self.objects[obj.0] {
+                worklist.extend(object.values().iter().filter_map(Value::obj_ref));
+            }
+        }
+
+        let mut freed = 0;
+        for (idx, slot) in self.objects.iter_mut().enumerate() {
+            if std::mem::replace(&mut self.marks[idx], false) {
+                continue;
+            }
+            if let Some(object) = slot.take() {
+                self.size -= object.size();
+                self.free.push(idx);
+                freed += 1;
+            }
+        }
+
+        self.collections += 1;
+        freed
+    }
+
+    /// Strings are equal when their contents are, arrays and closures only
+    /// when they are the same object.
+    pub fn equals(&self, a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::Str(_), Value::Str(_)) => self.string(a) == self.string(b),
+            (a, b) => a == b,
+        }
+    }
+
+    /// How `print` writes a value, arrays as `[1, 2, 3]`.
+    pub fn display(&self, value: &Value) -> String {
+        self.display_nested(value, &mut Vec::new())
+    }
+
+    /// `path` holds the arrays being displayed, so arrays containing
+    /// themselves are shown as `[...]`.
+    fn display_nested(&self, value: &Value, path: &mut Vec<ObjRef>) -> String {
+        match value {
+            Value::Int(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Null => "null".to_string(),
+            Value::Str(_) => self.string(value).unwrap_or_default().to_string(),
+            Value::Closure(_) => "function".to_string(),
+            Value::Array(obj) if path.contains(obj) => "[...]".to_string(),
+            Value::Array(obj) => {
+                path.push(*obj);
+                let elements = self
+                    .array(value)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|element| self.display_nested(element, path))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                path.pop();
+
+                format!("[{}]", elements)
+            }
+        }
+    }
+}
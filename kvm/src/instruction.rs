@@ -1,4 +1,4 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
     Halt,
     Add,
@@ -11,6 +11,37 @@ pub enum Instruction {
     JmpIf(u32),
     Dup(u32),
     PushStr(String),
-    PrintStack,
-    PrintStr,
+    PushBool(bool),
+    PushNull,
+    Print,
+    Input,
+    Call(u32, u32),
+    Ret,
+    LoadLocal(u32),
+    StoreLocal(u32),
+    LoadGlobal(u32),
+    StoreGlobal(u32),
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Ne,
+    Not,
+    And,
+    Or,
+    Mod,
+    Neg,
+    Pop,
+    Swap,
+    Over,
+    Rot,
+    JmpIfNot(u32),
+    NewArray(u32),
+    ArrayGet,
+    ArraySet,
+    ArrayLen,
+    Concat,
+    Closure(u32, u32),
+    CallClosure(u32),
+    CallNative(u32, u32),
 }
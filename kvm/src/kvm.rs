@@ -1,145 +1,305 @@
 use std::fs::File;
-use std::io::Read;
-
-use crate::{error::KvmError, instruction::Instruction};
-
-const STACK_CAPACITY: usize = 1024;
-const MAX_INSTRUCTIONS: usize = 1000;
-const STRING_POOL_CAPACITY: usize = 1024;
+use std::io::{BufRead, Read, Write};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    builder::{KvmBuilder, KvmConfig},
+    bytecode::{DebugInfo, Program},
+    error::KvmError,
+    heap::{Heap, ObjRef, Object},
+    instruction::Instruction,
+    native::{Native, NativeFn, DEFAULT_NATIVES},
+    observer::Observer,
+    value::Value,
+    verifier::verify_from,
+};
+
+/// Bookkeeping for an active `call`: where to resume once the callee returns
+/// and where its locals start on the stack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub return_address: usize,
+    pub base: usize,
+}
 
 pub struct Kvm {
-    stack: Vec<i32>,
+    stack: Vec<Value>,
     program: Vec<Instruction>,
-    string_pool: Vec<String>,
+    frames: Vec<Frame>,
+    globals: Vec<Option<Value>>,
+    heap: Heap,
+    debug_info: Option<DebugInfo>,
+    config: KvmConfig,
+    output: Box<dyn Write + Send>,
+    input: Box<dyn BufRead + Send>,
+    observer: Option<Box<dyn Observer + Send>>,
+    natives: Vec<Native>,
+    started: Instant,
+    random_state: u64,
+    entry: usize,
     ip: usize,
     halt: bool,
+    verified: bool,
+}
+
+impl Default for Kvm {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Kvm {
     pub fn new() -> Self {
+        KvmBuilder::new().build()
+    }
+
+    pub(crate) fn with_config(
+        config: KvmConfig,
+        output: Box<dyn Write + Send>,
+        input: Box<dyn BufRead + Send>,
+    ) -> Self {
         Kvm {
-            stack: Vec::with_capacity(STACK_CAPACITY),
-            program: Vec::with_capacity(MAX_INSTRUCTIONS),
-            string_pool: Vec::with_capacity(STRING_POOL_CAPACITY),
+            stack: Vec::new(),
+            program: Vec::new(),
+            frames: Vec::new(),
+            globals: Vec::new(),
+            heap: Heap::new(),
+            debug_info: None,
+            config,
+            output,
+            input,
+            observer: None,
+            natives: DEFAULT_NATIVES
+                .iter()
+                .map(|(name, function)| Native {
+                    name: name.to_string(),
+                    function: *function,
+                })
+                .collect(),
+            started: Instant::now(),
+            // xorshift needs a non zero state
+            random_state: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64)
+                | 1,
+            entry: 0,
             ip: 0,
             halt: false,
+            verified: false,
         }
     }
 
+    /// Runs the program until it halts, verifying it first unless disabled
+    /// in the config.
     pub fn execute_program(&mut self) -> Result<(), KvmError> {
-        for _ in 0..MAX_INSTRUCTIONS {
-            if self.halt {
-                break;
+        self.verify()?;
+
+        while !self.halt {
+            self.step()?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs `verify` on the loaded program unless disabled in the config.
+    /// Programs are only verified once, until more instructions are loaded.
+    pub fn verify(&mut self) -> Result<(), KvmError> {
+        if self.config.verify && !self.verified {
+            verify_from(&self.program, self.entry)?;
+            self.verified = true;
+        }
+
+        Ok(())
+    }
+
+    /// Executes the instruction at `ip`, doing nothing once the program halted.
+    pub fn step(&mut self) -> Result<(), KvmError> {
+        if self.halt {
+            return Ok(());
+        }
+
+        if let Some(fuel) = self.config.fuel.as_mut() {
+            if *fuel == 0 {
+                return Err(KvmError::OutOfFuel);
             }
+            *fuel -= 1;
+        }
+
+        let ip = self.ip;
+        let inst = self
+            .program
+            .get(ip)
+            .ok_or(KvmError::IpOutOfRange(ip))?
+            .clone();
+
+        if self.observer.is_none() {
+            return self.execute_instruction(inst);
+        }
 
-            let inst = self.program.get(self.ip).unwrap();
-            self.execute_instruction(inst.clone())?;
+        self.execute_instruction(inst.clone())?;
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_instruction(ip, &inst, &self.stack, &self.heap);
         }
 
         Ok(())
     }
 
+    /// Notifies `observer` of every instruction executed from now on.
+    pub fn set_observer(&mut self, observer: impl Observer + Send + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    pub fn take_observer(&mut self) -> Option<Box<dyn Observer + Send>> {
+        self.observer.take()
+    }
+
+    /// Limits how many instructions can still be executed, so programs that
+    /// never halt are stopped with `KvmError::OutOfFuel`.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.config.fuel = fuel;
+    }
+
+    pub fn get_fuel(&self) -> Option<u64> {
+        self.config.fuel
+    }
+
+    pub fn set_verify(&mut self, verify: bool) {
+        self.config.verify = verify;
+    }
+
+    pub fn get_config(&self) -> &KvmConfig {
+        &self.config
+    }
+
     pub fn get_instructions(&self) -> &[Instruction] {
         &self.program
     }
 
-    pub fn load_program_from_vec(&mut self, prog: Vec<Instruction>) {
-        self.program.extend(prog);
+    /// Where execution of the loaded program starts.
+    pub fn get_entry(&self) -> usize {
+        self.entry
     }
 
-    // TODO: handle strings without relying on ""
-    fn extract_string_from_bytes(&self, bytes: &[u8]) -> Option<String> {
-        if let Ok(text) = std::str::from_utf8(bytes) {
-            if let (Some(start), Some(end)) = (text.find('"'), text.rfind('"')) {
-                if start < end {
-                    return Some(text[start + 1..end].to_string());
-                }
+    pub fn get_stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    pub fn get_frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn get_globals(&self) -> &[Option<Value>] {
+        &self.globals
+    }
+
+    /// The objects referenced by strings, arrays and closures.
+    pub fn get_heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    /// Frees the heap objects no longer reachable from the stack or the
+    /// globals, returning how many were freed. This runs on its own whenever
+    /// an allocation would exceed the heap size.
+    pub fn collect_garbage(&mut self) -> usize {
+        let roots = self.stack.iter().chain(self.globals.iter().flatten());
+        self.heap.collect(roots)
+    }
+
+    /// Makes `function` callable with `callnative`, returning its id. A
+    /// native registered with the name of an existing one replaces it and
+    /// keeps its id.
+    pub fn register_native(&mut self, name: impl Into<String>, function: NativeFn) -> u32 {
+        let name = name.into();
+
+        match self.natives.iter().position(|native| native.name == name) {
+            Some(id) => {
+                self.natives[id].function = function;
+                id as u32
+            }
+            None => {
+                self.natives.push(Native { name, function });
+                self.natives.len() as u32 - 1
             }
         }
-        None
     }
 
-    pub fn load_program_from_file(&mut self, file_path: &str) {
-        let mut file = File::open(file_path).unwrap();
+    pub fn get_natives(&self) -> &[Native] {
+        &self.natives
+    }
 
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer).unwrap();
-
-        let mut instructions = Vec::new();
-        let mut i = 0;
-
-        // TODO: find a better way to map byte code to instructions
-        while i < buffer.len() {
-            let byte = buffer[i];
-
-            let inst = match byte {
-                _ if byte == Instruction::Halt.upcode() => Instruction::Halt,
-                _ if byte == Instruction::Add.upcode() => Instruction::Add,
-                _ if byte == Instruction::Sub.upcode() => Instruction::Sub,
-                _ if byte == Instruction::Div.upcode() => Instruction::Div,
-                _ if byte == Instruction::Mul.upcode() => Instruction::Mul,
-                _ if byte == Instruction::Eq.upcode() => Instruction::Eq,
-                _ if byte == Instruction::PrintStack.upcode() => Instruction::PrintStack,
-                _ if byte == Instruction::PrintStr.upcode() => Instruction::PrintStr,
-                _ if byte == Instruction::Push(0).upcode() => {
-                    let slice: [u8; 4] = buffer[i + 1..i + 5]
-                        .try_into()
-                        .expect("Could not convert push value to a number!");
-                    let num = i32::from_le_bytes(slice);
-
-                    i += std::mem::size_of::<i32>();
-
-                    Instruction::Push(num)
-                }
-                _ if byte == Instruction::Jmp(0).upcode()  => {
-                    let slice: [u8; 4] = buffer[i + 1..i + 5]
-                        .try_into()
-                        .expect("Could not convert push value to a number!");
-                    let addr = u32::from_le_bytes(slice);
+    /// Where `print` writes to, for natives.
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut self.output
+    }
 
-                    i += std::mem::size_of::<u32>();
+    /// Where `input` reads from, for natives.
+    pub fn input(&mut self) -> &mut dyn BufRead {
+        &mut self.input
+    }
 
-                    Instruction::Jmp(addr)
-                }
-                _ if byte == Instruction::JmpIf(0).upcode() => {
-                    let slice: [u8; 4] = buffer[i + 1..i + 5]
-                        .try_into()
-                        .expect("Could not convert push value to a number!");
-                    let addr = u32::from_le_bytes(slice);
+    /// Allocates a string on the heap.
+    pub fn new_string(&mut self, s: impl Into<String>) -> Result<Value, KvmError> {
+        Ok(Value::Str(self.alloc(Object::Str(s.into()))?))
+    }
 
-                    i += std::mem::size_of::<u32>();
+    /// Allocates an array on the heap. Garbage may be collected first, so
+    /// `values` must only refer to objects reachable from the stack or the
+    /// globals.
+    pub fn new_array(&mut self, values: Vec<Value>) -> Result<Value, KvmError> {
+        Ok(Value::Array(self.alloc(Object::Array(values))?))
+    }
 
-                    Instruction::JmpIf(addr)
-                }
-                _ if byte == Instruction::Dup(0).upcode() => {
-                    let slice: [u8; 4] = buffer[i + 1..i + 5]
-                        .try_into()
-                        .expect("Could not convert push value to a number!");
-                    let addr = u32::from_le_bytes(slice);
+    pub(crate) fn started(&self) -> Instant {
+        self.started
+    }
+
+    /// Next number of a xorshift64* sequence.
+    pub(crate) fn next_random(&mut self) -> u64 {
+        let mut x = self.random_state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.random_state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
 
-                    i += std::mem::size_of::<u32>();
+    pub fn get_ip(&self) -> usize {
+        self.ip
+    }
 
-                    Instruction::Dup(addr)
-                }
-                _ if byte == Instruction::PushStr("".to_string()).upcode() => {
-                    let str = self
-                        .extract_string_from_bytes(&buffer)
-                        .expect("Could not extract string from bytes");
+    pub fn is_halted(&self) -> bool {
+        self.halt
+    }
 
-                    // TODO: handle strings without relying on ""
-                    // skip "" and the string
-                    i += str.len() + 2;
+    pub fn load_program_from_vec(&mut self, prog: Vec<Instruction>) {
+        self.program.extend(prog);
+        self.verified = false;
+    }
 
-                    Instruction::PushStr(str.to_string())
-                }
-                upcode => panic!("Unknown instruction upcode {}", upcode),
-            };
+    /// Loads `program`, which starts running from its entry point.
+    pub fn load_program(&mut self, program: Program) {
+        self.load_program_from_vec(program.instructions);
+        self.debug_info = program.debug_info;
+        self.entry = program.entry as usize;
+        self.ip = self.entry;
+    }
 
-            instructions.push(inst);
-            i += 1;
-        }
+    pub fn load_program_from_file(&mut self, file_path: &str) -> Result<(), KvmError> {
+        let mut file = File::open(file_path)?;
+
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+
+        self.load_program_from_bytes(&buffer)
+    }
+
+    pub fn load_program_from_bytes(&mut self, bytes: &[u8]) -> Result<(), KvmError> {
+        self.load_program(Program::from_bytes(bytes)?);
+        Ok(())
+    }
 
-        self.load_program_from_vec(instructions);
+    pub fn get_debug_info(&self) -> Option<&DebugInfo> {
+        self.debug_info.as_ref()
     }
 
     pub fn dump_stack(&self) {
@@ -148,7 +308,9 @@ impl Kvm {
         if self.stack.is_empty() {
             println!("[Empty]");
         } else {
-            self.stack.iter().for_each(|e| println!("{}", e));
+            self.stack
+                .iter()
+                .for_each(|e| println!("{}", self.heap.display(e)));
         }
     }
 
@@ -157,103 +319,508 @@ impl Kvm {
         self.program.iter().for_each(|inst| println!("{:?}", inst));
     }
 
+    fn push(&mut self, value: Value) -> Result<(), KvmError> {
+        if self.stack.len() >= self.config.stack_size {
+            return Err(KvmError::StackOverflow);
+        }
+
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Value, KvmError> {
+        self.stack.pop().ok_or(KvmError::StackUnderflow)
+    }
+
+    /// Puts `object` on the heap, collecting garbage first when it would not
+    /// fit. Values the object is built from have to stay on the stack until
+    /// it is allocated, so they aren't collected.
+    fn alloc(&mut self, object: Object) -> Result<ObjRef, KvmError> {
+        let limit = self.config.heap_size;
+
+        if self.heap.size() + object.size() > limit {
+            self.collect_garbage();
+
+            if self.heap.size() + object.size() > limit {
+                return Err(KvmError::OutOfMemory(limit));
+            }
+        }
+
+        Ok(self.heap.alloc(object))
+    }
+
+    /// Replaces the top `n` values with a heap object built from them.
+    fn collect_top(
+        &mut self,
+        n: u32,
+        build: impl FnOnce(Vec<Value>) -> Object,
+    ) -> Result<ObjRef, KvmError> {
+        let start = self
+            .stack
+            .len()
+            .checked_sub(n as usize)
+            .ok_or(KvmError::StackUnderflow)?;
+
+        let obj = self.alloc(build(self.stack[start..].to_vec()))?;
+        self.stack.truncate(start);
+        Ok(obj)
+    }
+
+    fn pop_int(&mut self, instruction: &'static str) -> Result<i32, KvmError> {
+        match self.pop()? {
+            Value::Int(n) => Ok(n),
+            value => Err(KvmError::TypeMismatch {
+                instruction,
+                expected: "int",
+                found: value.type_name(),
+            }),
+        }
+    }
+
+    fn pop_bool(&mut self, instruction: &'static str) -> Result<bool, KvmError> {
+        match self.pop()? {
+            Value::Bool(b) => Ok(b),
+            value => Err(KvmError::TypeMismatch {
+                instruction,
+                expected: "bool",
+                found: value.type_name(),
+            }),
+        }
+    }
+
+    fn pop_array(&mut self, instruction: &'static str) -> Result<ObjRef, KvmError> {
+        match self.pop()? {
+            Value::Array(obj) => Ok(obj),
+            value => Err(KvmError::TypeMismatch {
+                instruction,
+                expected: "array",
+                found: value.type_name(),
+            }),
+        }
+    }
+
+    /// The elements of an array popped by `pop_array`.
+    fn elements(&mut self, obj: ObjRef) -> &mut Vec<Value> {
+        match self.heap.get_mut(obj) {
+            Some(Object::Array(elements)) => elements,
+            object => unreachable!("array values refer to arrays, found {:?}", object),
+        }
+    }
+
+    /// Pops the condition of a conditional jump, integers other than 0 and
+    /// `true` being truthy.
+    fn pop_condition(&mut self, instruction: &'static str) -> Result<bool, KvmError> {
+        match self.pop()? {
+            Value::Int(n) => Ok(n != 0),
+            Value::Bool(b) => Ok(b),
+            value => Err(KvmError::TypeMismatch {
+                instruction,
+                expected: "int or bool",
+                found: value.type_name(),
+            }),
+        }
+    }
+
+    /// Replaces the two integers on top of the stack with `op` applied to
+    /// them, the one below being the left operand. Results wrap around on
+    /// overflow.
+    fn arithmetic(
+        &mut self,
+        instruction: &'static str,
+        op: fn(i32, i32) -> Result<i32, KvmError>,
+    ) -> Result<(), KvmError> {
+        let right = self.pop_int(instruction)?;
+        let left = self.pop_int(instruction)?;
+        self.push(Value::Int(op(left, right)?))?;
+        self.ip += 1;
+        Ok(())
+    }
+
+    /// Replaces the two integers on top of the stack with the result of
+    /// comparing them, the one below being the left operand.
+    fn compare(
+        &mut self,
+        instruction: &'static str,
+        op: fn(&i32, &i32) -> bool,
+    ) -> Result<(), KvmError> {
+        let right = self.pop_int(instruction)?;
+        let left = self.pop_int(instruction)?;
+        self.push(Value::Bool(op(&left, &right)))?;
+        self.ip += 1;
+        Ok(())
+    }
+
+    /// Values below the top of the stack, `0` being the top.
+    fn peek(&self, depth: usize) -> Result<&Value, KvmError> {
+        self.stack
+            .len()
+            .checked_sub(depth + 1)
+            .map(|idx| &self.stack[idx])
+            .ok_or(KvmError::StackUnderflow)
+    }
+
     fn execute_instruction(&mut self, inst: Instruction) -> Result<(), KvmError> {
         match inst {
             Instruction::Push(n) => {
-                if self.stack.len() >= STACK_CAPACITY {
-                    return Err(KvmError::StackOverflow);
-                }
-                self.stack.push(n);
+                self.push(Value::Int(n))?;
                 self.ip += 1;
             }
-            Instruction::Add => {
-                let n1 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
-                let n2 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
-                self.stack.push(n1 + n2);
+            Instruction::PushBool(b) => {
+                self.push(Value::Bool(b))?;
                 self.ip += 1;
             }
-            Instruction::Sub => {
-                let n1 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
-                let n2 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
-                self.stack.push(n1 - n2);
+            Instruction::PushNull => {
+                self.push(Value::Null)?;
                 self.ip += 1;
             }
-            Instruction::Div => {
-                let n1 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
-                let n2 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
+            Instruction::PushStr(s) => {
+                let value = self.new_string(s)?;
+                self.push(value)?;
+                self.ip += 1;
+            }
+            Instruction::Add => self.arithmetic("add", |l, r| Ok(l.wrapping_add(r)))?,
+            Instruction::Sub => self.arithmetic("sub", |l, r| Ok(l.wrapping_sub(r)))?,
+            Instruction::Mul => self.arithmetic("mul", |l, r| Ok(l.wrapping_mul(r)))?,
+            Instruction::Div => self.arithmetic("div", |l, r| match r {
+                0 => Err(KvmError::DivisionByZero),
+                r => Ok(l.wrapping_div(r)),
+            })?,
+            Instruction::Mod => self.arithmetic("mod", |l, r| match r {
+                0 => Err(KvmError::DivisionByZero),
+                r => Ok(l.wrapping_rem(r)),
+            })?,
+            Instruction::Jmp(addr) => {
+                self.ip = addr as usize;
+            }
+            Instruction::Halt => self.halt = true,
+            Instruction::Dup(depth) => {
+                let value = self.peek(depth as usize)?.clone();
+                self.push(value)?;
+                self.ip += 1;
+            }
+            Instruction::Eq => {
+                let v1 = self.pop()?;
+                let v2 = self.pop()?;
+                self.push(Value::Bool(self.heap.equals(&v1, &v2)))?;
+                self.ip += 1;
+            }
+            Instruction::Ne => {
+                let v1 = self.pop()?;
+                let v2 = self.pop()?;
+                self.push(Value::Bool(!self.heap.equals(&v1, &v2)))?;
+                self.ip += 1;
+            }
+            Instruction::Lt => self.compare("lt", i32::lt)?,
+            Instruction::Gt => self.compare("gt", i32::gt)?,
+            Instruction::Le => self.compare("le", i32::le)?,
+            Instruction::Ge => self.compare("ge", i32::ge)?,
+            Instruction::Not => {
+                let b = self.pop_bool("not")?;
+                self.push(Value::Bool(!b))?;
+                self.ip += 1;
+            }
+            Instruction::And => {
+                let b1 = self.pop_bool("and")?;
+                let b2 = self.pop_bool("and")?;
+                self.push(Value::Bool(b1 && b2))?;
+                self.ip += 1;
+            }
+            Instruction::Or => {
+                let b1 = self.pop_bool("or")?;
+                let b2 = self.pop_bool("or")?;
+                self.push(Value::Bool(b1 || b2))?;
+                self.ip += 1;
+            }
+            Instruction::Neg => {
+                let n = self.pop_int("neg")?;
+                self.push(Value::Int(n.wrapping_neg()))?;
+                self.ip += 1;
+            }
+            Instruction::Pop => {
+                self.pop()?;
+                self.ip += 1;
+            }
+            Instruction::Swap => {
+                let len = self.stack.len();
+                if len < 2 {
+                    return Err(KvmError::StackUnderflow);
+                }
 
-                if n2 == 0 {
-                    return Err(KvmError::DivisionByZero);
+                self.stack.swap(len - 1, len - 2);
+                self.ip += 1;
+            }
+            Instruction::Over => {
+                let value = self.peek(1)?.clone();
+                self.push(value)?;
+                self.ip += 1;
+            }
+            // brings the third value to the top: `a b c` becomes `b c a`
+            Instruction::Rot => {
+                let len = self.stack.len();
+                if len < 3 {
+                    return Err(KvmError::StackUnderflow);
                 }
 
-                self.stack.push(n1 / n2);
+                self.stack[len - 3..].rotate_left(1);
                 self.ip += 1;
             }
-            Instruction::Mul => {
-                let n1 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
-                let n2 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
-                self.stack.push(n1 * n2);
+            Instruction::JmpIf(addr) => {
+                if self.pop_condition("jmpif")? {
+                    self.ip = addr as usize;
+                } else {
+                    self.ip += 1;
+                }
+            }
+            Instruction::JmpIfNot(addr) => {
+                if self.pop_condition("jmpifnot")? {
+                    self.ip += 1;
+                } else {
+                    self.ip = addr as usize;
+                }
+            }
+            Instruction::Print => {
+                let value = self.pop()?;
+                writeln!(self.output, "{}", self.heap.display(&value))?;
                 self.ip += 1;
             }
-            Instruction::Jmp(addr) => {
-                self.ip = addr as usize;
+            Instruction::Input => {
+                let mut line = String::new();
+
+                // the end of the input is read as null
+                let value = match self.input.read_line(&mut line)? {
+                    0 => Value::Null,
+                    _ => self.new_string(line.trim_end_matches(['\n', '\r']))?,
+                };
+                self.push(value)?;
+                self.ip += 1;
             }
-            Instruction::Halt => self.halt = true,
-            Instruction::Dup(addr) => {
-                if self.stack.len() >= STACK_CAPACITY {
-                    return Err(KvmError::StackOverflow);
+            Instruction::Call(addr, argc) => {
+                if self.frames.len() >= self.config.max_call_depth {
+                    return Err(KvmError::CallDepthExceeded(self.config.max_call_depth));
                 }
 
-                let idx = self.stack.len() - addr as usize;
-                if idx <= 0 {
+                // the arguments become the first locals of the callee
+                let base = self
+                    .stack
+                    .len()
+                    .checked_sub(argc as usize)
+                    .ok_or(KvmError::StackUnderflow)?;
+
+                self.frames.push(Frame {
+                    return_address: self.ip + 1,
+                    base,
+                });
+                self.ip = addr as usize;
+            }
+            Instruction::Ret => {
+                let frame = self.frames.pop().ok_or(KvmError::ReturnOutsideCall)?;
+                let value = self.stack.pop().ok_or(KvmError::StackUnderflow)?;
+
+                if self.stack.len() < frame.base {
                     return Err(KvmError::StackUnderflow);
                 }
 
-                let elem = self.stack.get(idx - 1).unwrap();
-                self.stack.push(*elem);
+                self.stack.truncate(frame.base);
+                self.stack.push(value);
+                self.ip = frame.return_address;
+            }
+            Instruction::LoadLocal(n) => {
+                if self.stack.len() >= self.config.stack_size {
+                    return Err(KvmError::StackOverflow);
+                }
+
+                let elem = self
+                    .stack
+                    .get(self.frame_base() + n as usize)
+                    .cloned()
+                    .ok_or(KvmError::StackUnderflow)?;
+                self.stack.push(elem);
                 self.ip += 1;
             }
-            Instruction::Eq => {
-                let n1 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
-                let n2 = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
-                self.stack.push((n1 == n2) as i32);
+            Instruction::StoreLocal(n) => {
+                let value = self.stack.pop().ok_or(KvmError::StackUnderflow)?;
+                let idx = self.frame_base() + n as usize;
+
+                // storing right past the last local declares a new one
+                match idx.cmp(&self.stack.len()) {
+                    std::cmp::Ordering::Less => self.stack[idx] = value,
+                    std::cmp::Ordering::Equal => self.stack.push(value),
+                    std::cmp::Ordering::Greater => return Err(KvmError::StackUnderflow),
+                }
                 self.ip += 1;
             }
-            Instruction::JmpIf(addr) => {
-                let n = self.stack.pop().ok_or_else(|| KvmError::StackUnderflow)?;
+            Instruction::LoadGlobal(n) => {
+                if self.stack.len() >= self.config.stack_size {
+                    return Err(KvmError::StackOverflow);
+                }
 
-                if n > 0 {
-                    self.ip = addr as usize;
-                } else {
-                    self.ip += 1;
+                let value = self
+                    .globals
+                    .get(n as usize)
+                    .cloned()
+                    .flatten()
+                    .ok_or(KvmError::UndefinedGlobal(n))?;
+                self.stack.push(value);
+                self.ip += 1;
+            }
+            Instruction::StoreGlobal(n) => {
+                let value = self.stack.pop().ok_or(KvmError::StackUnderflow)?;
+
+                if self.globals.len() <= n as usize {
+                    self.globals.resize(n as usize + 1, None);
                 }
+                self.globals[n as usize] = Some(value);
+                self.ip += 1;
             }
-            Instruction::PushStr(s) => {
-                self.string_pool.push(s);
+            Instruction::NewArray(n) => {
+                let obj = self.collect_top(n, Object::Array)?;
+                self.push(Value::Array(obj))?;
                 self.ip += 1;
             }
-            Instruction::PrintStack => {
-                println!(
-                    "{}",
-                    match self.stack.pop() {
-                        Some(i) => i.to_string(),
-                        None => "Empty".to_string(),
-                    }
-                );
+            Instruction::ArrayGet => {
+                let index = self.pop_int("arrayget")?;
+                let obj = self.pop_array("arrayget")?;
+                let elements = self.elements(obj);
+
+                let value =
+                    element_index(index, elements.len()).map(|idx| elements[idx].clone())?;
+                self.push(value)?;
+                self.ip += 1;
+            }
+            Instruction::ArraySet => {
+                let value = self.pop()?;
+                let index = self.pop_int("arrayset")?;
+                let obj = self.pop_array("arrayset")?;
+                let elements = self.elements(obj);
+
+                let idx = element_index(index, elements.len())?;
+                elements[idx] = value;
                 self.ip += 1;
             }
-            Instruction::PrintStr => {
-                println!(
-                    "{}",
-                    match self.string_pool.pop() {
-                        Some(i) => i.to_string(),
-                        None => "Empty".to_string(),
+            Instruction::ArrayLen => {
+                let obj = self.pop_array("arraylen")?;
+                let len = self.elements(obj).len();
+                self.push(Value::Int(len as i32))?;
+                self.ip += 1;
+            }
+            Instruction::Concat => {
+                let (left, right) = (self.peek(1)?, self.peek(0)?);
+
+                // the operands stay on the stack while the result is allocated
+                let object = match (left, right) {
+                    (Value::Str(_), Value::Str(_)) => Object::Str(format!(
+                        "{}{}",
+                        self.heap.string(left).unwrap_or_default(),
+                        self.heap.string(right).unwrap_or_default()
+                    )),
+                    (Value::Array(_), Value::Array(_)) => Object::Array(
+                        [self.heap.array(left), self.heap.array(right)]
+                            .into_iter()
+                            .flat_map(Option::unwrap_or_default)
+                            .cloned()
+                            .collect(),
+                    ),
+                    (Value::Str(_) | Value::Array(_), value) => {
+                        return Err(KvmError::TypeMismatch {
+                            instruction: "concat",
+                            expected: left.type_name(),
+                            found: value.type_name(),
+                        })
+                    }
+                    (value, _) => {
+                        return Err(KvmError::TypeMismatch {
+                            instruction: "concat",
+                            expected: "string or array",
+                            found: value.type_name(),
+                        })
                     }
-                );
+                };
+
+                let value = match object {
+                    Object::Str(_) => Value::Str(self.alloc(object)?),
+                    _ => Value::Array(self.alloc(object)?),
+                };
+                self.stack.truncate(self.stack.len() - 2);
+                self.push(value)?;
+                self.ip += 1;
+            }
+            Instruction::Closure(addr, n) => {
+                let obj = self.collect_top(n, |captured| Object::Closure { addr, captured })?;
+                self.push(Value::Closure(obj))?;
+                self.ip += 1;
+            }
+            Instruction::CallNative(id, argc) => {
+                let function = self
+                    .natives
+                    .get(id as usize)
+                    .ok_or(KvmError::UnknownNative(id))?
+                    .function;
+                let start = self
+                    .stack
+                    .len()
+                    .checked_sub(argc as usize)
+                    .ok_or(KvmError::StackUnderflow)?;
+
+                // the arguments stay on the stack while the native runs, so
+                // their objects aren't collected
+                let args = self.stack[start..].to_vec();
+                let result = function(self, &args)?;
+
+                self.stack.truncate(start);
+                self.push(result)?;
                 self.ip += 1;
             }
+            Instruction::CallClosure(argc) => {
+                let (addr, captured) = match self.pop()? {
+                    Value::Closure(obj) => match self.heap.get(obj) {
+                        Some(Object::Closure { addr, captured }) => (*addr, captured.clone()),
+                        object => {
+                            unreachable!("closure values refer to closures, found {:?}", object)
+                        }
+                    },
+                    value => {
+                        return Err(KvmError::TypeMismatch {
+                            instruction: "callclosure",
+                            expected: "closure",
+                            found: value.type_name(),
+                        })
+                    }
+                };
+
+                if self.frames.len() >= self.config.max_call_depth {
+                    return Err(KvmError::CallDepthExceeded(self.config.max_call_depth));
+                }
+
+                // the captured values become the locals after the arguments
+                let base = self
+                    .stack
+                    .len()
+                    .checked_sub(argc as usize)
+                    .ok_or(KvmError::StackUnderflow)?;
+                for value in captured {
+                    self.push(value)?;
+                }
+
+                self.frames.push(Frame {
+                    return_address: self.ip + 1,
+                    base,
+                });
+                self.ip = addr as usize;
+            }
         };
 
         Ok(())
     }
+
+    fn frame_base(&self) -> usize {
+        self.frames.last().map_or(0, |frame| frame.base)
+    }
+}
+
+/// Checks that `index` is inside an array of length `len`.
+fn element_index(index: i32, len: usize) -> Result<usize, KvmError> {
+    usize::try_from(index)
+        .ok()
+        .filter(|idx| *idx < len)
+        .ok_or(KvmError::IndexOutOfBounds { index, len })
 }
@@ -1,10 +1,30 @@
+pub mod builder;
+pub mod bytecode;
+pub mod debugger;
 pub mod error;
+pub mod heap;
 pub mod instruction;
 pub mod kvm;
+pub mod native;
+pub mod observer;
+pub mod optimizer;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod value;
+pub mod verifier;
 
+pub use builder::*;
+pub use bytecode::*;
+pub use debugger::*;
 pub use error::*;
+pub use heap::*;
 pub use instruction::*;
 pub use kvm::*;
+pub use native::*;
+pub use observer::*;
+pub use optimizer::*;
+pub use value::*;
+pub use verifier::*;
 
 use std::fmt::Display;
 
@@ -21,15 +41,115 @@ impl Display for Instruction {
             Instruction::Jmp(addr) => format!("jmp {}", addr),
             Instruction::JmpIf(addr) => format!("jmpif {}", addr),
             Instruction::Dup(addr) => format!("dup {}", addr),
-            Instruction::PushStr(str) => format!("pushstr \"{}\"", str),
-            Instruction::PrintStack => "printstack".to_string(),
-            Instruction::PrintStr => "printstr".to_string(),
+            Instruction::PushStr(str) => format!("pushstr \"{}\"", escape(str)),
+            Instruction::PushBool(b) => format!("pushbool {}", b),
+            Instruction::PushNull => "pushnull".to_string(),
+            Instruction::Print => "print".to_string(),
+            Instruction::Input => "input".to_string(),
+            Instruction::Call(addr, argc) => format!("call {} {}", addr, argc),
+            Instruction::Ret => "ret".to_string(),
+            Instruction::LoadLocal(n) => format!("loadlocal {}", n),
+            Instruction::StoreLocal(n) => format!("storelocal {}", n),
+            Instruction::LoadGlobal(n) => format!("loadglobal {}", n),
+            Instruction::StoreGlobal(n) => format!("storeglobal {}", n),
+            Instruction::Lt => "lt".to_string(),
+            Instruction::Gt => "gt".to_string(),
+            Instruction::Le => "le".to_string(),
+            Instruction::Ge => "ge".to_string(),
+            Instruction::Ne => "ne".to_string(),
+            Instruction::Not => "not".to_string(),
+            Instruction::And => "and".to_string(),
+            Instruction::Or => "or".to_string(),
+            Instruction::Mod => "mod".to_string(),
+            Instruction::Neg => "neg".to_string(),
+            Instruction::Pop => "pop".to_string(),
+            Instruction::Swap => "swap".to_string(),
+            Instruction::Over => "over".to_string(),
+            Instruction::Rot => "rot".to_string(),
+            Instruction::JmpIfNot(addr) => format!("jmpifnot {}", addr),
+            Instruction::NewArray(n) => format!("newarray {}", n),
+            Instruction::ArrayGet => "arrayget".to_string(),
+            Instruction::ArraySet => "arrayset".to_string(),
+            Instruction::ArrayLen => "arraylen".to_string(),
+            Instruction::Concat => "concat".to_string(),
+            Instruction::Closure(addr, n) => format!("closure {} {}", addr, n),
+            Instruction::CallClosure(argc) => format!("callclosure {}", argc),
+            Instruction::CallNative(id, argc) => format!("callnative {} {}", id, argc),
         };
         write!(f, "{}", s)
     }
 }
 
+/// Escapes quotes, backslashes and line breaks the way ksm string literals
+/// expect them.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
 impl Instruction {
+    /// The ksm mnemonic of the instruction.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instruction::Halt => "halt",
+            Instruction::Add => "add",
+            Instruction::Sub => "sub",
+            Instruction::Div => "div",
+            Instruction::Mul => "mul",
+            Instruction::Eq => "eq",
+            Instruction::Push(_) => "push",
+            Instruction::Jmp(_) => "jmp",
+            Instruction::JmpIf(_) => "jmpif",
+            Instruction::Dup(_) => "dup",
+            Instruction::PushStr(_) => "pushstr",
+            Instruction::PushBool(_) => "pushbool",
+            Instruction::PushNull => "pushnull",
+            Instruction::Print => "print",
+            Instruction::Input => "input",
+            Instruction::Call(_, _) => "call",
+            Instruction::Ret => "ret",
+            Instruction::LoadLocal(_) => "loadlocal",
+            Instruction::StoreLocal(_) => "storelocal",
+            Instruction::LoadGlobal(_) => "loadglobal",
+            Instruction::StoreGlobal(_) => "storeglobal",
+            Instruction::Lt => "lt",
+            Instruction::Gt => "gt",
+            Instruction::Le => "le",
+            Instruction::Ge => "ge",
+            Instruction::Ne => "ne",
+            Instruction::Not => "not",
+            Instruction::And => "and",
+            Instruction::Or => "or",
+            Instruction::Mod => "mod",
+            Instruction::Neg => "neg",
+            Instruction::Pop => "pop",
+            Instruction::Swap => "swap",
+            Instruction::Over => "over",
+            Instruction::Rot => "rot",
+            Instruction::JmpIfNot(_) => "jmpifnot",
+            Instruction::NewArray(_) => "newarray",
+            Instruction::ArrayGet => "arrayget",
+            Instruction::ArraySet => "arrayset",
+            Instruction::ArrayLen => "arraylen",
+            Instruction::Concat => "concat",
+            Instruction::Closure(_, _) => "closure",
+            Instruction::CallClosure(_) => "callclosure",
+            Instruction::CallNative(_, _) => "callnative",
+        }
+    }
+
     pub fn upcode(&self) -> u8 {
         match self {
             Instruction::Halt => 0x0,
@@ -43,45 +163,39 @@ impl Instruction {
             Instruction::JmpIf(_) => 0x8,
             Instruction::Dup(_) => 0x9,
             Instruction::PushStr(_) => 0x10,
-            Instruction::PrintStack => 0x11,
-            Instruction::PrintStr => 0x12,
+            Instruction::Print => 0x11,
+            Instruction::PushBool(_) => 0x19,
+            Instruction::PushNull => 0x1a,
+            Instruction::Input => 0x1b,
+            Instruction::Call(_, _) => 0x13,
+            Instruction::Ret => 0x14,
+            Instruction::LoadLocal(_) => 0x15,
+            Instruction::StoreLocal(_) => 0x16,
+            Instruction::LoadGlobal(_) => 0x17,
+            Instruction::StoreGlobal(_) => 0x18,
+            Instruction::Lt => 0x1c,
+            Instruction::Gt => 0x1d,
+            Instruction::Le => 0x1e,
+            Instruction::Ge => 0x1f,
+            Instruction::Ne => 0x20,
+            Instruction::Not => 0x21,
+            Instruction::And => 0x22,
+            Instruction::Or => 0x23,
+            Instruction::Mod => 0x24,
+            Instruction::Neg => 0x25,
+            Instruction::Pop => 0x26,
+            Instruction::Swap => 0x27,
+            Instruction::Over => 0x28,
+            Instruction::Rot => 0x29,
+            Instruction::JmpIfNot(_) => 0x2a,
+            Instruction::NewArray(_) => 0x2b,
+            Instruction::ArrayGet => 0x2c,
+            Instruction::ArraySet => 0x2d,
+            Instruction::ArrayLen => 0x2e,
+            Instruction::Concat => 0x2f,
+            Instruction::Closure(_, _) => 0x30,
+            Instruction::CallClosure(_) => 0x31,
+            Instruction::CallNative(_, _) => 0x32,
         }
     }
-
-    pub fn as_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-
-        match self {
-            Instruction::Halt => bytes.push(Instruction::Halt.upcode()),
-            Instruction::Add => bytes.push(Instruction::Add.upcode()),
-            Instruction::Sub => bytes.push(Instruction::Sub.upcode()),
-            Instruction::Div => bytes.push(Instruction::Div.upcode()),
-            Instruction::Mul => bytes.push(Instruction::Mul.upcode()),
-            Instruction::Eq => bytes.push(Instruction::Eq.upcode()),
-            Instruction::PrintStack => bytes.push(Instruction::PrintStack.upcode()),
-            Instruction::PrintStr => bytes.push(Instruction::PrintStr.upcode()),
-            Instruction::Push(n) => {
-                bytes.push(Instruction::Push(0).upcode());
-                bytes.extend(n.to_le_bytes());
-            }
-            Instruction::Jmp(n) => {
-                bytes.push(Instruction::Jmp(0).upcode());
-                bytes.extend(n.to_le_bytes());
-            }
-            Instruction::JmpIf(n) => {
-                bytes.push(Instruction::JmpIf(0).upcode());
-                bytes.extend(n.to_le_bytes());
-            }
-            Instruction::Dup(n) => {
-                bytes.push(Instruction::Dup(0).upcode());
-                bytes.extend(n.to_le_bytes());
-            }
-            Instruction::PushStr(str) => {
-                bytes.push(Instruction::PushStr("".to_string()).upcode());
-                bytes.extend(str.bytes());
-            }
-        };
-
-        bytes
-    }
 }
@@ -1,21 +1,47 @@
 use std::error::Error;
+use std::io::BufReader;
 
-use kvm::Kvm;
+use kvm::{Debugger, Kvm, Profiler, Tracer};
+
+const USAGE: &str = "Usage: kvm [--debug | --trace | --profile] <input.kvm>";
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = std::env::args().collect();
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    if args.len() < 2 {
-        panic!("Usage: kvm <input.kvm>");
-    }
+    let (mode, file_path) = match args.as_slice() {
+        [file_path] => ("", file_path),
+        [flag, file_path] if flag.starts_with("--") => (flag.as_str(), file_path),
+        _ => panic!("{}", USAGE),
+    };
 
     let mut vm = Kvm::new();
 
-    vm.load_program_from_file(&args[1]);
-    // vm.dump_program();
-    vm.execute_program()?;
-    // println!("--------");
-    // vm.dump_stack();
+    vm.load_program_from_file(file_path)?;
+
+    match mode {
+        "--debug" => {
+            // `input` reads stdin as well, a one byte buffer keeps the debugger
+            // from consuming the lines meant for the program
+            let commands = BufReader::with_capacity(1, std::io::stdin());
+            Debugger::new(vm)?.run(commands, &mut std::io::stdout())?;
+        }
+        "--trace" => {
+            vm.set_observer(Tracer::new(std::io::stderr()));
+            vm.execute_program()?;
+        }
+        "--profile" => {
+            vm.set_observer(Profiler::new());
+            let result = vm.execute_program();
+
+            // report what ran even when the program failed
+            if let Some(profiler) = vm.take_observer() {
+                profiler.report(&mut std::io::stderr())?;
+            }
+            result?;
+        }
+        "" => vm.execute_program()?,
+        _ => panic!("{}", USAGE),
+    }
 
     Ok(())
 }
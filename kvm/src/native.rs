@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use crate::{error::KvmError, kvm::Kvm, value::Value};
+
+/// A host function called by `callnative`, with the arguments it was called
+/// with in the order they were pushed. Its result replaces the arguments.
+pub type NativeFn = fn(&mut Kvm, &[Value]) -> Result<Value, KvmError>;
+
+#[derive(Debug, Clone)]
+pub struct Native {
+    pub name: String,
+    pub function: NativeFn,
+}
+
+/// Natives every `Kvm` starts with, their position being their id.
+pub static DEFAULT_NATIVES: [(&str, NativeFn); 4] = [
+    ("print", print),
+    ("readline", readline),
+    ("clock", clock),
+    ("random", random),
+];
+
+/// Id of the default native called `name`.
+pub fn native_id(name: &str) -> Option<u32> {
+    DEFAULT_NATIVES
+        .iter()
+        .position(|(native, _)| *native == name)
+        .map(|id| id as u32)
+}
+
+/// Name of the default native with the given id.
+pub fn native_name(id: u32) -> Option<&'static str> {
+    DEFAULT_NATIVES.get(id as usize).map(|(name, _)| *name)
+}
+
+/// Writes its arguments separated by spaces and a line break.
+fn print(vm: &mut Kvm, args: &[Value]) -> Result<Value, KvmError> {
+    let line = args
+        .iter()
+        .map(|arg| vm.get_heap().display(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    writeln!(vm.output(), "{}", line)?;
+    Ok(Value::Null)
+}
+
+/// Reads a line like `input` does.
+fn readline(vm: &mut Kvm, args: &[Value]) -> Result<Value, KvmError> {
+    expect_args("readline", args, 0)?;
+
+    let mut line = String::new();
+    match vm.input().read_line(&mut line)? {
+        0 => Ok(Value::Null),
+        _ => vm.new_string(line.trim_end_matches(['\n', '\r'])),
+    }
+}
+
+/// Milliseconds since the vm was created, see `clock_millis`.
+fn clock(vm: &mut Kvm, args: &[Value]) -> Result<Value, KvmError> {
+    expect_args("clock", args, 0)?;
+
+    Ok(Value::Int(clock_millis(vm.started().elapsed())))
+}
+
+/// What `clock` returns once `elapsed` passed since the vm was created: the
+/// elapsed milliseconds, wrapping back to 0 every 2^31 of them, about 24.8
+/// days, so they always fit a non-negative int. Programs measuring longer
+/// durations have to account for the wrap.
+pub fn clock_millis(elapsed: Duration) -> i32 {
+    (elapsed.as_millis() % (1 << 31)) as i32
+}
+
+/// A random non-negative integer, below the argument when there's one.
+fn random(vm: &mut Kvm, args: &[Value]) -> Result<Value, KvmError> {
+    let n = (vm.next_random() >> 33) as i32;
+
+    match args {
+        [] => Ok(Value::Int(n)),
+        [Value::Int(bound)] if *bound > 0 => Ok(Value::Int(n % bound)),
+        [Value::Int(bound)] => Err(native_error(
+            "random",
+            format!("expected a positive bound, got {}", bound),
+        )),
+        [value] => Err(KvmError::TypeMismatch {
+            instruction: "random",
+            expected: "int",
+            found: value.type_name(),
+        }),
+        _ => expect_args("random", args, 1).map(|_| Value::Null),
+    }
+}
+
+fn expect_args(name: &str, args: &[Value], expected: usize) -> Result<(), KvmError> {
+    match args.len() == expected {
+        true => Ok(()),
+        false => Err(native_error(
+            name,
+            format!("expected {} argument(s), got {}", expected, args.len()),
+        )),
+    }
+}
+
+fn native_error(name: &str, message: String) -> KvmError {
+    KvmError::Native {
+        name: name.to_string(),
+        message,
+    }
+}
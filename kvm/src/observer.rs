@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::{heap::Heap, instruction::Instruction, value::Value};
+
+/// Hook called by `Kvm` after every executed instruction. A `Kvm` without an
+/// observer skips the call entirely.
+pub trait Observer {
+    /// `ip` is the address `inst` was executed from and `stack` the stack it
+    /// left behind, its strings and arrays living on `heap`.
+    fn on_instruction(&mut self, ip: usize, inst: &Instruction, stack: &[Value], heap: &Heap);
+
+    /// Writes what was observed once the program stopped.
+    fn report(&self, _output: &mut dyn Write) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Logs every executed instruction with its address and the top of the stack.
+pub struct Tracer<W: Write> {
+    output: W,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(output: W) -> Self {
+        Tracer { output }
+    }
+}
+
+impl<W: Write> Observer for Tracer<W> {
+    fn on_instruction(&mut self, ip: usize, inst: &Instruction, stack: &[Value], heap: &Heap) {
+        let top = match stack.last() {
+            Some(value) => heap.display(value),
+            None => "[Empty]".to_string(),
+        };
+
+        // a broken trace must not stop the program
+        let _ = writeln!(
+            self.output,
+            "{:>6}: {:<24} top: {}",
+            ip,
+            inst.to_string(),
+            top
+        );
+    }
+}
+
+/// Counts executed instructions per opcode and per address.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    pub opcodes: BTreeMap<&'static str, u64>,
+    pub addresses: BTreeMap<usize, u64>,
+    instructions: BTreeMap<usize, String>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.opcodes.values().sum()
+    }
+}
+
+impl Observer for Profiler {
+    fn on_instruction(&mut self, ip: usize, inst: &Instruction, _stack: &[Value], _heap: &Heap) {
+        *self.opcodes.entry(inst.name()).or_default() += 1;
+        *self.addresses.entry(ip).or_default() += 1;
+        self.instructions
+            .entry(ip)
+            .or_insert_with(|| inst.to_string());
+    }
+
+    fn report(&self, output: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(output, "{} instructions executed", self.total())?;
+
+        let mut opcodes: Vec<_> = self.opcodes.iter().collect();
+        opcodes.sort_by(|a, b| b.1.cmp(a.1));
+
+        writeln!(output, "\nPer opcode:")?;
+        for (name, count) in opcodes {
+            writeln!(output, "{:>10}  {}", count, name)?;
+        }
+
+        // hottest addresses first, this is where loops show up
+        let mut addresses: Vec<_> = self.addresses.iter().collect();
+        addresses.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        writeln!(output, "\nPer address:")?;
+        for (ip, count) in addresses {
+            writeln!(
+                output,
+                "{:>10}  {:>6}: {}",
+                count, ip, self.instructions[ip]
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+
+use crate::{
+    bytecode::{DebugInfo, Program},
+    instruction::Instruction,
+};
+
+/// Peephole optimizations for programs that pass `verify`: constants are
+/// folded, pushes that are popped right away and jumps to the next
+/// instruction are removed, conditional jumps on constants become plain
+/// jumps, jump chains are collapsed and unreachable code is removed. Jump,
+/// call and closure targets are rewritten to follow the removed
+/// instructions.
+pub fn optimize(instructions: &[Instruction]) -> Vec<Instruction> {
+    optimize_program(&Program::new(instructions.to_vec())).instructions
+}
+
+/// Same as `optimize`, keeping the entry point and the debug info of the
+/// instructions that are left in sync.
+pub fn optimize_program(program: &Program) -> Program {
+    let mut optimizer = Optimizer {
+        slots: program.instructions.iter().cloned().map(Some).collect(),
+        debug_info: program.debug_info.clone(),
+        entry: program.entry as usize,
+    };
+
+    loop {
+        let mut changed = optimizer.collapse_jumps();
+        changed |= optimizer.rewrite();
+        changed |= optimizer.compact();
+        optimizer.remove_unreachable();
+        changed |= optimizer.compact();
+
+        if !changed {
+            break;
+        }
+    }
+
+    Program {
+        instructions: optimizer.slots.into_iter().flatten().collect(),
+        debug_info: optimizer.debug_info,
+        entry: optimizer.entry as u32,
+    }
+}
+
+/// Removed instructions are `None` until the slots are compacted.
+struct Optimizer {
+    slots: Vec<Option<Instruction>>,
+    debug_info: Option<DebugInfo>,
+    entry: usize,
+}
+
+impl Optimizer {
+    fn instruction(&self, ip: usize) -> Option<&Instruction> {
+        self.slots.get(ip).and_then(Option::as_ref)
+    }
+
+    /// Addresses something jumps to, which optimizations must not merge
+    /// with the instructions before them.
+    fn targets(&self) -> HashSet<usize> {
+        self.slots
+            .iter()
+            .flatten()
+            .filter_map(|inst| target(inst).map(|target| target as usize))
+            .chain([self.entry])
+            .collect()
+    }
+
+    /// Points jumps to a `jmp` at where the chain of jumps ends, and replaces
+    /// jumps to a `halt` or `ret` with it.
+    fn collapse_jumps(&mut self) -> bool {
+        let mut changed = false;
+
+        for ip in 0..self.slots.len() {
+            let Some(mut addr) = self.instruction(ip).and_then(target) else {
+                continue;
+            };
+
+            // jump chains can loop, in which case the loop is kept as it is
+            let mut seen = HashSet::from([addr]);
+            while let Some(Instruction::Jmp(next)) = self.instruction(addr as usize) {
+                if !seen.insert(*next) {
+                    break;
+                }
+                addr = *next;
+            }
+
+            let collapsed = match (&self.slots[ip], self.instruction(addr as usize)) {
+                (Some(Instruction::Jmp(_)), Some(end @ (Instruction::Halt | Instruction::Ret))) => {
+                    end.clone()
+                }
+                (Some(inst), _) => retarget(inst, addr),
+                (None, _) => continue,
+            };
+
+            if self.slots[ip].as_ref() != Some(&collapsed) {
+                self.slots[ip] = Some(collapsed);
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Replaces short sequences of instructions with cheaper ones. A
+    /// sequence never spans a jump target, except for its first instruction.
+    fn rewrite(&mut self) -> bool {
+        let targets = self.targets();
+        let mut changed = false;
+        let mut ip = 0;
+
+        while ip < self.slots.len() {
+            let window: Vec<&Instruction> = (ip..self.slots.len())
+                .take(3)
+                .take_while(|idx| *idx == ip || !targets.contains(idx))
+                .map_while(|idx| self.instruction(idx))
+                .collect();
+
+            match peephole(ip, &window) {
+                Some((replacement, len)) => {
+                    self.slots[ip] = replacement;
+                    for idx in ip + 1..ip + len {
+                        self.slots[idx] = None;
+                    }
+                    changed = true;
+                    ip += len;
+                }
+                None => ip += 1,
+            }
+        }
+
+        changed
+    }
+
+    /// Removes the instructions that can't be reached from the entry point.
+    fn remove_unreachable(&mut self) {
+        let mut reachable = vec![false; self.slots.len()];
+        let mut worklist = vec![self.entry];
+
+        while let Some(ip) = worklist.pop() {
+            let Some(inst) = self.instruction(ip) else {
+                continue;
+            };
+            if std::mem::replace(&mut reachable[ip], true) {
+                continue;
+            }
+
+            let falls_through = !matches!(
+                inst,
+                Instruction::Halt | Instruction::Ret | Instruction::Jmp(_)
+            );
+            if falls_through {
+                worklist.push(ip + 1);
+            }
+            worklist.extend(target(inst).map(|target| target as usize));
+        }
+
+        for (slot, reachable) in self.slots.iter_mut().zip(reachable) {
+            if !reachable {
+                *slot = None;
+            }
+        }
+    }
+
+    /// Drops the removed instructions, pointing jumps to a removed
+    /// instruction at the one that followed it.
+    fn compact(&mut self) -> bool {
+        let len = self.slots.len();
+        let mut addresses = Vec::with_capacity(len + 1);
+        let mut kept = 0;
+
+        for slot in &self.slots {
+            addresses.push(kept);
+            kept += slot.is_some() as usize;
+        }
+        addresses.push(kept);
+
+        if kept == len {
+            return false;
+        }
+
+        // targets outside of the program keep their distance to its end
+        let address = |addr: usize| match addresses.get(addr) {
+            Some(addr) => *addr,
+            None => addr - (len - kept),
+        };
+
+        if let Some(debug_info) = self.debug_info.as_mut() {
+            let kept = |values: &mut Vec<u32>| {
+                let mut slots = self.slots.iter();
+                values.retain(|_| slots.next().is_some_and(Option::is_some));
+            };
+            kept(&mut debug_info.lines);
+            kept(&mut debug_info.files);
+            debug_info
+                .labels
+                .values_mut()
+                .for_each(|addr| *addr = address(*addr as usize) as u32);
+        }
+        self.entry = address(self.entry);
+        self.slots = std::mem::take(&mut self.slots)
+            .into_iter()
+            .flatten()
+            .map(|inst| match target(&inst) {
+                Some(addr) => Some(retarget(&inst, address(addr as usize) as u32)),
+                None => Some(inst),
+            })
+            .collect();
+
+        true
+    }
+}
+
+/// The replacement for the instructions at the start of `window`, and how
+/// many of them it replaces.
+fn peephole(ip: usize, window: &[&Instruction]) -> Option<(Option<Instruction>, usize)> {
+    use Instruction::*;
+
+    let replacement = match window {
+        [Push(a), Push(b), op, ..] => (fold(*a, *b, op)?, 3),
+        [PushBool(a), PushBool(b), op, ..] => {
+            let result = match op {
+                And => *a && *b,
+                Or => *a || *b,
+                Eq => a == b,
+                Ne => a != b,
+                _ => return None,
+            };
+            (Some(PushBool(result)), 3)
+        }
+        [Push(n), Neg, ..] => (Some(Push(n.wrapping_neg())), 2),
+        [PushBool(b), Not, ..] => (Some(PushBool(!b)), 2),
+        [Push(_) | PushBool(_) | PushNull | PushStr(_) | Dup(_), Pop, ..] => (None, 2),
+        [condition @ (Push(_) | PushBool(_)), jump @ (JmpIf(target) | JmpIfNot(target)), ..] => {
+            let truthy = matches!(condition, Push(n) if *n != 0) || **condition == PushBool(true);
+            let jumps = truthy == matches!(jump, JmpIf(_));
+            (jumps.then_some(Jmp(*target)), 2)
+        }
+        [Jmp(target), ..] if *target as usize == ip + 1 => (None, 1),
+        _ => return None,
+    };
+
+    Some(replacement)
+}
+
+/// Folds an operation on two integers, leaving errors like a division by
+/// zero to the vm.
+fn fold(a: i32, b: i32, op: &Instruction) -> Option<Option<Instruction>> {
+    let result = match op {
+        Instruction::Add => Instruction::Push(a.wrapping_add(b)),
+        Instruction::Sub => Instruction::Push(a.wrapping_sub(b)),
+        Instruction::Mul => Instruction::Push(a.wrapping_mul(b)),
+        Instruction::Div if b != 0 => Instruction::Push(a.wrapping_div(b)),
+        Instruction::Mod if b != 0 => Instruction::Push(a.wrapping_rem(b)),
+        Instruction::Eq => Instruction::PushBool(a == b),
+        Instruction::Ne => Instruction::PushBool(a != b),
+        Instruction::Lt => Instruction::PushBool(a < b),
+        Instruction::Gt => Instruction::PushBool(a > b),
+        Instruction::Le => Instruction::PushBool(a <= b),
+        Instruction::Ge => Instruction::PushBool(a >= b),
+        _ => return None,
+    };
+
+    Some(Some(result))
+}
+
+/// Address an instruction jumps, calls or points a closure to.
+fn target(inst: &Instruction) -> Option<u32> {
+    match inst {
+        Instruction::Jmp(addr)
+        | Instruction::JmpIf(addr)
+        | Instruction::JmpIfNot(addr)
+        | Instruction::Call(addr, _)
+        | Instruction::Closure(addr, _) => Some(*addr),
+        _ => None,
+    }
+}
+
+fn retarget(inst: &Instruction, addr: u32) -> Instruction {
+    match inst {
+        Instruction::Jmp(_) => Instruction::Jmp(addr),
+        Instruction::JmpIf(_) => Instruction::JmpIf(addr),
+        Instruction::JmpIfNot(_) => Instruction::JmpIfNot(addr),
+        Instruction::Call(_, argc) => Instruction::Call(addr, *argc),
+        Instruction::Closure(_, n) => Instruction::Closure(addr, *n),
+        inst => inst.clone(),
+    }
+}
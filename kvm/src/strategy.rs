@@ -0,0 +1,94 @@
+use proptest::prelude::*;
+
+use crate::instruction::Instruction;
+
+/// Any instruction with any operands, for property tests of code working on
+/// kvm programs. Only built with the `proptest` feature.
+pub fn instruction() -> impl Strategy<Value = Instruction> {
+    instruction_with_targets(any::<u32>())
+}
+
+/// Same as `instruction`, with the addresses of jumps, calls and closures
+/// taken from `target`.
+pub fn instruction_with_targets(
+    target: impl Strategy<Value = u32> + Clone + 'static,
+) -> impl Strategy<Value = Instruction> {
+    prop_oneof![
+        Just(Instruction::Halt),
+        Just(Instruction::Add),
+        Just(Instruction::Sub),
+        Just(Instruction::Div),
+        Just(Instruction::Mul),
+        Just(Instruction::Eq),
+        Just(Instruction::PushNull),
+        Just(Instruction::Print),
+        Just(Instruction::Input),
+        Just(Instruction::Ret),
+        prop::sample::select(vec![
+            Instruction::Lt,
+            Instruction::Gt,
+            Instruction::Le,
+            Instruction::Ge,
+            Instruction::Ne,
+            Instruction::Not,
+            Instruction::And,
+            Instruction::Or,
+            Instruction::Mod,
+            Instruction::Neg,
+            Instruction::Pop,
+            Instruction::Swap,
+            Instruction::Over,
+            Instruction::Rot,
+            Instruction::ArrayGet,
+            Instruction::ArraySet,
+            Instruction::ArrayLen,
+            Instruction::Concat,
+        ]),
+        any::<i32>().prop_map(Instruction::Push),
+        any::<bool>().prop_map(Instruction::PushBool),
+        any::<String>().prop_map(Instruction::PushStr),
+        target.clone().prop_map(Instruction::Jmp),
+        target.clone().prop_map(Instruction::JmpIf),
+        target.clone().prop_map(Instruction::JmpIfNot),
+        any::<u32>().prop_map(Instruction::Dup),
+        (target.clone(), any::<u32>()).prop_map(|(addr, argc)| Instruction::Call(addr, argc)),
+        any::<u32>().prop_map(Instruction::LoadLocal),
+        any::<u32>().prop_map(Instruction::StoreLocal),
+        any::<u32>().prop_map(Instruction::LoadGlobal),
+        any::<u32>().prop_map(Instruction::StoreGlobal),
+        any::<u32>().prop_map(Instruction::NewArray),
+        (target, any::<u32>()).prop_map(|(addr, n)| Instruction::Closure(addr, n)),
+        any::<u32>().prop_map(Instruction::CallClosure),
+        (any::<u32>(), any::<u32>()).prop_map(|(id, argc)| Instruction::CallNative(id, argc)),
+    ]
+}
+
+/// Instructions working on integers and booleans on the stack, without
+/// jumps, calls or i/o. Their operands are small, so constants often fold
+/// and `dup` usually finds a value.
+pub fn stack_instruction() -> impl Strategy<Value = Instruction> {
+    prop_oneof![
+        (-4..4).prop_map(Instruction::Push),
+        Just(Instruction::Push(i32::MIN)),
+        any::<bool>().prop_map(Instruction::PushBool),
+        Just(Instruction::PushNull),
+        (0..3u32).prop_map(Instruction::Dup),
+        prop::sample::select(vec![
+            Instruction::Add,
+            Instruction::Sub,
+            Instruction::Mul,
+            Instruction::Div,
+            Instruction::Mod,
+            Instruction::Neg,
+            Instruction::Eq,
+            Instruction::Ne,
+            Instruction::Lt,
+            Instruction::Ge,
+            Instruction::Not,
+            Instruction::And,
+            Instruction::Or,
+            Instruction::Pop,
+            Instruction::Swap,
+        ]),
+    ]
+}
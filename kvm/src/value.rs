@@ -0,0 +1,34 @@
+use crate::heap::ObjRef;
+
+/// A value on the stack. Strings, arrays and closures live on the heap and
+/// are displayed and compared through it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Bool(bool),
+    Str(ObjRef),
+    Array(ObjRef),
+    Closure(ObjRef),
+    Null,
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "string",
+            Value::Array(_) => "array",
+            Value::Closure(_) => "closure",
+            Value::Null => "null",
+        }
+    }
+
+    /// The heap object the value refers to, if any.
+    pub fn obj_ref(&self) -> Option<ObjRef> {
+        match self {
+            Value::Str(obj) | Value::Array(obj) | Value::Closure(obj) => Some(*obj),
+            Value::Int(_) | Value::Bool(_) | Value::Null => None,
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{error::KvmError, instruction::Instruction};
+
+/// Checks a program before it runs: every jump and call target has to be an
+/// instruction of the program, and no instruction may need more values than
+/// the stack holds on some path leading to it. Possible underflows are
+/// rejected, not only the ones every path runs into, as branches are not
+/// evaluated.
+///
+/// Returns the smallest stack depth each instruction can be reached with,
+/// relative to the base of the frame it runs in, or `None` for unreachable
+/// instructions. Functions are entered with their arguments as the stack.
+pub fn verify(program: &[Instruction]) -> Result<Vec<Option<usize>>, KvmError> {
+    verify_from(program, 0)
+}
+
+/// Same as `verify`, for programs starting at `entry`.
+pub fn verify_from(program: &[Instruction], entry: usize) -> Result<Vec<Option<usize>>, KvmError> {
+    if entry != 0 && entry >= program.len() {
+        return Err(KvmError::InvalidEntry(entry));
+    }
+
+    for (ip, inst) in program.iter().enumerate() {
+        if let Instruction::Jmp(target)
+        | Instruction::JmpIf(target)
+        | Instruction::JmpIfNot(target)
+        | Instruction::Call(target, _)
+        | Instruction::Closure(target, _) = inst
+        {
+            if *target as usize >= program.len() {
+                return Err(KvmError::InvalidJumpTarget {
+                    ip,
+                    target: *target,
+                });
+            }
+        }
+    }
+
+    let mut depths = vec![None; program.len()];
+    let mut worklist = Vec::new();
+
+    if !program.is_empty() {
+        depths[entry] = Some(0);
+        worklist.push(entry);
+    }
+
+    // depths only ever decrease, so loops that grow the stack converge on
+    // the depth they are first entered with
+    while let Some(ip) = worklist.pop() {
+        let depth = depths[ip].unwrap_or_default();
+
+        for (next, next_depth) in successors(ip, &program[ip], depth) {
+            // falling off the end is caught by the vm
+            if next >= program.len() {
+                continue;
+            }
+
+            if depths[next].is_none_or(|known| next_depth < known) {
+                depths[next] = Some(next_depth);
+                worklist.push(next);
+            }
+        }
+    }
+
+    for (ip, inst) in program.iter().enumerate() {
+        if let Some(depth) = depths[ip] {
+            let needed = needed(inst);
+
+            if depth < needed {
+                return Err(KvmError::Underflow {
+                    ip,
+                    instruction: inst.to_string(),
+                    needed,
+                    depth,
+                });
+            }
+        }
+    }
+
+    Ok(depths)
+}
+
+/// Values an instruction reads from the top of its frame.
+fn needed(inst: &Instruction) -> usize {
+    match inst {
+        Instruction::Add
+        | Instruction::Sub
+        | Instruction::Div
+        | Instruction::Mul
+        | Instruction::Eq
+        | Instruction::Ne
+        | Instruction::Lt
+        | Instruction::Gt
+        | Instruction::Le
+        | Instruction::Ge
+        | Instruction::And
+        | Instruction::Or
+        | Instruction::Mod
+        | Instruction::Swap
+        | Instruction::Over
+        | Instruction::ArrayGet
+        | Instruction::Concat => 2,
+        Instruction::Rot | Instruction::ArraySet => 3,
+        Instruction::JmpIf(_)
+        | Instruction::JmpIfNot(_)
+        | Instruction::Not
+        | Instruction::Neg
+        | Instruction::Pop
+        | Instruction::Print
+        | Instruction::Ret
+        | Instruction::StoreGlobal(_)
+        | Instruction::ArrayLen => 1,
+        Instruction::Dup(n) | Instruction::LoadLocal(n) | Instruction::StoreLocal(n) => {
+            *n as usize + 1
+        }
+        Instruction::Call(_, argc)
+        | Instruction::NewArray(argc)
+        | Instruction::Closure(_, argc)
+        | Instruction::CallNative(_, argc) => *argc as usize,
+        Instruction::CallClosure(argc) => *argc as usize + 1,
+        Instruction::Halt
+        | Instruction::Push(_)
+        | Instruction::PushStr(_)
+        | Instruction::PushBool(_)
+        | Instruction::PushNull
+        | Instruction::Input
+        | Instruction::Jmp(_)
+        | Instruction::LoadGlobal(_) => 0,
+    }
+}
+
+/// Instructions that can run after `inst`, with the stack depth they start
+/// with.
+fn successors(ip: usize, inst: &Instruction, depth: usize) -> Vec<(usize, usize)> {
+    match inst {
+        Instruction::Halt | Instruction::Ret => vec![],
+        Instruction::Jmp(target) => vec![(*target as usize, depth)],
+        Instruction::JmpIf(target) | Instruction::JmpIfNot(target) => {
+            let depth = depth.saturating_sub(1);
+            vec![(ip + 1, depth), (*target as usize, depth)]
+        }
+        // the callee starts a frame holding its arguments, and returns a
+        // single value in their place
+        Instruction::Call(target, argc) => {
+            let argc = *argc as usize;
+            vec![
+                (ip + 1, depth.saturating_sub(argc) + 1),
+                (*target as usize, argc),
+            ]
+        }
+        Instruction::Add
+        | Instruction::Sub
+        | Instruction::Div
+        | Instruction::Mul
+        | Instruction::Eq
+        | Instruction::Ne
+        | Instruction::Lt
+        | Instruction::Gt
+        | Instruction::Le
+        | Instruction::Ge
+        | Instruction::And
+        | Instruction::Or
+        | Instruction::Mod
+        | Instruction::Pop
+        | Instruction::Print
+        | Instruction::StoreGlobal(_)
+        | Instruction::ArrayGet
+        | Instruction::Concat => vec![(ip + 1, depth.saturating_sub(1))],
+        Instruction::ArraySet => vec![(ip + 1, depth.saturating_sub(3))],
+        Instruction::Not
+        | Instruction::Neg
+        | Instruction::Swap
+        | Instruction::Rot
+        | Instruction::ArrayLen => vec![(ip + 1, depth)],
+        Instruction::NewArray(n) | Instruction::Closure(_, n) | Instruction::CallNative(_, n) => {
+            vec![(ip + 1, depth.saturating_sub(*n as usize) + 1)]
+        }
+        // closures are only known while running, so their bodies are checked
+        // by the vm
+        Instruction::CallClosure(argc) => {
+            vec![(ip + 1, depth.saturating_sub(*argc as usize + 1) + 1)]
+        }
+        // storing right past the last local declares a new one
+        Instruction::StoreLocal(n) if *n as usize + 1 == depth => vec![(ip + 1, depth)],
+        Instruction::StoreLocal(_) => vec![(ip + 1, depth.saturating_sub(1))],
+        Instruction::Push(_)
+        | Instruction::PushStr(_)
+        | Instruction::PushBool(_)
+        | Instruction::PushNull
+        | Instruction::Input
+        | Instruction::Dup(_)
+        | Instruction::Over
+        | Instruction::LoadLocal(_)
+        | Instruction::LoadGlobal(_) => vec![(ip + 1, depth + 1)],
+    }
+}
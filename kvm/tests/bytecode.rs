@@ -0,0 +1,274 @@
+use std::collections::BTreeMap;
+
+use kvm::strategy::instruction;
+use kvm::{
+    ConstantTable, DebugInfo, Instruction, Kvm, KvmError, Program, SourceFile, FORMAT_VERSION,
+    MAGIC,
+};
+use proptest::prelude::*;
+
+#[test]
+fn given_a_program_it_should_write_the_header_and_sections() {
+    let program = Program::new(vec![
+        Instruction::PushStr("hi".to_string()),
+        Instruction::Print,
+        Instruction::Halt,
+    ]);
+
+    let mut expected = Vec::new();
+    expected.extend(MAGIC);
+    expected.extend(FORMAT_VERSION.to_le_bytes());
+    expected.push(0);
+    expected.extend(1u32.to_le_bytes());
+    expected.extend(2u32.to_le_bytes());
+    expected.extend(b"hi");
+    expected.extend(7u32.to_le_bytes());
+    expected.push(Instruction::PushStr(String::new()).upcode());
+    expected.extend(0u32.to_le_bytes());
+    expected.push(Instruction::Print.upcode());
+    expected.push(Instruction::Halt.upcode());
+
+    assert_eq!(program.to_bytes(), expected);
+}
+
+#[test]
+fn given_an_entry_point_it_should_store_it_after_the_flags() {
+    let mut program = Program::new(vec![Instruction::Halt, Instruction::Halt]);
+    program.entry = 1;
+
+    let bytes = program.to_bytes();
+
+    assert_eq!(bytes[MAGIC.len() + 2] & 0x2, 0x2);
+    assert_eq!(bytes[MAGIC.len() + 3..MAGIC.len() + 7], 1u32.to_le_bytes());
+    assert_eq!(Program::from_bytes(&bytes).unwrap(), program);
+}
+
+#[test]
+fn given_a_program_it_should_locate_every_instruction_in_its_bytes() {
+    let program = Program::new(vec![
+        Instruction::Push(7),
+        Instruction::Call(0, 1),
+        Instruction::Halt,
+    ]);
+
+    let encoded = program.encode();
+
+    assert_eq!(encoded.bytes, program.to_bytes());
+    assert_eq!(encoded.instructions, vec![15..20, 20..29, 29..30]);
+    assert_eq!(
+        encoded.bytes[encoded.instructions[1].clone()],
+        [0x13, 0, 0, 0, 0, 1, 0, 0, 0]
+    );
+}
+
+#[test]
+fn given_repeated_strings_it_should_store_them_once() {
+    let mut constants = ConstantTable::new();
+
+    assert_eq!(constants.intern("foo"), 0);
+    assert_eq!(constants.intern("bar \"baz\""), 1);
+    assert_eq!(constants.intern("foo"), 0);
+    assert_eq!(constants.len(), 2);
+    assert_eq!(constants.get(1), Some("bar \"baz\""));
+}
+
+#[test]
+fn given_an_encoded_program_it_should_decode_the_same_program() {
+    let mut program = Program::new(vec![
+        Instruction::PushStr("hello \"world\"".to_string()),
+        Instruction::PushStr("héllo".to_string()),
+        Instruction::PushStr("hello \"world\"".to_string()),
+        Instruction::Eq,
+        Instruction::Push(-42),
+        Instruction::PushBool(true),
+        Instruction::Call(9, 2),
+        Instruction::Print,
+        Instruction::Halt,
+    ]);
+
+    assert_eq!(Program::from_bytes(&program.to_bytes()).unwrap(), program);
+
+    program.debug_info = Some(DebugInfo {
+        lines: vec![1, 2, 3, 4, 5, 6, 7, 1, 2],
+        files: vec![0, 0, 0, 0, 0, 0, 0, 1, 1],
+        sources: vec![
+            SourceFile {
+                name: "main.ksm".to_string(),
+                included_at: 0,
+            },
+            SourceFile {
+                name: "lib/square.ksm".to_string(),
+                included_at: 8,
+            },
+        ],
+        labels: BTreeMap::from([("main".to_string(), 0), ("square".to_string(), 7)]),
+    });
+
+    assert_eq!(Program::from_bytes(&program.to_bytes()).unwrap(), program);
+}
+
+fn code_section(code: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend(MAGIC);
+    bytes.extend(FORMAT_VERSION.to_le_bytes());
+    bytes.push(0);
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend((code.len() as u32).to_le_bytes());
+    bytes.extend(code);
+    bytes
+}
+
+#[test]
+fn given_malformed_programs_it_should_return_an_error() {
+    let unknown_opcode = code_section(&[Instruction::Halt.upcode(), 0xff]);
+    let truncated_operand = code_section(&[Instruction::Push(0).upcode(), 1, 0]);
+    let missing_constant =
+        code_section(&[Instruction::PushStr(String::new()).upcode(), 3, 0, 0, 0]);
+
+    let mut invalid_utf8 = Vec::new();
+    invalid_utf8.extend(MAGIC);
+    invalid_utf8.extend(FORMAT_VERSION.to_le_bytes());
+    invalid_utf8.push(0);
+    invalid_utf8.extend(1u32.to_le_bytes());
+    invalid_utf8.extend(2u32.to_le_bytes());
+    invalid_utf8.extend([0xc3, 0x28]);
+
+    let mut unsupported_version = code_section(&[]);
+    unsupported_version[4] = 42;
+
+    let invalid_bool = code_section(&[Instruction::PushBool(true).upcode(), 2]);
+
+    let mut program = Program::new(vec![Instruction::Push(1), Instruction::Halt]);
+    program.debug_info = Some(DebugInfo::new(vec![1]));
+    let missing_debug_line = program.to_bytes();
+
+    program.debug_info = Some(DebugInfo {
+        files: vec![0, 1],
+        ..DebugInfo::new(vec![1, 2])
+    });
+    let missing_source_file = program.to_bytes();
+
+    assert!(matches!(
+        Program::from_bytes(&unknown_opcode),
+        Err(KvmError::UnknownOpcode {
+            upcode: 0xff,
+            offset: 16
+        })
+    ));
+    assert!(matches!(
+        Program::from_bytes(&truncated_operand),
+        Err(KvmError::TruncatedOperand { offset: 16 })
+    ));
+    assert!(matches!(
+        Program::from_bytes(&missing_constant),
+        Err(KvmError::InvalidConstant { idx: 3, offset: 16 })
+    ));
+    assert!(matches!(
+        Program::from_bytes(&invalid_utf8),
+        Err(KvmError::InvalidUtf8 { offset: 15 })
+    ));
+    assert!(matches!(
+        Program::from_bytes(b"ELF\0"),
+        Err(KvmError::InvalidMagic)
+    ));
+    assert!(matches!(
+        Program::from_bytes(&unsupported_version),
+        Err(KvmError::UnsupportedVersion(42))
+    ));
+    assert!(matches!(
+        Program::from_bytes(&invalid_bool),
+        Err(KvmError::InvalidBool {
+            byte: 2,
+            offset: 16
+        })
+    ));
+    assert!(matches!(
+        Program::from_bytes(&missing_debug_line),
+        Err(KvmError::InvalidDebugInfo {
+            lines: 1,
+            instructions: 2
+        })
+    ));
+    assert!(matches!(
+        Program::from_bytes(&missing_source_file),
+        Err(KvmError::InvalidSourceFile { ip: 1, file: 1 })
+    ));
+}
+
+#[test]
+fn given_a_missing_file_it_should_return_an_io_error() {
+    let mut vm = Kvm::new();
+
+    assert!(matches!(
+        vm.load_program_from_file("does/not/exist.kvm"),
+        Err(KvmError::Io(_))
+    ));
+}
+
+fn program() -> impl Strategy<Value = Program> {
+    prop::collection::vec(instruction(), 0..64)
+        .prop_flat_map(|instructions| {
+            let len = instructions.len();
+            (
+                Just(instructions),
+                prop::option::of(debug_info(len)),
+                prop_oneof![Just(0), any::<u32>()],
+            )
+        })
+        .prop_map(|(instructions, debug_info, entry)| Program {
+            instructions,
+            debug_info,
+            entry,
+        })
+}
+
+fn debug_info(len: usize) -> impl Strategy<Value = DebugInfo> {
+    let source = (any::<String>(), any::<u32>())
+        .prop_map(|(name, included_at)| SourceFile { name, included_at });
+
+    prop::collection::vec(source, 1..4).prop_flat_map(move |sources| {
+        let files = prop::collection::vec(0..sources.len() as u32, len);
+        (
+            prop::collection::vec(any::<u32>(), len),
+            files,
+            Just(sources),
+            prop::collection::btree_map("[a-z_][a-z0-9_.]{0,8}", any::<u32>(), 0..4),
+        )
+            .prop_map(|(lines, files, sources, labels)| DebugInfo {
+                lines,
+                files,
+                sources,
+                labels,
+            })
+    })
+}
+
+proptest! {
+    #[test]
+    fn given_any_program_it_should_round_trip_through_bytes(program in program()) {
+        prop_assert_eq!(Program::from_bytes(&program.to_bytes()).unwrap(), program);
+    }
+
+    #[test]
+    fn given_any_instruction_it_should_decode_its_encoding(inst in instruction()) {
+        let mut constants = ConstantTable::new();
+        let code = inst.as_bytes(&mut constants);
+        let program = Program::new(vec![inst.clone()]).to_bytes();
+
+        prop_assert!(program.ends_with(&code));
+        prop_assert_eq!(Program::from_bytes(&program).unwrap().instructions, vec![inst]);
+    }
+
+    #[test]
+    fn given_a_truncated_program_it_should_return_an_error(program in program(), cut in any::<prop::sample::Index>()) {
+        let bytes = program.to_bytes();
+        let len = cut.index(bytes.len());
+
+        prop_assert!(Program::from_bytes(&bytes[..len]).is_err());
+    }
+
+    #[test]
+    fn given_arbitrary_bytes_it_should_not_panic(code in prop::collection::vec(any::<u8>(), 0..256)) {
+        let _ = Program::from_bytes(&code_section(&code));
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+
+use kvm::{
+    DebugInfo, Debugger, Instruction, Kvm, KvmBuilder, KvmError, Program, SourceFile, Value,
+};
+
+fn debugger(program: Program) -> Debugger {
+    let mut vm = Kvm::new();
+    vm.load_program(program);
+    Debugger::new(vm).unwrap()
+}
+
+fn run(debugger: &mut Debugger, commands: &str) -> String {
+    let mut output = Vec::new();
+    debugger.run(commands.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+fn square() -> Program {
+    Program {
+        instructions: vec![
+            Instruction::Push(7),
+            Instruction::Call(4, 1),
+            Instruction::PushStr("done".to_string()),
+            Instruction::Halt,
+            Instruction::LoadLocal(0),
+            Instruction::LoadLocal(0),
+            Instruction::Mul,
+            Instruction::Ret,
+        ],
+        debug_info: Some(DebugInfo {
+            labels: BTreeMap::from([("main".to_string(), 0), ("square".to_string(), 4)]),
+            ..DebugInfo::new(vec![1, 2, 3, 4, 6, 7, 8, 9])
+        }),
+        entry: 0,
+    }
+}
+
+#[test]
+fn given_step_commands_it_should_execute_one_instruction_at_a_time() {
+    let mut debugger = debugger(square());
+
+    run(&mut debugger, "step\ns 2\n");
+
+    assert_eq!(debugger.vm().get_ip(), 5);
+    assert_eq!(debugger.vm().get_frames().len(), 1);
+    assert_eq!(debugger.vm().get_stack(), [Value::Int(7), Value::Int(7)]);
+}
+
+#[test]
+fn given_breakpoints_it_should_stop_before_them_when_continuing() {
+    let mut debugger = debugger(square());
+
+    let output = run(&mut debugger, "b 6\nb line 3\nc\nframes\nc\nstack\nc\n");
+
+    assert!(output.contains("Breakpoint at 6\n=> *   6: mul (line 8)"));
+    assert!(output.contains("#0 return to 2, locals from stack slot 0"));
+    assert!(output.contains("Breakpoint at 2\n=> *   2: pushstr \"done\" (line 3)"));
+    assert!(output.contains("   0: 49\n"));
+    assert!(output.ends_with("Program halted\n(kdb) "));
+    assert!(debugger.vm().is_halted());
+}
+
+#[test]
+fn given_a_list_command_it_should_disassemble_around_ip() {
+    let mut debugger = debugger(square());
+
+    let output = run(&mut debugger, "s 3\nl 3\nq\nstep\n");
+
+    assert!(output.contains(
+        "       4: loadlocal 0 (line 6)\n=>     5: loadlocal 0 (line 7)\n       6: mul (line 8)\n"
+    ));
+    assert_eq!(debugger.vm().get_ip(), 5);
+}
+
+#[test]
+fn given_a_label_it_should_break_after_it() {
+    let mut debugger = debugger(square());
+
+    let output = run(
+        &mut debugger,
+        "b square
+b nowhere
+c
+",
+    );
+
+    assert!(output.contains("Breakpoint at 4\n"));
+    assert!(output.contains("No instruction after label nowhere\n"));
+    assert!(output.contains("=> *   4: loadlocal 0 (line 6)"));
+    assert_eq!(debugger.vm().get_frames().len(), 1);
+}
+
+#[test]
+fn given_instructions_of_an_included_file_it_should_name_the_file() {
+    let mut program = Program::new(vec![Instruction::Push(1), Instruction::Halt]);
+    program.debug_info = Some(DebugInfo {
+        lines: vec![2, 1],
+        files: vec![0, 1],
+        sources: vec![
+            SourceFile::default(),
+            SourceFile {
+                name: "lib/halt.ksm".to_string(),
+                included_at: 3,
+            },
+        ],
+        ..Default::default()
+    });
+    let mut debugger = debugger(program);
+
+    let output = run(
+        &mut debugger,
+        "l
+b line 1
+",
+    );
+
+    assert!(output.contains("=>     0: push 1 (line 2)\n       1: halt (line 1 of lib/halt.ksm)\n"));
+    assert!(output.contains("No instruction on line 1\n"));
+}
+
+#[test]
+fn given_a_program_failing_verification_it_should_not_start() {
+    let mut vm = Kvm::new();
+    vm.load_program(Program::new(vec![Instruction::Add, Instruction::Halt]));
+
+    assert!(matches!(
+        Debugger::new(vm),
+        Err(KvmError::Underflow { ip: 0, .. })
+    ));
+}
+
+#[test]
+fn given_a_failing_program_it_should_report_the_error_and_keep_its_state() {
+    let mut vm = KvmBuilder::new().verify(false).build();
+    vm.load_program(Program::new(vec![
+        Instruction::Push(1),
+        Instruction::Add,
+        Instruction::Halt,
+    ]));
+    let mut debugger = Debugger::new(vm).unwrap();
+
+    let output = run(&mut debugger, "c\nstack\nb line 1\n");
+
+    assert!(output.contains("Error: Stack underflow"));
+    assert!(output.contains("[Empty]"));
+    assert!(output.contains("The program has no debug info"));
+    assert_eq!(debugger.vm().get_ip(), 1);
+}
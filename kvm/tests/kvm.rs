@@ -0,0 +1,794 @@
+use std::io::{Cursor, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use kvm::{clock_millis, Instruction, Kvm, KvmBuilder, KvmError, Profiler, Program, Tracer, Value};
+
+fn run(program: Vec<Instruction>) -> Result<Vec<Value>, KvmError> {
+    let mut vm = Kvm::new();
+    vm.load_program_from_vec(program);
+    vm.execute_program()?;
+    Ok(vm.get_stack().to_vec())
+}
+
+#[test]
+fn given_a_call_it_should_return_the_result_in_place_of_the_arguments() {
+    let program = vec![
+        Instruction::Push(3),
+        Instruction::Push(4),
+        Instruction::Call(4, 2),
+        Instruction::Halt,
+        Instruction::LoadLocal(0),
+        Instruction::LoadLocal(1),
+        Instruction::Mul,
+        Instruction::Ret,
+    ];
+
+    assert_eq!(run(program).unwrap(), vec![Value::Int(12)]);
+}
+
+#[test]
+fn given_a_recursive_function_it_should_use_a_frame_per_call() {
+    let factorial = vec![
+        Instruction::Push(5),
+        Instruction::Call(3, 1),
+        Instruction::Halt,
+        Instruction::LoadLocal(0),
+        Instruction::JmpIf(7),
+        Instruction::Push(1),
+        Instruction::Ret,
+        Instruction::LoadLocal(0),
+        Instruction::LoadLocal(0),
+        Instruction::Push(-1),
+        Instruction::Add,
+        Instruction::Call(3, 1),
+        Instruction::Mul,
+        Instruction::Ret,
+    ];
+
+    assert_eq!(run(factorial).unwrap(), vec![Value::Int(120)]);
+}
+
+#[test]
+fn given_store_local_it_should_overwrite_or_declare_locals() {
+    let program = vec![
+        Instruction::Push(1),
+        Instruction::Push(2),
+        Instruction::StoreLocal(0),
+        Instruction::Push(3),
+        Instruction::StoreLocal(1),
+        Instruction::Halt,
+    ];
+
+    assert_eq!(run(program).unwrap(), vec![Value::Int(2), Value::Int(3)]);
+}
+
+#[test]
+fn given_globals_it_should_share_them_between_frames() {
+    let program = vec![
+        Instruction::Push(7),
+        Instruction::StoreGlobal(2),
+        Instruction::Call(5, 0),
+        Instruction::LoadGlobal(2),
+        Instruction::Halt,
+        Instruction::LoadGlobal(2),
+        Instruction::LoadGlobal(2),
+        Instruction::Mul,
+        Instruction::StoreGlobal(2),
+        Instruction::Push(0),
+        Instruction::Ret,
+    ];
+
+    assert_eq!(run(program).unwrap(), vec![Value::Int(0), Value::Int(49)]);
+}
+
+#[test]
+fn given_invalid_calls_it_should_return_an_error() {
+    let unbounded_recursion = vec![Instruction::Call(0, 0)];
+    let return_outside_call = vec![Instruction::Push(1), Instruction::Ret];
+    let undefined_global = vec![Instruction::LoadGlobal(0), Instruction::Halt];
+
+    assert!(matches!(
+        run(unbounded_recursion),
+        Err(KvmError::CallDepthExceeded(_))
+    ));
+    assert!(matches!(
+        run(return_outside_call),
+        Err(KvmError::ReturnOutsideCall)
+    ));
+    assert!(matches!(
+        run(undefined_global),
+        Err(KvmError::UndefinedGlobal(0))
+    ));
+}
+
+#[test]
+fn given_values_of_different_types_it_should_compare_them_uniformly() {
+    let program = vec![
+        Instruction::PushStr("foo".to_string()),
+        Instruction::PushStr("foo".to_string()),
+        Instruction::Eq,
+        Instruction::Push(1),
+        Instruction::PushBool(true),
+        Instruction::Eq,
+        Instruction::PushNull,
+        Instruction::PushNull,
+        Instruction::Eq,
+        Instruction::Halt,
+    ];
+
+    assert_eq!(
+        run(program).unwrap(),
+        vec![Value::Bool(true), Value::Bool(false), Value::Bool(true)]
+    );
+}
+
+#[test]
+fn given_strings_it_should_store_them_and_pass_them_to_functions() {
+    let program = vec![
+        Instruction::PushStr("kvm".to_string()),
+        Instruction::StoreGlobal(0),
+        Instruction::LoadGlobal(0),
+        Instruction::Call(5, 1),
+        Instruction::Halt,
+        Instruction::LoadLocal(0),
+        Instruction::PushStr("kvm".to_string()),
+        Instruction::Eq,
+        Instruction::Ret,
+    ];
+
+    assert_eq!(run(program).unwrap(), vec![Value::Bool(true)]);
+}
+
+#[test]
+fn given_arithmetic_on_non_integers_it_should_return_a_type_mismatch() {
+    let programs = [
+        vec![
+            Instruction::Push(1),
+            Instruction::PushStr("a".to_string()),
+            Instruction::Add,
+        ],
+        vec![
+            Instruction::PushBool(true),
+            Instruction::Push(1),
+            Instruction::Mul,
+        ],
+        vec![Instruction::PushNull, Instruction::JmpIf(0)],
+    ];
+
+    programs
+        .into_iter()
+        .for_each(|program| assert!(matches!(run(program), Err(KvmError::TypeMismatch { .. }))));
+}
+
+#[test]
+fn given_comparisons_it_should_compare_the_value_below_with_the_top() {
+    let compare = |a, b, inst| {
+        run(vec![
+            Instruction::Push(a),
+            Instruction::Push(b),
+            inst,
+            Instruction::Halt,
+        ])
+        .unwrap()
+    };
+
+    let t = vec![Value::Bool(true)];
+    let f = vec![Value::Bool(false)];
+
+    assert_eq!(compare(1, 2, Instruction::Lt), t);
+    assert_eq!(compare(2, 1, Instruction::Lt), f);
+    assert_eq!(compare(2, 1, Instruction::Gt), t);
+    assert_eq!(compare(2, 2, Instruction::Gt), f);
+    assert_eq!(compare(2, 2, Instruction::Le), t);
+    assert_eq!(compare(3, 2, Instruction::Le), f);
+    assert_eq!(compare(2, 2, Instruction::Ge), t);
+    assert_eq!(compare(1, 2, Instruction::Ge), f);
+    assert_eq!(compare(1, 2, Instruction::Ne), t);
+    assert_eq!(compare(2, 2, Instruction::Ne), f);
+    assert_eq!(
+        run(vec![
+            Instruction::PushStr("a".to_string()),
+            Instruction::PushNull,
+            Instruction::Ne,
+            Instruction::Halt,
+        ])
+        .unwrap(),
+        t
+    );
+}
+
+#[test]
+fn given_logic_operators_it_should_combine_booleans() {
+    let logic = |a, b, inst| {
+        run(vec![
+            Instruction::PushBool(a),
+            Instruction::PushBool(b),
+            inst,
+            Instruction::Halt,
+        ])
+        .unwrap()
+    };
+
+    assert_eq!(logic(true, false, Instruction::And), [Value::Bool(false)]);
+    assert_eq!(logic(true, true, Instruction::And), [Value::Bool(true)]);
+    assert_eq!(logic(false, true, Instruction::Or), [Value::Bool(true)]);
+    assert_eq!(logic(false, false, Instruction::Or), [Value::Bool(false)]);
+    assert_eq!(
+        run(vec![
+            Instruction::PushBool(false),
+            Instruction::Not,
+            Instruction::Halt
+        ])
+        .unwrap(),
+        [Value::Bool(true)]
+    );
+    assert!(matches!(
+        run(vec![Instruction::Push(1), Instruction::Not]),
+        Err(KvmError::TypeMismatch {
+            instruction: "not",
+            expected: "bool",
+            found: "int"
+        })
+    ));
+}
+
+#[test]
+fn given_mod_and_neg_it_should_compute_remainders_and_negations() {
+    assert_eq!(
+        run(vec![
+            Instruction::Push(-7),
+            Instruction::Push(3),
+            Instruction::Mod,
+            Instruction::Push(5),
+            Instruction::Neg,
+            Instruction::Push(i32::MIN),
+            Instruction::Neg,
+            Instruction::Halt,
+        ])
+        .unwrap(),
+        [Value::Int(-1), Value::Int(-5), Value::Int(i32::MIN)]
+    );
+    assert!(matches!(
+        run(vec![
+            Instruction::Push(1),
+            Instruction::Push(0),
+            Instruction::Mod
+        ]),
+        Err(KvmError::DivisionByZero)
+    ));
+}
+
+#[test]
+fn given_stack_manipulation_it_should_reorder_the_top_values() {
+    let program = |inst| {
+        run(vec![
+            Instruction::Push(1),
+            Instruction::Push(2),
+            Instruction::Push(3),
+            inst,
+            Instruction::Halt,
+        ])
+        .unwrap()
+    };
+    let ints = |values: &[i32]| values.iter().map(|n| Value::Int(*n)).collect::<Vec<_>>();
+
+    assert_eq!(program(Instruction::Pop), ints(&[1, 2]));
+    assert_eq!(program(Instruction::Swap), ints(&[1, 3, 2]));
+    assert_eq!(program(Instruction::Over), ints(&[1, 2, 3, 2]));
+    assert_eq!(program(Instruction::Rot), ints(&[2, 3, 1]));
+}
+
+#[test]
+fn given_jmpifnot_it_should_jump_on_falsy_values() {
+    let program = |condition| {
+        run(vec![
+            condition,
+            Instruction::JmpIfNot(4),
+            Instruction::Push(1),
+            Instruction::Halt,
+            Instruction::Push(2),
+            Instruction::Halt,
+        ])
+        .unwrap()
+    };
+
+    assert_eq!(program(Instruction::PushBool(false)), [Value::Int(2)]);
+    assert_eq!(program(Instruction::Push(0)), [Value::Int(2)]);
+    assert_eq!(program(Instruction::PushBool(true)), [Value::Int(1)]);
+    assert_eq!(program(Instruction::Push(3)), [Value::Int(1)]);
+}
+
+fn count_to(limit: i32) -> Vec<Instruction> {
+    vec![
+        Instruction::Push(0),
+        Instruction::Push(1),
+        Instruction::Add,
+        Instruction::Dup(0),
+        Instruction::Push(limit),
+        Instruction::Eq,
+        Instruction::JmpIf(8),
+        Instruction::Jmp(1),
+        Instruction::Halt,
+    ]
+}
+
+#[test]
+fn given_a_long_program_it_should_run_until_it_halts() {
+    assert_eq!(run(count_to(5000)).unwrap(), vec![Value::Int(5000)]);
+}
+
+#[test]
+fn given_a_fuel_limit_it_should_stop_programs_that_run_out_of_it() {
+    let mut vm = Kvm::new();
+    vm.load_program_from_vec(count_to(5000));
+    vm.set_fuel(Some(1000));
+
+    assert!(matches!(vm.execute_program(), Err(KvmError::OutOfFuel)));
+    assert_eq!(vm.get_fuel(), Some(0));
+
+    let mut vm = Kvm::new();
+    vm.load_program_from_vec(count_to(10));
+    vm.set_fuel(Some(1000));
+
+    assert!(vm.execute_program().is_ok());
+    assert_eq!(vm.get_fuel(), Some(1000 - 7 * 10 - 1));
+}
+
+#[test]
+fn given_an_ip_outside_of_the_program_it_should_return_an_error() {
+    let missing_halt = vec![Instruction::Push(1)];
+    let jump_too_far = vec![Instruction::Jmp(42)];
+
+    assert!(matches!(run(missing_halt), Err(KvmError::IpOutOfRange(1))));
+
+    // the verifier rejects the jump before it runs
+    let mut vm = KvmBuilder::new().verify(false).build();
+    vm.load_program_from_vec(jump_too_far);
+
+    assert!(matches!(
+        vm.execute_program(),
+        Err(KvmError::IpOutOfRange(42))
+    ));
+}
+
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn given_an_output_handle_it_should_print_to_it() {
+    let output = SharedOutput::default();
+    let mut vm = KvmBuilder::new().output(output.clone()).build();
+    vm.load_program_from_vec(vec![
+        Instruction::PushStr("hello".to_string()),
+        Instruction::Print,
+        Instruction::Push(42),
+        Instruction::Print,
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    assert_eq!(output.0.lock().unwrap().as_slice(), b"hello\n42\n");
+}
+
+#[test]
+fn given_an_input_handle_it_should_read_lines_until_null() {
+    let mut vm = KvmBuilder::new()
+        .input(Cursor::new("first\r\nsecond\n"))
+        .build();
+    vm.load_program_from_vec(vec![
+        Instruction::Input,
+        Instruction::Input,
+        Instruction::Input,
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    let heap = vm.get_heap();
+    let lines: Vec<_> = vm.get_stack().iter().map(|v| heap.string(v)).collect();
+
+    assert_eq!(lines, [Some("first"), Some("second"), None]);
+    assert_eq!(vm.get_stack()[2], Value::Null);
+}
+
+#[test]
+fn given_a_config_it_should_enforce_its_limits() {
+    let mut vm = KvmBuilder::new().stack_size(2).build();
+    vm.load_program_from_vec(vec![
+        Instruction::Push(1),
+        Instruction::Push(2),
+        Instruction::Push(3),
+        Instruction::Halt,
+    ]);
+
+    assert!(matches!(vm.execute_program(), Err(KvmError::StackOverflow)));
+
+    let mut vm = KvmBuilder::new().max_call_depth(8).fuel(10_000).build();
+    vm.load_program_from_vec(vec![Instruction::Call(0, 0)]);
+
+    assert!(matches!(
+        vm.execute_program(),
+        Err(KvmError::CallDepthExceeded(8))
+    ));
+}
+
+#[test]
+fn given_unbounded_limits_it_should_not_allocate_them_up_front() {
+    let mut vm = KvmBuilder::new()
+        .stack_size(usize::MAX)
+        .max_call_depth(usize::MAX)
+        .build();
+    vm.load_program_from_vec(vec![
+        Instruction::Push(1),
+        Instruction::Push(2),
+        Instruction::Add,
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    assert_eq!(vm.get_stack(), &[Value::Int(3)]);
+}
+
+#[test]
+fn given_a_tracer_it_should_log_every_executed_instruction() {
+    let trace = SharedOutput::default();
+    let mut vm = Kvm::new();
+    vm.set_observer(Tracer::new(trace.clone()));
+    vm.load_program_from_vec(vec![
+        Instruction::Push(2),
+        Instruction::Push(3),
+        Instruction::Mul,
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    let trace = String::from_utf8(trace.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<&str> = trace.lines().collect();
+
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "     0: push 2                   top: 2");
+    assert_eq!(lines[2], "     2: mul                      top: 6");
+}
+
+#[test]
+fn given_a_profiler_it_should_count_opcodes_and_addresses() {
+    let mut vm = Kvm::new();
+    vm.set_observer(Profiler::new());
+    vm.load_program_from_vec(count_to(10));
+    vm.execute_program().unwrap();
+
+    let mut report = Vec::new();
+    vm.take_observer().unwrap().report(&mut report).unwrap();
+    let report = String::from_utf8(report).unwrap();
+
+    assert!(report.starts_with("71 instructions executed\n"));
+    assert!(report.contains("        10  dup\n"));
+    assert!(report.contains("        10       3: dup 0\n"));
+    assert!(report.ends_with("         1       8: halt\n"));
+    assert!(vm.take_observer().is_none());
+}
+
+#[test]
+fn given_an_entry_point_it_should_start_running_from_it() {
+    let mut program = Program::new(vec![
+        Instruction::LoadLocal(0),
+        Instruction::Push(2),
+        Instruction::Mul,
+        Instruction::Ret,
+        Instruction::Push(21),
+        Instruction::Call(0, 1),
+        Instruction::Halt,
+    ]);
+    program.entry = 4;
+
+    let mut vm = Kvm::new();
+    vm.load_program(program);
+    vm.execute_program().unwrap();
+
+    assert_eq!(vm.get_stack(), [Value::Int(42)]);
+}
+
+#[test]
+fn given_an_entry_point_outside_of_the_program_it_should_fail_verification() {
+    let mut program = Program::new(vec![Instruction::Halt]);
+    program.entry = 3;
+
+    let mut vm = Kvm::new();
+    vm.load_program(program);
+
+    assert!(matches!(
+        vm.execute_program(),
+        Err(KvmError::InvalidEntry(3))
+    ));
+}
+
+#[test]
+fn given_arrays_it_should_create_read_and_update_them() {
+    let mut vm = Kvm::new();
+    vm.load_program_from_vec(vec![
+        Instruction::Push(1),
+        Instruction::Push(2),
+        Instruction::Push(3),
+        Instruction::NewArray(3),
+        Instruction::Dup(0),
+        Instruction::Push(1),
+        Instruction::Push(20),
+        Instruction::ArraySet,
+        Instruction::Dup(0),
+        Instruction::Push(1),
+        Instruction::ArrayGet,
+        Instruction::Over,
+        Instruction::ArrayLen,
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    let stack = vm.get_stack();
+    assert_eq!(stack[1..], [Value::Int(20), Value::Int(3)]);
+    assert_eq!(vm.get_heap().display(&stack[0]), "[1, 20, 3]");
+}
+
+#[test]
+fn given_an_index_outside_of_an_array_it_should_return_an_error() {
+    let program = vec![
+        Instruction::NewArray(0),
+        Instruction::Push(0),
+        Instruction::ArrayGet,
+    ];
+    assert!(matches!(
+        run(program),
+        Err(KvmError::IndexOutOfBounds { index: 0, len: 0 })
+    ));
+
+    let program = vec![
+        Instruction::Push(7),
+        Instruction::NewArray(1),
+        Instruction::Push(-1),
+        Instruction::PushNull,
+        Instruction::ArraySet,
+    ];
+    assert!(matches!(
+        run(program),
+        Err(KvmError::IndexOutOfBounds { index: -1, len: 1 })
+    ));
+}
+
+#[test]
+fn given_concat_it_should_join_strings_and_arrays() {
+    let output = SharedOutput::default();
+    let mut vm = KvmBuilder::new().output(output.clone()).build();
+    vm.load_program_from_vec(vec![
+        Instruction::PushStr("kl".to_string()),
+        Instruction::PushStr("-rs".to_string()),
+        Instruction::Concat,
+        Instruction::Print,
+        Instruction::Push(1),
+        Instruction::NewArray(1),
+        Instruction::Push(2),
+        Instruction::NewArray(1),
+        Instruction::Concat,
+        Instruction::Print,
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    assert_eq!(output.0.lock().unwrap().as_slice(), b"kl-rs\n[1, 2]\n");
+    assert!(matches!(
+        run(vec![
+            Instruction::PushStr("a".to_string()),
+            Instruction::Push(1),
+            Instruction::Concat,
+        ]),
+        Err(KvmError::TypeMismatch {
+            expected: "string",
+            found: "int",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn given_heap_values_it_should_compare_strings_by_content_and_arrays_by_identity() {
+    let program = vec![
+        Instruction::PushStr("a".to_string()),
+        Instruction::PushStr("a".to_string()),
+        Instruction::Eq,
+        Instruction::NewArray(0),
+        Instruction::NewArray(0),
+        Instruction::Eq,
+        Instruction::NewArray(0),
+        Instruction::Dup(0),
+        Instruction::Eq,
+        Instruction::Halt,
+    ];
+
+    assert_eq!(
+        run(program).unwrap(),
+        vec![Value::Bool(true), Value::Bool(false), Value::Bool(true)]
+    );
+}
+
+#[test]
+fn given_a_closure_it_should_pass_its_captured_values_after_the_arguments() {
+    let program = vec![
+        Instruction::Push(5),
+        Instruction::Push(10),
+        Instruction::Closure(5, 1),
+        Instruction::CallClosure(1),
+        Instruction::Halt,
+        Instruction::LoadLocal(0),
+        Instruction::LoadLocal(1),
+        Instruction::Sub,
+        Instruction::Ret,
+    ];
+
+    assert_eq!(run(program).unwrap(), vec![Value::Int(-5)]);
+}
+
+#[test]
+fn given_garbage_it_should_collect_it_when_the_heap_is_full() {
+    let mut vm = KvmBuilder::new().heap_size(1024).build();
+    vm.load_program_from_vec(vec![
+        Instruction::Push(1000),
+        Instruction::PushStr("garbage".to_string()),
+        Instruction::Pop,
+        Instruction::Push(1),
+        Instruction::Sub,
+        Instruction::Dup(0),
+        Instruction::JmpIf(1),
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    let heap = vm.get_heap();
+    assert!(heap.collections() > 0);
+    assert!(heap.size() <= 1024);
+}
+
+#[test]
+fn given_more_live_objects_than_the_heap_holds_it_should_run_out_of_memory() {
+    let mut vm = KvmBuilder::new().heap_size(256).fuel(10_000).build();
+    vm.load_program_from_vec(vec![
+        Instruction::PushStr("live".to_string()),
+        Instruction::Jmp(0),
+    ]);
+
+    assert!(matches!(
+        vm.execute_program(),
+        Err(KvmError::OutOfMemory(256))
+    ));
+}
+
+#[test]
+fn given_an_array_containing_itself_it_should_print_and_collect_it() {
+    let output = SharedOutput::default();
+    let mut vm = KvmBuilder::new().output(output.clone()).build();
+    vm.load_program_from_vec(vec![
+        Instruction::Push(0),
+        Instruction::NewArray(1),
+        Instruction::Dup(0),
+        Instruction::Push(0),
+        Instruction::Dup(2),
+        Instruction::ArraySet,
+        Instruction::Print,
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    assert_eq!(output.0.lock().unwrap().as_slice(), b"[[...]]\n");
+    assert_eq!(vm.get_heap().len(), 1);
+    assert_eq!(vm.collect_garbage(), 1);
+    assert!(vm.get_heap().is_empty());
+}
+
+fn sum(_vm: &mut Kvm, args: &[Value]) -> Result<Value, KvmError> {
+    args.iter()
+        .try_fold(Value::Int(0), |sum, arg| match (sum, arg) {
+            (Value::Int(sum), Value::Int(n)) => Ok(Value::Int(sum + n)),
+            (_, arg) => Err(KvmError::TypeMismatch {
+                instruction: "sum",
+                expected: "int",
+                found: arg.type_name(),
+            }),
+        })
+}
+
+#[test]
+fn given_a_registered_native_it_should_call_it_with_the_arguments() {
+    let mut vm = Kvm::new();
+    let id = vm.register_native("sum", sum);
+    vm.load_program_from_vec(vec![
+        Instruction::Push(1),
+        Instruction::Push(2),
+        Instruction::Push(3),
+        Instruction::CallNative(id, 3),
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    assert_eq!(id, 4);
+    assert_eq!(vm.get_natives()[4].name, "sum");
+    assert_eq!(vm.get_stack(), [Value::Int(6)]);
+}
+
+#[test]
+fn given_a_native_with_an_existing_name_it_should_replace_it() {
+    let mut vm = Kvm::new();
+
+    assert_eq!(vm.register_native("print", sum), 0);
+    assert_eq!(vm.get_natives().len(), 4);
+}
+
+#[test]
+fn given_the_default_natives_it_should_print_and_read_lines() {
+    let output = SharedOutput::default();
+    let mut vm = KvmBuilder::new()
+        .output(output.clone())
+        .input(Cursor::new("line\n"))
+        .build();
+    vm.load_program_from_vec(vec![
+        Instruction::PushStr("a".to_string()),
+        Instruction::Push(1),
+        Instruction::CallNative(0, 2),
+        Instruction::Pop,
+        Instruction::CallNative(1, 0),
+        Instruction::CallNative(0, 1),
+        Instruction::Pop,
+        Instruction::CallNative(1, 0),
+        Instruction::Halt,
+    ]);
+    vm.execute_program().unwrap();
+
+    assert_eq!(output.0.lock().unwrap().as_slice(), b"a 1\nline\n");
+    assert_eq!(vm.get_stack(), [Value::Null]);
+}
+
+#[test]
+fn given_random_and_clock_it_should_return_integers_in_range() {
+    let mut vm = Kvm::new();
+    let mut program: Vec<_> = (0..32)
+        .flat_map(|_| [Instruction::Push(10), Instruction::CallNative(3, 1)])
+        .collect();
+    program.extend([Instruction::CallNative(2, 0), Instruction::Halt]);
+    vm.load_program_from_vec(program);
+    vm.execute_program().unwrap();
+
+    assert!(vm
+        .get_stack()
+        .iter()
+        .take(32)
+        .all(|value| matches!(value, Value::Int(0..=9))));
+    assert!(matches!(vm.get_stack()[32], Value::Int(0..)));
+    assert!(matches!(
+        run(vec![Instruction::Push(0), Instruction::CallNative(3, 1)]),
+        Err(KvmError::Native { .. })
+    ));
+}
+
+#[test]
+fn given_a_clock_running_for_weeks_it_should_wrap_back_to_zero() {
+    let wrap = Duration::from_millis(1 << 31);
+
+    assert_eq!(clock_millis(Duration::from_millis(1500)), 1500);
+    assert_eq!(clock_millis(wrap - Duration::from_millis(1)), i32::MAX);
+    assert_eq!(clock_millis(wrap), 0);
+    assert_eq!(clock_millis(wrap * 3 + Duration::from_secs(2)), 2000);
+}
+
+#[test]
+fn given_an_unknown_native_it_should_return_an_error() {
+    assert!(matches!(
+        run(vec![Instruction::CallNative(99, 0)]),
+        Err(KvmError::UnknownNative(99))
+    ));
+}
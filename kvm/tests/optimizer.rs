@@ -0,0 +1,301 @@
+use std::collections::BTreeMap;
+
+use kvm::strategy::stack_instruction;
+use kvm::{optimize, optimize_program, verify, DebugInfo, Instruction, Kvm, Program, SourceFile};
+use proptest::prelude::*;
+
+fn run(program: Vec<Instruction>) -> String {
+    let mut vm = Kvm::new();
+    vm.load_program_from_vec(program);
+
+    format!(
+        "{:?}",
+        vm.execute_program().map(|_| vm.get_stack().to_vec())
+    )
+}
+
+#[test]
+fn given_constant_expressions_it_should_fold_them() {
+    let program = vec![
+        Instruction::Push(2),
+        Instruction::Push(3),
+        Instruction::Mul,
+        Instruction::Push(1),
+        Instruction::Add,
+        Instruction::Neg,
+        Instruction::Push(-5),
+        Instruction::Lt,
+        Instruction::PushBool(true),
+        Instruction::And,
+        Instruction::Not,
+        Instruction::Print,
+        Instruction::Halt,
+    ];
+
+    assert_eq!(
+        optimize(&program),
+        vec![
+            Instruction::PushBool(false),
+            Instruction::Print,
+            Instruction::Halt
+        ]
+    );
+}
+
+#[test]
+fn given_a_division_by_zero_it_should_leave_it_to_the_vm() {
+    let program = vec![
+        Instruction::Push(1),
+        Instruction::Push(0),
+        Instruction::Div,
+        Instruction::Halt,
+    ];
+
+    assert_eq!(optimize(&program), program);
+}
+
+#[test]
+fn given_values_popped_right_away_it_should_remove_them() {
+    let program = vec![
+        Instruction::Push(1),
+        Instruction::PushStr("unused".to_string()),
+        Instruction::Pop,
+        Instruction::Dup(0),
+        Instruction::Pop,
+        Instruction::Print,
+        Instruction::Halt,
+    ];
+
+    assert_eq!(
+        optimize(&program),
+        vec![Instruction::Push(1), Instruction::Print, Instruction::Halt]
+    );
+}
+
+#[test]
+fn given_a_jump_target_inside_a_sequence_it_should_not_fold_it() {
+    let program = vec![
+        Instruction::Push(3),
+        Instruction::Push(1),
+        Instruction::Sub,
+        Instruction::Dup(0),
+        Instruction::JmpIf(1),
+        Instruction::Halt,
+    ];
+
+    assert_eq!(optimize(&program), program);
+}
+
+#[test]
+fn given_constant_conditions_it_should_replace_the_conditional_jumps() {
+    let never = vec![
+        Instruction::PushBool(false),
+        Instruction::JmpIf(3),
+        Instruction::Push(1),
+        Instruction::Halt,
+    ];
+    let always = vec![
+        Instruction::Push(1),
+        Instruction::Push(1),
+        Instruction::Eq,
+        Instruction::JmpIf(5),
+        Instruction::Push(7),
+        Instruction::Halt,
+    ];
+
+    assert_eq!(
+        optimize(&never),
+        vec![Instruction::Push(1), Instruction::Halt]
+    );
+    assert_eq!(optimize(&always), vec![Instruction::Halt]);
+}
+
+#[test]
+fn given_a_jump_chain_it_should_jump_to_its_end() {
+    let program = vec![
+        Instruction::Push(3),
+        Instruction::JmpIf(3),
+        Instruction::Halt,
+        Instruction::Jmp(5),
+        Instruction::Halt,
+        Instruction::Jmp(8),
+        Instruction::Push(9),
+        Instruction::Halt,
+        Instruction::Push(1),
+        Instruction::Print,
+        Instruction::Halt,
+    ];
+
+    assert_eq!(
+        optimize(&program),
+        vec![Instruction::Push(1), Instruction::Print, Instruction::Halt]
+    );
+}
+
+#[test]
+fn given_a_jump_to_halt_it_should_halt_instead() {
+    let program = vec![
+        Instruction::Dup(0),
+        Instruction::JmpIf(3),
+        Instruction::Jmp(5),
+        Instruction::Push(1),
+        Instruction::Print,
+        Instruction::Halt,
+    ];
+
+    assert_eq!(
+        optimize(&program),
+        vec![
+            Instruction::Dup(0),
+            Instruction::JmpIf(3),
+            Instruction::Halt,
+            Instruction::Push(1),
+            Instruction::Print,
+            Instruction::Halt,
+        ]
+    );
+}
+
+#[test]
+fn given_a_jump_loop_it_should_keep_it() {
+    let program = vec![Instruction::Jmp(1), Instruction::Jmp(0)];
+
+    assert_eq!(optimize(&program), vec![Instruction::Jmp(0)]);
+}
+
+#[test]
+fn given_removed_instructions_it_should_rewrite_the_targets_after_them() {
+    let program = vec![
+        Instruction::Push(3),
+        Instruction::Push(0),
+        Instruction::Pop,
+        Instruction::Call(7, 1),
+        Instruction::Dup(0),
+        Instruction::JmpIf(3),
+        Instruction::Halt,
+        Instruction::Push(42),
+        Instruction::Pop,
+        Instruction::LoadLocal(0),
+        Instruction::Push(1),
+        Instruction::Sub,
+        Instruction::Ret,
+    ];
+
+    let optimized = optimize(&program);
+
+    assert_eq!(
+        optimized,
+        vec![
+            Instruction::Push(3),
+            Instruction::Call(5, 1),
+            Instruction::Dup(0),
+            Instruction::JmpIf(1),
+            Instruction::Halt,
+            Instruction::LoadLocal(0),
+            Instruction::Push(1),
+            Instruction::Sub,
+            Instruction::Ret,
+        ]
+    );
+    assert_eq!(run(optimized), run(program));
+}
+
+#[test]
+fn given_unreachable_code_it_should_keep_closures_and_calls() {
+    let program = vec![
+        Instruction::Push(2),
+        Instruction::Closure(5, 1),
+        Instruction::CallClosure(0),
+        Instruction::Print,
+        Instruction::Halt,
+        Instruction::LoadLocal(0),
+        Instruction::Ret,
+        Instruction::Push(1),
+        Instruction::Ret,
+    ];
+
+    assert_eq!(optimize(&program), program[..7].to_vec());
+}
+
+#[test]
+fn given_an_entry_point_it_should_keep_it_and_the_debug_info_in_sync() {
+    let mut program = Program::new(vec![
+        Instruction::Push(1),
+        Instruction::Print,
+        Instruction::Push(2),
+        Instruction::Push(3),
+        Instruction::Add,
+        Instruction::Print,
+        Instruction::Halt,
+    ]);
+    program.entry = 2;
+    program.debug_info = Some(DebugInfo {
+        files: vec![0, 0, 1, 1, 1, 0, 0],
+        sources: vec![
+            SourceFile::default(),
+            SourceFile {
+                name: "sum.ksm".to_string(),
+                included_at: 3,
+            },
+        ],
+        labels: BTreeMap::from([
+            ("unused".to_string(), 0),
+            ("main".to_string(), 2),
+            ("done".to_string(), 5),
+        ]),
+        ..DebugInfo::new(vec![1, 2, 4, 5, 6, 7, 8])
+    });
+
+    let optimized = optimize_program(&program);
+    let debug_info = optimized.debug_info.unwrap();
+
+    assert_eq!(
+        optimized.instructions,
+        vec![Instruction::Push(5), Instruction::Print, Instruction::Halt]
+    );
+    assert_eq!(optimized.entry, 0);
+    assert_eq!(debug_info.lines, vec![4, 7, 8]);
+    assert_eq!(debug_info.files, vec![1, 0, 0]);
+    assert_eq!(
+        debug_info.labels,
+        BTreeMap::from([
+            ("unused".to_string(), 0),
+            ("main".to_string(), 0),
+            ("done".to_string(), 1),
+        ])
+    );
+}
+
+/// Straight line code with forward jumps, so every program halts.
+fn program() -> impl Strategy<Value = Vec<Instruction>> {
+    prop::collection::vec((stack_instruction(), 0..8u8, 1..4u32), 0..24).prop_map(|code| {
+        let len = code.len() as u32;
+        let mut program: Vec<Instruction> = code
+            .into_iter()
+            .enumerate()
+            .map(|(ip, (inst, kind, offset))| {
+                let target = (ip as u32 + offset).min(len);
+                match kind {
+                    0 => Instruction::Jmp(target),
+                    1 => Instruction::JmpIf(target),
+                    2 => Instruction::JmpIfNot(target),
+                    _ => inst,
+                }
+            })
+            .collect();
+        program.push(Instruction::Halt);
+        program
+    })
+}
+
+proptest! {
+    #[test]
+    fn given_any_valid_program_it_should_not_change_its_result(program in program()) {
+        prop_assume!(verify(&program).is_ok());
+
+        let optimized = optimize(&program);
+
+        prop_assert!(optimized.len() <= program.len());
+        prop_assert_eq!(run(optimized), run(program));
+    }
+}
@@ -0,0 +1,222 @@
+use kvm::{verify, Instruction, Kvm, KvmError};
+
+#[test]
+fn given_a_valid_program_it_should_compute_the_stack_depths() {
+    let program = vec![
+        Instruction::Push(5),
+        Instruction::Call(4, 1),
+        Instruction::Print,
+        Instruction::Halt,
+        Instruction::LoadLocal(0),
+        Instruction::Push(1),
+        Instruction::StoreLocal(2),
+        Instruction::LoadLocal(1),
+        Instruction::Ret,
+        Instruction::Push(42),
+    ];
+
+    assert_eq!(
+        verify(&program).unwrap(),
+        vec![
+            Some(0),
+            Some(1),
+            Some(1),
+            Some(0),
+            Some(1),
+            Some(2),
+            Some(3),
+            Some(3),
+            Some(4),
+            None
+        ]
+    );
+}
+
+#[test]
+fn given_a_loop_growing_the_stack_it_should_keep_the_smallest_depth() {
+    let program = vec![
+        Instruction::Push(0),
+        Instruction::Push(1),
+        Instruction::Dup(1),
+        Instruction::Dup(1),
+        Instruction::Add,
+        Instruction::Dup(0),
+        Instruction::Push(144),
+        Instruction::Eq,
+        Instruction::JmpIf(10),
+        Instruction::Jmp(2),
+        Instruction::Halt,
+    ];
+
+    let depths = verify(&program).unwrap();
+
+    assert_eq!(depths[2], Some(2));
+    assert_eq!(depths[10], Some(3));
+}
+
+#[test]
+fn given_an_invalid_target_it_should_reject_the_program() {
+    let jump = vec![
+        Instruction::Push(1),
+        Instruction::JmpIf(3),
+        Instruction::Halt,
+    ];
+    let call = vec![Instruction::Call(7, 0), Instruction::Halt];
+
+    assert!(matches!(
+        verify(&jump),
+        Err(KvmError::InvalidJumpTarget { ip: 1, target: 3 })
+    ));
+    assert!(matches!(
+        verify(&call),
+        Err(KvmError::InvalidJumpTarget { ip: 0, target: 7 })
+    ));
+}
+
+#[test]
+fn given_a_path_that_underflows_it_should_reject_the_program() {
+    // the add is fine when the jump is taken, but not when falling through
+    let program = vec![
+        Instruction::PushBool(true),
+        Instruction::JmpIf(4),
+        Instruction::Push(1),
+        Instruction::Jmp(5),
+        Instruction::Push(2),
+        Instruction::Push(3),
+        Instruction::Dup(2),
+        Instruction::Add,
+        Instruction::Halt,
+    ];
+
+    let err = verify(&program).unwrap_err();
+
+    assert!(matches!(
+        err,
+        KvmError::Underflow {
+            ip: 6,
+            needed: 3,
+            depth: 2,
+            ..
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Instruction 6 (dup 2) needs 3 values on the stack but can be reached with 2"
+    );
+}
+
+#[test]
+fn given_branches_joining_with_different_depths_it_should_accept_code_using_the_smallest() {
+    let program = vec![
+        Instruction::Input,
+        Instruction::JmpIf(4),
+        Instruction::Push(1),
+        Instruction::Push(2),
+        Instruction::Push(3),
+        Instruction::Print,
+        Instruction::Halt,
+    ];
+
+    assert_eq!(
+        verify(&program).unwrap(),
+        vec![
+            Some(0),
+            Some(1),
+            Some(0),
+            Some(1),
+            Some(0),
+            Some(1),
+            Some(0)
+        ]
+    );
+}
+
+#[test]
+fn given_stack_manipulation_it_should_track_the_depth() {
+    let program = vec![
+        Instruction::Push(1),
+        Instruction::Push(2),
+        Instruction::Over,
+        Instruction::Rot,
+        Instruction::Swap,
+        Instruction::Lt,
+        Instruction::JmpIfNot(8),
+        Instruction::Pop,
+        Instruction::Rot,
+    ];
+
+    let err = verify(&program).unwrap_err();
+
+    assert!(matches!(
+        err,
+        KvmError::Underflow {
+            ip: 8,
+            needed: 3,
+            depth: 0,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn given_an_invalid_program_it_should_not_execute_it() {
+    let mut vm = Kvm::new();
+    vm.load_program_from_vec(vec![
+        Instruction::Push(1),
+        Instruction::Print,
+        Instruction::Print,
+        Instruction::Halt,
+    ]);
+
+    assert!(matches!(
+        vm.execute_program(),
+        Err(KvmError::Underflow { ip: 2, .. })
+    ));
+    assert_eq!(vm.get_ip(), 0);
+
+    vm.set_verify(false);
+
+    assert!(matches!(
+        vm.execute_program(),
+        Err(KvmError::StackUnderflow)
+    ));
+    assert_eq!(vm.get_ip(), 2);
+}
+
+#[test]
+fn given_arrays_and_closures_it_should_track_their_stack_effects() {
+    let program = vec![
+        Instruction::Push(1),
+        Instruction::Push(2),
+        Instruction::NewArray(2),
+        Instruction::Push(0),
+        Instruction::Closure(7, 1),
+        Instruction::CallClosure(1),
+        Instruction::Halt,
+        Instruction::LoadLocal(0),
+        Instruction::Ret,
+    ];
+
+    assert_eq!(
+        verify(&program).unwrap(),
+        vec![
+            Some(0),
+            Some(1),
+            Some(2),
+            Some(1),
+            Some(2),
+            Some(2),
+            Some(1),
+            None,
+            None
+        ]
+    );
+    assert!(matches!(
+        verify(&[Instruction::Closure(3, 0)]),
+        Err(KvmError::InvalidJumpTarget { ip: 0, target: 3 })
+    ));
+    assert!(matches!(
+        verify(&[Instruction::NewArray(1)]),
+        Err(KvmError::Underflow { needed: 1, .. })
+    ));
+}
@@ -23,6 +23,8 @@ pub enum Expression {
     },
     FunctionExpression {
         parameters: Vec<Token>,
+        parameter_types: Vec<Option<TypeAnnotation>>,
+        return_type: Option<TypeAnnotation>,
         body: Box<BlockStatement>,
     },
     CallExpression {
@@ -41,6 +43,7 @@ pub enum Statement {
     ReturnStatement(Box<Expression>),
     LetStatement {
         name: Box<Expression>,
+        annotation: Option<TypeAnnotation>,
         value: Box<Expression>,
     },
 }
@@ -51,3 +54,16 @@ pub enum AstNode {
     Expression(Box<Expression>),
     Program { statements: Vec<AstNode> },
 }
+
+/// Optional type written by the user, like `int` in `let x: int = 1`.
+#[derive(Debug, Eq, Clone, PartialEq)]
+pub enum TypeAnnotation {
+    Int,
+    Bool,
+    String,
+    Array(Box<TypeAnnotation>),
+    Function {
+        parameters: Vec<TypeAnnotation>,
+        return_type: Box<TypeAnnotation>,
+    },
+}
@@ -4,18 +4,43 @@ use std::collections::HashMap;
 
 pub type BuiltinFn = fn(Vec<Object>) -> Object;
 
+pub struct BuiltinFunction {
+    pub function: BuiltinFn,
+    pub arity: usize,
+}
+
 lazy_static! {
-    pub static ref BUILTIN_FUNCTIONS: HashMap<&'static str, BuiltinFn> =
-        HashMap::from([("len", len as BuiltinFn)]);
+    pub static ref BUILTIN_FUNCTIONS: HashMap<&'static str, BuiltinFunction> = HashMap::from([
+        (
+            "len",
+            BuiltinFunction {
+                function: len,
+                arity: 1
+            }
+        ),
+        (
+            "puts",
+            BuiltinFunction {
+                function: puts,
+                arity: 1
+            }
+        ),
+    ]);
 }
 
 fn len(args: Vec<Object>) -> Object {
     let first_obj = args
         .first()
-        .expect("len function must be provided a string argument!");
+        .expect("len function must be provided a string or an array argument!");
 
     match first_obj {
         Object::String(str) => Object::Integer(str.len() as i32),
-        _ => panic!("len function must be provided a string argument!"),
+        Object::Array(elems) => Object::Integer(elems.len() as i32),
+        _ => panic!("len function must be provided a string or an array argument!"),
     }
 }
+
+fn puts(args: Vec<Object>) -> Object {
+    args.iter().for_each(|arg| println!("{}", arg.inspect()));
+    Object::Null
+}
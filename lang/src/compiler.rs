@@ -0,0 +1,782 @@
+use std::collections::HashMap;
+
+use kvm::Instruction;
+
+use crate::ast::{AstNode, BlockStatement, Expression, Statement, TypeAnnotation};
+use crate::token::Token;
+
+/// Lowers a kl-rs program into kvm instructions. The value of the program is
+/// left on top of the stack when the vm halts.
+pub fn compile(program: &AstNode) -> Result<Vec<Instruction>, String> {
+    let mut compiler = Compiler::new();
+    let exit = compiler.new_label();
+
+    let value = compiler.compile_node(program)?;
+    if !compiler.diverged {
+        compiler.push_constant(&value);
+    }
+    compiler.emit_label(exit);
+    compiler.emit(Instruction::Halt);
+
+    // functions are placed after the main program, so they only run when called
+    let functions = std::mem::take(&mut compiler.functions);
+    compiler.ops.extend(functions);
+
+    Ok(compiler.finish(exit))
+}
+
+/// What the compiler knows about the value an expression produced. Integers,
+/// booleans, null and arrays take one stack slot each, while strings and
+/// functions are tracked as constants and only pushed when they are the result
+/// of a block. Arrays know the value of their elements.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Int,
+    Bool,
+    Null,
+    Str(String),
+    Function(usize),
+    Array(Box<Value>),
+}
+
+impl Value {
+    fn is_constant(&self) -> bool {
+        matches!(self, Value::Str(_) | Value::Function(_))
+    }
+}
+
+/// Locals are addressed relative to the frame of the function being compiled,
+/// the main program being compiled as a frame starting at the bottom of the
+/// stack. The bindings of the main program are also stored as globals, so
+/// functions can read them.
+#[derive(Debug, Clone)]
+enum Binding {
+    Slot(usize, Value),
+    Global(u32, Value),
+    Constant(Value),
+}
+
+#[derive(Debug)]
+struct Function {
+    label: usize,
+    parameters: Vec<Value>,
+    value: Value,
+}
+
+/// Jumps are kept symbolic until the whole program is compiled, so branches
+/// can be compiled on their own and stitched together.
+#[derive(Debug)]
+enum Op {
+    Emit(Instruction),
+    Jmp(usize),
+    JmpIf(usize),
+    Call(usize, usize),
+    Label(usize),
+}
+
+struct Compiler {
+    ops: Vec<Op>,
+    functions: Vec<Op>,
+    function_signatures: Vec<Function>,
+    labels: usize,
+    bindings: HashMap<String, Binding>,
+    /// Bindings of the main program, visible from every function defined
+    /// after them.
+    globals: HashMap<String, Binding>,
+    globals_len: u32,
+    /// Number of if branches being compiled, whose bindings are not globals.
+    branches: usize,
+    depth: usize,
+    diverged: bool,
+    /// Kind of the values returned by the function being compiled, `None`
+    /// when compiling the main program.
+    return_value: Option<Option<Value>>,
+}
+
+impl Compiler {
+    fn new() -> Self {
+        Compiler {
+            ops: Vec::new(),
+            functions: Vec::new(),
+            function_signatures: Vec::new(),
+            labels: 1,
+            bindings: HashMap::new(),
+            globals: HashMap::new(),
+            globals_len: 0,
+            branches: 0,
+            depth: 0,
+            diverged: false,
+            return_value: None,
+        }
+    }
+
+    fn new_label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels - 1
+    }
+
+    fn emit(&mut self, inst: Instruction) {
+        self.depth = match inst {
+            Instruction::Push(_)
+            | Instruction::PushBool(_)
+            | Instruction::PushNull
+            | Instruction::PushStr(_)
+            | Instruction::Input
+            | Instruction::Dup(_)
+            | Instruction::LoadLocal(_)
+            | Instruction::LoadGlobal(_) => self.depth + 1,
+            Instruction::NewArray(n) => self.depth + 1 - n as usize,
+            Instruction::Add
+            | Instruction::Sub
+            | Instruction::Mul
+            | Instruction::Div
+            | Instruction::Eq
+            | Instruction::Ne
+            | Instruction::Lt
+            | Instruction::Gt
+            | Instruction::Print
+            | Instruction::Pop
+            | Instruction::StoreGlobal(_)
+            | Instruction::Ret => self.depth - 1,
+            _ => self.depth,
+        };
+        self.ops.push(Op::Emit(inst));
+    }
+
+    fn emit_jmp(&mut self, label: usize) {
+        self.ops.push(Op::Jmp(label));
+    }
+
+    fn emit_jmp_if(&mut self, label: usize) {
+        self.depth -= 1;
+        self.ops.push(Op::JmpIf(label));
+    }
+
+    fn emit_label(&mut self, label: usize) {
+        self.ops.push(Op::Label(label));
+    }
+
+    /// Calls a function, replacing its arguments with its result.
+    fn emit_call(&mut self, label: usize, argc: usize) {
+        self.depth = self.depth - argc + 1;
+        self.ops.push(Op::Call(label, argc));
+    }
+
+    fn finish(self, exit: usize) -> Vec<Instruction> {
+        let mut addresses = vec![0; self.labels];
+        let mut address = 0;
+
+        for op in &self.ops {
+            match op {
+                Op::Label(label) => addresses[*label] = address,
+                _ => address += 1,
+            }
+        }
+        addresses[0] = addresses[exit];
+
+        self.ops
+            .into_iter()
+            .filter_map(|op| match op {
+                Op::Emit(inst) => Some(inst),
+                Op::Jmp(label) => Some(Instruction::Jmp(addresses[label] as u32)),
+                Op::JmpIf(label) => Some(Instruction::JmpIf(addresses[label] as u32)),
+                Op::Call(label, argc) => {
+                    Some(Instruction::Call(addresses[label] as u32, argc as u32))
+                }
+                Op::Label(_) => None,
+            })
+            .collect()
+    }
+
+    fn compile_node(&mut self, node: &AstNode) -> Result<Value, String> {
+        match node {
+            AstNode::Program { statements } => self.compile_statements(statements),
+            AstNode::Statement(statement) => self.compile_statement(statement),
+            AstNode::Expression(expression) => self.compile_expression(expression),
+        }
+    }
+
+    fn compile_statements(&mut self, statements: &[AstNode]) -> Result<Value, String> {
+        let mut value = Value::Null;
+
+        if statements.is_empty() {
+            self.emit(Instruction::PushNull);
+        }
+
+        for (idx, statement) in statements.iter().enumerate() {
+            // only the last value is kept, while let bindings stay as locals
+            let is_last = idx + 1 == statements.len();
+            value = match statement {
+                AstNode::Expression(expression) if !is_last => {
+                    self.compile_discarded(expression)?
+                }
+                statement => self.compile_node(statement)?,
+            };
+
+            // the statements after a return are never evaluated
+            if self.diverged {
+                break;
+            }
+
+            if !is_last && matches!(statement, AstNode::Expression(_)) && !value.is_constant() {
+                self.emit(Instruction::Pop);
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Compiles an expression whose value is popped right after, so the
+    /// branches of an if expression may leave different values, or none.
+    fn compile_discarded(&mut self, expression: &Expression) -> Result<Value, String> {
+        match expression {
+            Expression::IfExpression {
+                condition,
+                consequence,
+                alternative,
+            } => self.compile_if_expression(condition, consequence, alternative.as_deref(), true),
+            expression => self.compile_expression(expression),
+        }
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) -> Result<Value, String> {
+        match statement {
+            Statement::ReturnStatement(value) => {
+                let value = self.compile_expression(value)?;
+                self.push_constant(&value);
+
+                match &self.return_value {
+                    None => {
+                        // label 0 is resolved to the final halt of the program
+                        self.emit_jmp(0);
+                    }
+                    Some(return_value) => {
+                        self.set_return_value(value.clone(), return_value.clone())?;
+                        self.emit(Instruction::Ret);
+                    }
+                }
+                self.diverged = true;
+
+                Ok(value)
+            }
+            Statement::LetStatement { name, value, .. } => {
+                let name = match &**name {
+                    Expression::Identifier(name) => name.clone(),
+                    name => return Err(format!("invalid let binding name: {:?}", name)),
+                };
+
+                let value = match &**value {
+                    Expression::FunctionExpression {
+                        parameters,
+                        parameter_types,
+                        return_type,
+                        body,
+                    } => self.compile_function(
+                        Some(&name),
+                        parameters,
+                        parameter_types,
+                        return_type.as_ref(),
+                        body,
+                    )?,
+                    value => self.compile_expression(value)?,
+                };
+                let binding = match value.is_constant() {
+                    true => Binding::Constant(value.clone()),
+                    false => Binding::Slot(self.depth - 1, value.clone()),
+                };
+                if self.return_value.is_none() && self.branches == 0 {
+                    self.bind_global(&name, &binding);
+                }
+                self.bindings.insert(name, binding);
+
+                Ok(value)
+            }
+        }
+    }
+
+    /// Copies a binding of the main program into a global, keeping it on the
+    /// stack as a local of the main program.
+    fn bind_global(&mut self, name: &str, binding: &Binding) {
+        let global = match binding {
+            Binding::Slot(_, value) => {
+                self.emit(Instruction::Dup(0));
+                self.emit(Instruction::StoreGlobal(self.globals_len));
+                self.globals_len += 1;
+                Binding::Global(self.globals_len - 1, value.clone())
+            }
+            binding => binding.clone(),
+        };
+
+        self.globals.insert(name.to_string(), global);
+    }
+
+    /// Makes sure a constant is materialised as a stack value when it is the
+    /// result of a block.
+    fn push_constant(&mut self, value: &Value) {
+        match value {
+            Value::Str(s) => self.emit(Instruction::PushStr(s.clone())),
+            Value::Function(_) => self.emit(Instruction::PushNull),
+            _ => {}
+        }
+    }
+
+    /// Checks that every path out of a function returns the same kind of
+    /// value.
+    fn set_return_value(&mut self, value: Value, previous: Option<Value>) -> Result<(), String> {
+        let value = match value {
+            Value::Int | Value::Bool | Value::Null | Value::Array(_) => value,
+            value => return Err(format!("unsupported function return value: {:?}", value)),
+        };
+
+        match previous {
+            Some(previous) if previous != value => Err(format!(
+                "function returns different values: {:?} and {:?}",
+                previous, value
+            )),
+            _ => {
+                self.return_value = Some(Some(value));
+                Ok(())
+            }
+        }
+    }
+
+    fn compile_expression(&mut self, expression: &Expression) -> Result<Value, String> {
+        match expression {
+            Expression::Int(value) => {
+                self.emit(Instruction::Push(*value));
+                Ok(Value::Int)
+            }
+            Expression::Boolean(value) => {
+                self.emit(Instruction::PushBool(*value));
+                Ok(Value::Bool)
+            }
+            Expression::String(value) => Ok(Value::Str(value.clone())),
+            Expression::Array(elements) => self.compile_array(elements),
+            Expression::Identifier(name) => match self
+                .bindings
+                .get(name)
+                .or_else(|| self.globals.get(name))
+                .cloned()
+            {
+                Some(Binding::Slot(slot, value)) => {
+                    self.emit(Instruction::LoadLocal(slot as u32));
+                    Ok(value)
+                }
+                Some(Binding::Global(idx, value)) => {
+                    self.emit(Instruction::LoadGlobal(idx));
+                    Ok(value)
+                }
+                Some(Binding::Constant(value)) => Ok(value),
+                None => Err(format!("undefined identifier: {}", name)),
+            },
+            Expression::Prefix { operator, right } => {
+                let value = self.compile_expression(right)?;
+
+                match (operator, value) {
+                    (Token::Bang, Value::Bool) => {
+                        self.emit(Instruction::Not);
+                        Ok(Value::Bool)
+                    }
+                    (Token::Minus, Value::Int) => {
+                        self.emit(Instruction::Neg);
+                        Ok(Value::Int)
+                    }
+                    (operator, value) => Err(format!(
+                        "unsupported prefix expression: {:?} {:?}",
+                        operator, value
+                    )),
+                }
+            }
+            Expression::Infix {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.compile_expression(left)?;
+                let right = self.compile_expression(right)?;
+                self.compile_infix_expression(operator, left, right)
+            }
+            Expression::IfExpression {
+                condition,
+                consequence,
+                alternative,
+            } => self.compile_if_expression(condition, consequence, alternative.as_deref(), false),
+            Expression::FunctionExpression {
+                parameters,
+                parameter_types,
+                return_type,
+                body,
+            } => self.compile_function(
+                None,
+                parameters,
+                parameter_types,
+                return_type.as_ref(),
+                body,
+            ),
+            Expression::CallExpression {
+                function,
+                arguments,
+            } => match (&**function, arguments.as_slice()) {
+                (Expression::Identifier(name), [argument]) if name == "puts" => {
+                    self.compile_puts(argument)
+                }
+                (Expression::Identifier(name), [argument]) if name == "len" => {
+                    self.compile_len(argument)
+                }
+                (function, arguments) => self.compile_call(function, arguments),
+            },
+        }
+    }
+
+    fn compile_infix_expression(
+        &mut self,
+        operator: &Token,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, String> {
+        match (operator, left, right) {
+            (Token::Plus, Value::Str(left), Value::Str(right)) => {
+                Ok(Value::Str(format!("{left}{right}")))
+            }
+            (Token::Equals | Token::NotEquals, Value::Str(left), Value::Str(right)) => {
+                let is_equal = left == right;
+                self.emit(Instruction::PushBool(
+                    is_equal == (*operator == Token::Equals),
+                ));
+                Ok(Value::Bool)
+            }
+            (operator, Value::Int, Value::Int) => {
+                match operator {
+                    Token::Plus => self.emit(Instruction::Add),
+                    Token::Asterisk => self.emit(Instruction::Mul),
+                    Token::Minus => self.emit(Instruction::Sub),
+                    Token::Slash => self.emit(Instruction::Div),
+                    Token::Equals => self.emit(Instruction::Eq),
+                    Token::NotEquals => self.emit(Instruction::Ne),
+                    Token::LessThan => self.emit(Instruction::Lt),
+                    Token::GreaterThan => self.emit(Instruction::Gt),
+                    operator => return Err(format!("unsupported infix operator: {:?}", operator)),
+                }
+
+                Ok(match operator {
+                    Token::Plus | Token::Minus | Token::Asterisk | Token::Slash => Value::Int,
+                    _ => Value::Bool,
+                })
+            }
+            (operator, left, right) => Err(format!(
+                "unsupported operands for {:?}: {:?} and {:?}",
+                operator, left, right
+            )),
+        }
+    }
+
+    fn compile_if_expression(
+        &mut self,
+        condition: &Expression,
+        consequence: &BlockStatement,
+        alternative: Option<&BlockStatement>,
+        is_discarded: bool,
+    ) -> Result<Value, String> {
+        // any non zero integer is truthy, so test whether the condition is 0
+        match self.compile_expression(condition)? {
+            Value::Int => self.emit(Instruction::Push(0)),
+            Value::Bool => self.emit(Instruction::PushBool(false)),
+            value => return Err(format!("unsupported if condition: {:?}", value)),
+        }
+        self.emit(Instruction::Eq);
+
+        let else_label = self.new_label();
+        let end_label = self.new_label();
+        self.emit_jmp_if(else_label);
+
+        let depth = self.depth;
+        let (consequence_ops, consequence_value, consequence_depth) =
+            self.compile_branch(&consequence.statements)?;
+
+        self.depth = depth;
+        let (alternative_ops, alternative_value, alternative_depth) = match alternative {
+            Some(alternative) => self.compile_branch(&alternative.statements)?,
+            None => self.compile_branch(&[])?,
+        };
+
+        let value = match (consequence_depth, alternative_depth) {
+            (None, None) => {
+                self.diverged = true;
+                consequence_value
+            }
+            (None, _) => alternative_value,
+            (_, None) => consequence_value,
+            _ if is_discarded => Value::Null,
+            _ if alternative.is_none() && consequence_value != Value::Null => {
+                return Err("if expressions without else can not produce a value".to_string())
+            }
+            _ if consequence_value != alternative_value => {
+                return Err(format!(
+                    "if branches produce different values: {:?} and {:?}",
+                    consequence_value, alternative_value
+                ))
+            }
+            _ => consequence_value,
+        };
+
+        // both branches must leave the stack with the same depth, so the
+        // shallower one duplicates its result until they match
+        let end_depth = consequence_depth
+            .into_iter()
+            .chain(alternative_depth)
+            .max()
+            .unwrap_or(depth);
+
+        self.ops.extend(consequence_ops);
+        if let Some(consequence_depth) = consequence_depth {
+            (consequence_depth..end_depth)
+                .for_each(|_| self.ops.push(Op::Emit(Instruction::Dup(0))));
+            self.emit_jmp(end_label);
+        }
+
+        self.emit_label(else_label);
+        self.ops.extend(alternative_ops);
+        if let Some(alternative_depth) = alternative_depth {
+            (alternative_depth..end_depth)
+                .for_each(|_| self.ops.push(Op::Emit(Instruction::Dup(0))));
+        }
+
+        self.emit_label(end_label);
+        self.depth = end_depth;
+
+        Ok(value)
+    }
+
+    /// Compiles the statements of an if branch on their own, returning the
+    /// stack depth they leave behind or `None` if the branch always returns.
+    /// Bindings created inside a branch are not visible after the if.
+    fn compile_branch(
+        &mut self,
+        statements: &[AstNode],
+    ) -> Result<(Vec<Op>, Value, Option<usize>), String> {
+        let ops = std::mem::take(&mut self.ops);
+        let bindings = self.bindings.clone();
+
+        self.branches += 1;
+        let result = self.compile_statements(statements);
+        self.branches -= 1;
+        if let Ok(value) = &result {
+            if !self.diverged {
+                self.push_constant(value);
+            }
+        }
+
+        let branch_ops = std::mem::replace(&mut self.ops, ops);
+        self.bindings = bindings;
+
+        let depth = match self.diverged {
+            true => None,
+            false => Some(self.depth),
+        };
+        self.diverged = false;
+
+        let value = match result? {
+            value if value.is_constant() => Value::Null,
+            value => value,
+        };
+
+        Ok((branch_ops, value, depth))
+    }
+
+    fn compile_puts(&mut self, argument: &Expression) -> Result<Value, String> {
+        match self.compile_expression(argument)? {
+            Value::Int | Value::Bool | Value::Null | Value::Array(_) => {
+                self.emit(Instruction::Print)
+            }
+            Value::Str(s) => {
+                self.emit(Instruction::PushStr(s));
+                self.emit(Instruction::Print);
+            }
+            Value::Function(_) => {
+                self.emit(Instruction::PushStr("function".to_string()));
+                self.emit(Instruction::Print);
+            }
+        }
+
+        self.emit(Instruction::PushNull);
+        Ok(Value::Null)
+    }
+
+    fn compile_len(&mut self, argument: &Expression) -> Result<Value, String> {
+        match self.compile_expression(argument)? {
+            Value::Array(_) => self.emit(Instruction::ArrayLen),
+            Value::Str(s) => self.emit(Instruction::Push(s.len() as i32)),
+            value => return Err(format!("unsupported len argument: {:?}", value)),
+        }
+
+        Ok(Value::Int)
+    }
+
+    /// Pushes the elements and collects them into an array on the kvm heap.
+    /// Every element has to produce the same kind of value, and strings and
+    /// functions can't be stored in arrays yet.
+    fn compile_array(&mut self, elements: &[Expression]) -> Result<Value, String> {
+        let mut element = None;
+
+        for expression in elements {
+            let value = self.compile_expression(expression)?;
+
+            if value.is_constant() {
+                return Err(format!("unsupported array element: {:?}", value));
+            }
+            match &element {
+                Some(element) if *element != value => {
+                    return Err(format!(
+                        "array elements have different values: {:?} and {:?}",
+                        element, value
+                    ))
+                }
+                _ => element = Some(value),
+            }
+        }
+
+        self.emit(Instruction::NewArray(elements.len() as u32));
+        Ok(Value::Array(Box::new(element.unwrap_or(Value::Null))))
+    }
+
+    /// Compiles a function body into its own frame, where the parameters are
+    /// the first locals. The body sees its parameters and bindings, the
+    /// bindings of the main program, read as globals, and the function itself
+    /// through the `name` it is bound to. Parameters are integers unless
+    /// annotated otherwise, and so are the results of recursive calls.
+    fn compile_function(
+        &mut self,
+        name: Option<&str>,
+        parameters: &[Token],
+        parameter_types: &[Option<TypeAnnotation>],
+        return_type: Option<&TypeAnnotation>,
+        body: &BlockStatement,
+    ) -> Result<Value, String> {
+        let mut bindings = HashMap::new();
+        let mut parameter_values = Vec::new();
+
+        for (idx, parameter) in parameters.iter().enumerate() {
+            let value = match parameter_types.get(idx) {
+                Some(Some(annotation)) => parameter_value(annotation)?,
+                Some(None) | None => Value::Int,
+            };
+
+            match parameter {
+                Token::Identifier(name) => {
+                    bindings.insert(name.clone(), Binding::Slot(idx, value.clone()))
+                }
+                parameter => return Err(format!("invalid parameter: {:?}", parameter)),
+            };
+            parameter_values.push(value);
+        }
+
+        let label = self.new_label();
+        let function = self.function_signatures.len();
+        let assumed_value = return_type
+            .and_then(|annotation| parameter_value(annotation).ok())
+            .unwrap_or(Value::Int);
+        self.function_signatures.push(Function {
+            label,
+            parameters: parameter_values,
+            value: assumed_value.clone(),
+        });
+        if let Some(name) = name {
+            bindings
+                .entry(name.to_string())
+                .or_insert(Binding::Constant(Value::Function(function)));
+        }
+
+        let ops = std::mem::replace(&mut self.ops, vec![Op::Label(label)]);
+        let outer_bindings = std::mem::replace(&mut self.bindings, bindings);
+        let depth = std::mem::replace(&mut self.depth, parameters.len());
+        let return_value = self.return_value.replace(None);
+
+        let result = self.compile_function_body(body);
+
+        let function_ops = std::mem::replace(&mut self.ops, ops);
+        self.bindings = outer_bindings;
+        self.depth = depth;
+        self.diverged = false;
+        let value = std::mem::replace(&mut self.return_value, return_value);
+
+        result?;
+        let value = value.flatten().unwrap_or(Value::Null);
+        let is_recursive = function_ops
+            .iter()
+            .any(|op| matches!(op, Op::Call(callee, _) if *callee == label));
+        if is_recursive && value != assumed_value {
+            return Err(format!(
+                "recursive function returns {:?} instead of {:?}, annotate its return type",
+                value, assumed_value
+            ));
+        }
+
+        self.functions.extend(function_ops);
+        self.function_signatures[function].value = value;
+
+        Ok(Value::Function(function))
+    }
+
+    fn compile_function_body(&mut self, body: &BlockStatement) -> Result<(), String> {
+        let value = self.compile_statements(&body.statements)?;
+
+        if !self.diverged {
+            let return_value = self.return_value.clone().flatten();
+            self.set_return_value(value, return_value)?;
+            self.emit(Instruction::Ret);
+        }
+
+        Ok(())
+    }
+
+    fn compile_call(
+        &mut self,
+        function: &Expression,
+        arguments: &[Expression],
+    ) -> Result<Value, String> {
+        let function = match self.compile_expression(function)? {
+            Value::Function(function) => function,
+            value => return Err(format!("unsupported function call: {:?}", value)),
+        };
+
+        let Function {
+            label,
+            parameters,
+            value,
+        } = &self.function_signatures[function];
+        let (label, parameters, value) = (*label, parameters.clone(), value.clone());
+
+        if parameters.len() != arguments.len() {
+            return Err(format!(
+                "function expects {} argument(s) but was given {}",
+                parameters.len(),
+                arguments.len()
+            ));
+        }
+
+        for (argument, parameter) in arguments.iter().zip(parameters.iter()) {
+            let argument = self.compile_expression(argument)?;
+
+            if argument != *parameter {
+                return Err(format!(
+                    "unsupported argument: expected {:?}, found {:?}",
+                    parameter, argument
+                ));
+            }
+        }
+
+        self.emit_call(label, arguments.len());
+
+        Ok(value)
+    }
+}
+
+/// The value of a parameter annotated with `annotation`.
+fn parameter_value(annotation: &TypeAnnotation) -> Result<Value, String> {
+    match annotation {
+        TypeAnnotation::Int => Ok(Value::Int),
+        TypeAnnotation::Bool => Ok(Value::Bool),
+        TypeAnnotation::Array(element) => Ok(Value::Array(Box::new(parameter_value(element)?))),
+        annotation => Err(format!("unsupported parameter type: {:?}", annotation)),
+    }
+}
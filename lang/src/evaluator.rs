@@ -3,7 +3,7 @@ use crate::token::Token;
 use std::cell::RefCell;
 use std::collections::HashMap;
 
-use crate::builtin::{BuiltinFn, BUILTIN_FUNCTIONS};
+use crate::builtin::BUILTIN_FUNCTIONS;
 
 #[derive(Default)]
 pub struct Evaluator {
@@ -17,7 +17,8 @@ pub enum Object {
     String(String),
     Array(Vec<Object>),
     Return(Box<Object>),
-    Builtin(BuiltinFn),
+    /// A builtin function, by its name in `BUILTIN_FUNCTIONS`.
+    Builtin(&'static str),
     Null,
     Function {
         parameters: Vec<Token>,
@@ -97,9 +98,9 @@ impl Evaluator {
                 let function = self.eval(AstNode::Expression(function));
 
                 match function {
-                    Object::Builtin(builtin_fn) => {
+                    Object::Builtin(name) => {
                         let args = self.eval_expressions(arguments);
-                        builtin_fn(args)
+                        (BUILTIN_FUNCTIONS[name].function)(args)
                     }
                     Object::Function {
                         parameters,
@@ -113,8 +114,8 @@ impl Evaluator {
     }
 
     fn eval_identifier(&self, name: String) -> Object {
-        if let Some(function) = BUILTIN_FUNCTIONS.get(name.as_str()) {
-            return Object::Builtin(*function);
+        if let Some((name, _)) = BUILTIN_FUNCTIONS.get_key_value(name.as_str()) {
+            return Object::Builtin(name);
         }
 
         self.context
@@ -145,7 +146,8 @@ impl Evaluator {
                 _ => panic!(),
             });
 
-        self.context.borrow_mut().clear();
+        // the parameters shadow the bindings visible where the function is
+        // called, which include the function itself, so it can recurse
         scope.iter().for_each(|(key, value)| {
             self.context.borrow_mut().insert(key.clone(), value.clone());
         });
@@ -166,7 +168,7 @@ impl Evaluator {
                 let result_object = self.eval(AstNode::Expression(value));
                 Object::Return(Box::new(result_object))
             }
-            Statement::LetStatement { name, value } => {
+            Statement::LetStatement { name, value, .. } => {
                 let let_name = match *name {
                     Expression::Identifier(identifier_name) => identifier_name,
                     _ => panic!(),
@@ -236,6 +238,10 @@ impl Evaluator {
     }
 
     fn eval_infix_expression(&self, left: Object, right: Object, operator: Token) -> Object {
+        if let (Object::String(left_str), Object::String(right_str)) = (&left, &right) {
+            return self.eval_string_infix_expression(left_str, right_str, operator);
+        }
+
         let left_int = match left {
             Object::Integer(num) => num,
             _ => return Object::Null,
@@ -257,6 +263,15 @@ impl Evaluator {
             _ => Object::Null,
         }
     }
+
+    fn eval_string_infix_expression(&self, left: &str, right: &str, operator: Token) -> Object {
+        match operator {
+            Token::Plus => Object::String(format!("{left}{right}")),
+            Token::Equals => Object::Boolean(left == right),
+            Token::NotEquals => Object::Boolean(left != right),
+            _ => Object::Null,
+        }
+    }
 }
 
 impl Object {
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::token::Token;
+use crate::token::{Span, Token};
 
 use lazy_static::lazy_static;
 
@@ -22,18 +22,27 @@ pub struct Lexer<'l> {
     current_position: usize,
     read_position: usize,
     current_char: Option<char>,
+    current_span: Span,
+    token_span: Span,
 }
 
 impl<'l> Lexer<'l> {
-    pub fn new(input: &'l str) -> Lexer<'_> {
+    pub fn new(input: &'l str) -> Lexer<'l> {
         Lexer {
             input,
             current_position: 0,
-            read_position: 1,
-            current_char: input.chars().nth(0),
+            read_position: input.chars().next().map_or(0, char::len_utf8),
+            current_char: input.chars().next(),
+            current_span: Span { line: 1, column: 1 },
+            token_span: Span { line: 1, column: 1 },
         }
     }
 
+    /// Returns where the last token yielded by the lexer starts.
+    pub fn span(&self) -> Span {
+        self.token_span
+    }
+
     fn read_string(&mut self) -> String {
         if self.current_char.unwrap() != '"' {
             panic!(
@@ -87,20 +96,23 @@ impl<'l> Lexer<'l> {
     }
 
     fn peek_char(&self, pos: usize) -> Option<char> {
-        self.input.chars().nth(pos)
+        self.input.get(pos..).and_then(|rest| rest.chars().next())
     }
 
+    /// Moves to the next char, keeping track of its line and column.
     fn read_char(&mut self) {
-        if self.read_position > self.input.len() {
-            self.current_char = None;
-            return;
+        match self.current_char {
+            Some('\n') => {
+                self.current_span.line += 1;
+                self.current_span.column = 1;
+            }
+            Some(_) => self.current_span.column += 1,
+            None => return,
         }
 
-        let ch = self.input.chars().nth(self.read_position);
-
-        self.current_char = ch;
+        self.current_char = self.peek_char(self.read_position);
         self.current_position = self.read_position;
-        self.read_position += 1;
+        self.read_position += self.current_char.map_or(0, char::len_utf8);
     }
 
     fn skip_whitespaces(&mut self) {
@@ -142,6 +154,7 @@ impl Iterator for Lexer<'_> {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.skip_whitespaces();
+        self.token_span = self.current_span;
 
         if self.current_char.is_none() {
             return Some(Token::Eof);
@@ -155,10 +168,17 @@ impl Iterator for Lexer<'_> {
             '{' => Token::LeftBrace,
             '}' => Token::RightBrace,
             '+' => Token::Plus,
-            '-' => Token::Minus,
+            '-' => match self.peek_char(self.read_position) {
+                Some('>') => {
+                    self.read_char();
+                    Token::Arrow
+                }
+                _ => Token::Minus,
+            },
             '<' => Token::LessThan,
             '>' => Token::GreaterThan,
             ',' => Token::Comma,
+            ':' => Token::Colon,
             ';' => Token::Semicolon,
             '"' => Token::String(self.read_string()),
             '*' => Token::Asterisk,
@@ -1,6 +1,11 @@
 pub mod ast;
 mod builtin;
+pub mod compiler;
 pub mod evaluator;
 pub mod lexer;
+pub mod linter;
+pub mod optimizer;
 pub mod parser;
 pub mod token;
+pub mod typechecker;
+pub mod visitor;
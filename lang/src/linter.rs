@@ -0,0 +1,437 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::ast::{AstNode, BlockStatement, Expression, Statement};
+use crate::builtin::BUILTIN_FUNCTIONS;
+use crate::evaluator::{Evaluator, Object};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::token::{Span, Token};
+use crate::visitor::{self, Visitor};
+
+#[derive(Debug, Eq, Clone, Copy, PartialEq, Hash)]
+pub enum Rule {
+    UnusedBinding,
+    ShadowedName,
+    UnreachableCode,
+    UndefinedIdentifier,
+    BuiltinArity,
+    ConstantComparison,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [
+        Rule::UnusedBinding,
+        Rule::ShadowedName,
+        Rule::UnreachableCode,
+        Rule::UndefinedIdentifier,
+        Rule::BuiltinArity,
+        Rule::ConstantComparison,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::UnusedBinding => "unused-binding",
+            Rule::ShadowedName => "shadowed-name",
+            Rule::UnreachableCode => "unreachable-code",
+            Rule::UndefinedIdentifier => "undefined-identifier",
+            Rule::BuiltinArity => "builtin-arity",
+            Rule::ConstantComparison => "constant-comparison",
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rule::ALL
+            .into_iter()
+            .find(|rule| rule.id() == s)
+            .ok_or_else(|| format!("unknown lint rule: {}", s))
+    }
+}
+
+#[derive(Debug, Eq, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub span: Span,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.span, self.rule.id(), self.message)
+    }
+}
+
+/// Static checks over a kl-rs script. Rules can be suppressed with `allow`.
+#[derive(Debug, Default)]
+pub struct Linter {
+    allowed: HashSet<Rule>,
+}
+
+impl Linter {
+    pub fn new() -> Self {
+        Linter {
+            allowed: HashSet::new(),
+        }
+    }
+
+    pub fn allow(&mut self, rule: Rule) {
+        self.allowed.insert(rule);
+    }
+
+    /// Lints the given source code, returning the parser errors instead when
+    /// the code could not be parsed.
+    pub fn lint(&self, input: &str) -> Result<Vec<Diagnostic>, Vec<String>> {
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program();
+
+        if !parser.errors.is_empty() {
+            return Err(parser.errors);
+        }
+
+        let mut pass = LintPass {
+            spans: SourceSpans::new(input),
+            scopes: vec![Scope::default()],
+            function_name: None,
+            last_return_span: Span::default(),
+            diagnostics: Vec::new(),
+        };
+        pass.visit_node(&program);
+        pass.close_scope();
+
+        let mut diagnostics: Vec<Diagnostic> = pass
+            .diagnostics
+            .into_iter()
+            .filter(|diagnostic| !self.allowed.contains(&diagnostic.rule))
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span);
+
+        Ok(diagnostics)
+    }
+}
+
+/// The ast has no positions, so spans are recovered from the token stream: the
+/// ast is walked in source order and each node consumes the next occurrence of
+/// its token.
+struct SourceSpans {
+    spans: HashMap<Token, VecDeque<Span>>,
+}
+
+impl SourceSpans {
+    fn new(input: &str) -> Self {
+        let mut lexer = Lexer::new(input);
+        let mut spans: HashMap<Token, VecDeque<Span>> = HashMap::new();
+
+        while let Some(token) = lexer.next() {
+            if token == Token::Eof {
+                break;
+            }
+            spans.entry(token).or_default().push_back(lexer.span());
+        }
+
+        SourceSpans { spans }
+    }
+
+    fn next(&mut self, token: &Token) -> Span {
+        self.spans
+            .get_mut(token)
+            .and_then(|spans| spans.pop_front())
+            .unwrap_or_default()
+    }
+}
+
+struct Binding {
+    span: Span,
+    used: bool,
+}
+
+#[derive(Default)]
+struct Scope {
+    bindings: HashMap<String, Binding>,
+    parameters: HashSet<String>,
+}
+
+struct LintPass {
+    spans: SourceSpans,
+    scopes: Vec<Scope>,
+    /// Name of the function a let statement is binding, visible in its body.
+    function_name: Option<String>,
+    last_return_span: Span,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl LintPass {
+    fn report(&mut self, rule: Rule, span: Span, message: String) {
+        self.diagnostics.push(Diagnostic {
+            rule,
+            span,
+            message,
+        });
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("lint scope stack is empty")
+    }
+
+    fn close_scope(&mut self) {
+        let scope = self.scopes.pop().expect("lint scope stack is empty");
+        scope
+            .bindings
+            .into_iter()
+            .filter(|(_, binding)| !binding.used)
+            .for_each(|(name, binding)| self.report_unused(&name, binding.span));
+    }
+
+    fn report_unused(&mut self, name: &str, span: Span) {
+        self.report(
+            Rule::UnusedBinding,
+            span,
+            format!("'{}' is bound but never used", name),
+        );
+    }
+
+    /// The innermost scope binding the given name, if any. Evaluated
+    /// functions see the bindings they are called with, so the scopes around
+    /// a function are visible in its body.
+    fn defining_scope(&mut self, name: &str) -> Option<&mut Scope> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.bindings.contains_key(name) || scope.parameters.contains(name))
+    }
+
+    fn is_defined(&mut self, name: &str) -> bool {
+        self.defining_scope(name).is_some()
+    }
+
+    fn check_shadowing(&mut self, name: &str, span: Span) {
+        let is_bound = self.is_defined(name);
+
+        if BUILTIN_FUNCTIONS.contains_key(name) {
+            self.report(
+                Rule::ShadowedName,
+                span,
+                format!(
+                    "'{}' is hidden by the builtin function with the same name",
+                    name
+                ),
+            );
+        } else if is_bound {
+            self.report(
+                Rule::ShadowedName,
+                span,
+                format!("'{}' shadows an existing binding", name),
+            );
+        }
+    }
+
+    fn bind(&mut self, name: String, span: Span) {
+        self.check_shadowing(&name, span);
+
+        let previous = self
+            .scope()
+            .bindings
+            .insert(name.clone(), Binding { span, used: false });
+
+        if let Some(previous) = previous {
+            if !previous.used {
+                self.report_unused(&name, previous.span);
+            }
+        }
+    }
+
+    fn mark_used(&mut self, name: &str) {
+        if let Some(binding) = self
+            .defining_scope(name)
+            .and_then(|scope| scope.bindings.get_mut(name))
+        {
+            binding.used = true;
+        }
+    }
+
+    fn visit_statements(&mut self, statements: &[AstNode]) {
+        let mut return_span = None;
+        let mut is_reported = false;
+
+        for statement in statements {
+            if let Some(span) = return_span.take() {
+                if !is_reported {
+                    self.report(
+                        Rule::UnreachableCode,
+                        span,
+                        "code after this return statement is unreachable".to_string(),
+                    );
+                    is_reported = true;
+                }
+            }
+
+            self.visit_node(statement);
+
+            if let AstNode::Statement(statement) = statement {
+                if let Statement::ReturnStatement(_) = **statement {
+                    return_span = Some(self.last_return_span);
+                }
+            }
+        }
+    }
+
+    fn visit_call(&mut self, name: &str, arguments: &[Expression]) {
+        let span = self.spans.next(&Token::Identifier(name.to_string()));
+
+        if let Some(builtin) = BUILTIN_FUNCTIONS.get(name) {
+            if builtin.arity != arguments.len() {
+                self.report(
+                    Rule::BuiltinArity,
+                    span,
+                    format!(
+                        "'{}' expects {} argument(s) but was given {}",
+                        name,
+                        builtin.arity,
+                        arguments.len()
+                    ),
+                );
+            }
+        } else if self.is_defined(name) {
+            self.mark_used(name);
+        } else {
+            self.report(
+                Rule::UndefinedIdentifier,
+                span,
+                format!("call to undefined function '{}'", name),
+            );
+        }
+
+        arguments
+            .iter()
+            .for_each(|argument| self.visit_expression(argument));
+    }
+
+    fn check_comparison(
+        &mut self,
+        operator: &Token,
+        left: &Expression,
+        right: &Expression,
+        span: Span,
+    ) {
+        if !matches!(
+            operator,
+            Token::Equals | Token::NotEquals | Token::LessThan | Token::GreaterThan
+        ) {
+            return;
+        }
+
+        let message = match (left, right) {
+            (Expression::Identifier(left_name), Expression::Identifier(right_name))
+                if left_name == right_name =>
+            {
+                let result = matches!(operator, Token::Equals);
+                format!("comparing '{}' with itself is always {}", left_name, result)
+            }
+            _ if is_literal(left) && is_literal(right) => {
+                let comparison = Expression::Infix {
+                    operator: operator.clone(),
+                    left: Box::new(left.clone()),
+                    right: Box::new(right.clone()),
+                };
+                match Evaluator::new().eval(AstNode::Expression(Box::new(comparison))) {
+                    Object::Boolean(result) => {
+                        format!("comparison between constants is always {}", result)
+                    }
+                    _ => return,
+                }
+            }
+            _ => return,
+        };
+
+        self.report(Rule::ConstantComparison, span, message);
+    }
+}
+
+fn is_literal(expression: &Expression) -> bool {
+    matches!(
+        expression,
+        Expression::Int(_) | Expression::Boolean(_) | Expression::String(_)
+    )
+}
+
+impl Visitor for LintPass {
+    fn visit_node(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Program { statements } => self.visit_statements(statements),
+            node => visitor::walk_node(self, node),
+        }
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        self.visit_statements(&block.statements);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::ReturnStatement(value) => {
+                self.last_return_span = self.spans.next(&Token::Return);
+                self.visit_expression(value);
+            }
+            Statement::LetStatement { name, value, .. } => match &**name {
+                Expression::Identifier(name) => {
+                    let span = self.spans.next(&Token::Identifier(name.clone()));
+                    if let Expression::FunctionExpression { .. } = **value {
+                        self.function_name = Some(name.clone());
+                    }
+                    self.visit_expression(value);
+                    self.bind(name.clone(), span);
+                }
+                _ => visitor::walk_statement(self, statement),
+            },
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(name) => {
+                self.spans.next(&Token::Identifier(name.clone()));
+                self.mark_used(name);
+            }
+            Expression::Infix {
+                operator,
+                left,
+                right,
+            } => {
+                self.visit_expression(left);
+                let span = self.spans.next(operator);
+                self.visit_expression(right);
+                self.check_comparison(operator, left, right, span);
+            }
+            Expression::FunctionExpression {
+                parameters, body, ..
+            } => {
+                let mut scope = Scope::default();
+                scope.parameters.extend(self.function_name.take());
+                self.scopes.push(scope);
+
+                for parameter in parameters {
+                    if let Token::Identifier(name) = parameter {
+                        let span = self.spans.next(parameter);
+                        self.check_shadowing(name, span);
+                        self.scope().parameters.insert(name.clone());
+                    }
+                }
+
+                self.visit_block_statement(body);
+                self.close_scope();
+            }
+            Expression::CallExpression {
+                function,
+                arguments,
+            } => match &**function {
+                Expression::Identifier(name) => self.visit_call(name, arguments),
+                _ => visitor::walk_expression(self, expression),
+            },
+            expression => visitor::walk_expression(self, expression),
+        }
+    }
+}
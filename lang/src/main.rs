@@ -1,6 +1,15 @@
 use std::io::{self, BufRead, Write};
 
-use kl_rs::{evaluator::Evaluator, lexer::Lexer, parser::Parser, token::Token};
+use kl_rs::{
+    compiler,
+    evaluator::Evaluator,
+    lexer::Lexer,
+    linter::{Linter, Rule},
+    optimizer,
+    parser::Parser,
+    token::Token,
+    typechecker::TypeChecker,
+};
 
 gflags::define! {
     -h, --help = false
@@ -8,17 +17,41 @@ gflags::define! {
 gflags::define! {
     -v, --verbose = false
 }
+gflags::define! {
+    -O, --optimize = false
+}
+gflags::define! {
+    /// Infer types and report type errors before evaluating.
+    -t, --typecheck = false
+}
+gflags::define! {
+    /// Output file for the bytecode produced by `kl-rs compile`.
+    -o, --output <FILE> = "out.kvm"
+}
+gflags::define! {
+    /// Comma-separated list of lint rules to suppress, used by `kl-rs lint`.
+    --allow <RULES> = ""
+}
 
 fn main() {
     let stdin = std::io::stdin();
     let mut handle = stdin.lock();
-    gflags::parse();
+    let args = gflags::parse();
 
     if HELP.flag {
         gflags::print_help_and_exit(0);
     }
 
+    if let Some(&"lint") = args.first() {
+        std::process::exit(lint_files(&args[1..]));
+    }
+
+    if let Some(&"compile") = args.first() {
+        std::process::exit(compile_file(&args[1..]));
+    }
+
     let evaluator = Evaluator::new();
+    let mut type_checker = TypeChecker::new();
 
     loop {
         let mut input = String::new();
@@ -50,7 +83,20 @@ fn main() {
             debug_parser(Parser::new(Lexer::new(input.as_str())));
         }
 
-        let program = parser.parse_program();
+        let mut program = parser.parse_program();
+
+        if TYPECHECK.flag {
+            if let Err(errors) = type_checker.check(&program) {
+                errors
+                    .iter()
+                    .for_each(|err| println!("TYPE ERROR: {}", err));
+                continue;
+            }
+        }
+
+        if OPTIMIZE.flag {
+            program = optimizer::optimize(program);
+        }
 
         let object = evaluator.eval(program);
         println!("{}", object.inspect());
@@ -87,3 +133,92 @@ fn debug_parser(mut parser: Parser) {
     }
     println!();
 }
+
+fn lint_files(files: &[&str]) -> i32 {
+    if files.is_empty() {
+        eprintln!("Usage: kl-rs lint <file.kl>...");
+        return 2;
+    }
+
+    let mut linter = Linter::new();
+    for rule in ALLOW.flag.split(',').filter(|rule| !rule.is_empty()) {
+        match rule.parse::<Rule>() {
+            Ok(rule) => linter.allow(rule),
+            Err(err) => {
+                eprintln!("{}", err);
+                return 2;
+            }
+        }
+    }
+
+    let mut has_findings = false;
+
+    for file in files {
+        let input = match std::fs::read_to_string(file) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("{}: {}", file, err);
+                return 2;
+            }
+        };
+
+        match linter.lint(&input) {
+            Ok(diagnostics) => diagnostics.iter().for_each(|diagnostic| {
+                has_findings = true;
+                println!("{}:{}", file, diagnostic);
+            }),
+            Err(errors) => errors.iter().for_each(|err| {
+                has_findings = true;
+                println!("{}: parse-error: {}", file, err);
+            }),
+        }
+    }
+
+    has_findings as i32
+}
+
+fn compile_file(files: &[&str]) -> i32 {
+    let file = match files {
+        [file] => file,
+        _ => {
+            eprintln!("Usage: kl-rs compile <file.kl> -o <file.kvm>");
+            return 2;
+        }
+    };
+
+    let input = match std::fs::read_to_string(file) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("{}: {}", file, err);
+            return 2;
+        }
+    };
+
+    let mut parser = Parser::new(Lexer::new(&input));
+    let program = parser.parse_program();
+
+    if !parser.errors.is_empty() {
+        parser
+            .errors
+            .iter()
+            .for_each(|err| eprintln!("{}: parse-error: {}", file, err));
+        return 1;
+    }
+
+    let instructions = match compiler::compile(&program) {
+        Ok(instructions) => instructions,
+        Err(err) => {
+            eprintln!("{}: compile-error: {}", file, err);
+            return 1;
+        }
+    };
+
+    let bytecode = kvm::Program::new(instructions).to_bytes();
+
+    if let Err(err) = std::fs::write(OUTPUT.flag, bytecode) {
+        eprintln!("{}: {}", OUTPUT.flag, err);
+        return 2;
+    }
+
+    0
+}
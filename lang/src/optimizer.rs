@@ -0,0 +1,188 @@
+use crate::ast::{AstNode, BlockStatement, Expression, Statement};
+use crate::token::Token;
+use crate::visitor::{self, Folder};
+
+/// Runs every ast optimisation pass over the given node.
+pub fn optimize(node: AstNode) -> AstNode {
+    ConstantFolder.fold_node(node)
+}
+
+/// Folds constant arithmetic, string concatenation and comparisons, prunes
+/// `if` branches whose condition is known ahead of time and drops statements
+/// that follow a `return`.
+///
+/// Expressions that would fail at runtime (division by zero, integer
+/// overflow) are left untouched so the error still happens when evaluating.
+pub struct ConstantFolder;
+
+impl Folder for ConstantFolder {
+    fn fold_node(&mut self, node: AstNode) -> AstNode {
+        match node {
+            AstNode::Program { statements } => AstNode::Program {
+                statements: self.fold_statements(statements),
+            },
+            node => visitor::fold_node(self, node),
+        }
+    }
+
+    fn fold_block_statement(&mut self, block: BlockStatement) -> BlockStatement {
+        BlockStatement {
+            statements: self.fold_statements(block.statements),
+        }
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        match visitor::fold_expression(self, expression) {
+            Expression::Prefix { operator, right } => fold_prefix_expression(operator, *right),
+            Expression::Infix {
+                operator,
+                left,
+                right,
+            } => fold_infix_expression(operator, *left, *right),
+            Expression::IfExpression {
+                condition,
+                consequence,
+                alternative,
+            } => fold_if_expression(*condition, *consequence, alternative.map(|block| *block)),
+            expression => expression,
+        }
+    }
+}
+
+impl ConstantFolder {
+    fn fold_statements(&mut self, statements: Vec<AstNode>) -> Vec<AstNode> {
+        let mut folded = Vec::new();
+        let statements_len = statements.len();
+
+        for (idx, statement) in statements.into_iter().enumerate() {
+            let statement = self.fold_node(statement);
+            let is_last = idx + 1 == statements_len;
+
+            match statement {
+                AstNode::Expression(expression) => match *expression {
+                    // blocks do not open a new scope, so a branch that is always
+                    // taken can be spliced into the enclosing statements
+                    Expression::IfExpression {
+                        condition,
+                        consequence,
+                        alternative: None,
+                    } if *condition == Expression::Boolean(true)
+                        && !consequence.statements.is_empty() =>
+                    {
+                        folded.extend(consequence.statements)
+                    }
+                    // a branch that is never taken only matters as the block value
+                    Expression::IfExpression {
+                        condition,
+                        consequence,
+                        alternative: None,
+                    } if *condition == Expression::Boolean(false)
+                        && consequence.statements.is_empty()
+                        && !is_last => {}
+                    expression => folded.push(AstNode::Expression(Box::new(expression))),
+                },
+                statement => folded.push(statement),
+            }
+
+            if folded.last().is_some_and(is_return_statement) {
+                break;
+            }
+        }
+
+        folded
+    }
+}
+
+fn is_return_statement(node: &AstNode) -> bool {
+    matches!(node, AstNode::Statement(statement) if matches!(**statement, Statement::ReturnStatement(_)))
+}
+
+fn fold_prefix_expression(operator: Token, right: Expression) -> Expression {
+    let folded = match (&operator, &right) {
+        (Token::Bang, Expression::Boolean(value)) => Some(Expression::Boolean(!value)),
+        (Token::Minus, Expression::Int(value)) => value.checked_neg().map(Expression::Int),
+        _ => None,
+    };
+
+    folded.unwrap_or(Expression::Prefix {
+        operator,
+        right: Box::new(right),
+    })
+}
+
+fn fold_infix_expression(operator: Token, left: Expression, right: Expression) -> Expression {
+    let folded = match (&left, &right) {
+        (Expression::Int(left_int), Expression::Int(right_int)) => {
+            fold_integer_infix_expression(&operator, *left_int, *right_int)
+        }
+        (Expression::String(left_str), Expression::String(right_str)) => match operator {
+            Token::Plus => Some(Expression::String(format!("{left_str}{right_str}"))),
+            Token::Equals => Some(Expression::Boolean(left_str == right_str)),
+            Token::NotEquals => Some(Expression::Boolean(left_str != right_str)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    folded.unwrap_or(Expression::Infix {
+        operator,
+        left: Box::new(left),
+        right: Box::new(right),
+    })
+}
+
+fn fold_integer_infix_expression(operator: &Token, left: i32, right: i32) -> Option<Expression> {
+    match operator {
+        Token::Plus => left.checked_add(right).map(Expression::Int),
+        Token::Minus => left.checked_sub(right).map(Expression::Int),
+        Token::Asterisk => left.checked_mul(right).map(Expression::Int),
+        Token::Slash => left.checked_div(right).map(Expression::Int),
+        Token::Equals => Some(Expression::Boolean(left == right)),
+        Token::NotEquals => Some(Expression::Boolean(left != right)),
+        Token::LessThan => Some(Expression::Boolean(left < right)),
+        Token::GreaterThan => Some(Expression::Boolean(left > right)),
+        _ => None,
+    }
+}
+
+fn fold_if_expression(
+    condition: Expression,
+    consequence: BlockStatement,
+    alternative: Option<BlockStatement>,
+) -> Expression {
+    let is_truthy = match condition {
+        Expression::Int(value) => value != 0,
+        Expression::Boolean(value) => value,
+        Expression::String(_) => false,
+        _ => {
+            return Expression::IfExpression {
+                condition: Box::new(condition),
+                consequence: Box::new(consequence),
+                alternative: alternative.map(Box::new),
+            }
+        }
+    };
+
+    let taken = match (is_truthy, alternative) {
+        (true, _) => Some(consequence),
+        (false, alternative) => alternative,
+    };
+
+    match taken {
+        Some(block) => match block.statements.as_slice() {
+            [AstNode::Expression(expression)] => *expression.clone(),
+            _ => Expression::IfExpression {
+                condition: Box::new(Expression::Boolean(true)),
+                consequence: Box::new(block),
+                alternative: None,
+            },
+        },
+        None => Expression::IfExpression {
+            condition: Box::new(Expression::Boolean(false)),
+            consequence: Box::new(BlockStatement {
+                statements: Vec::new(),
+            }),
+            alternative: None,
+        },
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    ast::{AstNode, BlockStatement, Expression, Statement}, lexer::Lexer, token::Token
+    ast::{AstNode, BlockStatement, Expression, Statement, TypeAnnotation}, lexer::Lexer, token::Token
 };
 
 #[derive(Debug, PartialEq, PartialOrd)]
@@ -81,6 +81,16 @@ impl<'p> Parser<'p> {
 
                 self.advance_tokens();
 
+                let annotation = match self.current_token.clone()? {
+                    Token::Colon => {
+                        self.advance_tokens();
+                        let annotation = self.parse_type_annotation()?;
+                        self.advance_tokens();
+                        Some(annotation)
+                    }
+                    _ => None,
+                };
+
                 if self.current_token.clone()? != Token::Assign {
                     self.report_expected_token_error(Token::Assign, self.current_token.clone());
                     return None;
@@ -99,6 +109,7 @@ impl<'p> Parser<'p> {
 
                 Some(AstNode::Statement(Box::new(Statement::LetStatement {
                     name,
+                    annotation,
                     value,
                 })))
             }
@@ -225,10 +236,8 @@ impl<'p> Parser<'p> {
             elements.push(exp);
         }
 
-        self.advance_tokens();
-
-        if !self.expect_current_token(Token::RightBracket) {
-            self.report_expected_token_error(Token::RightBracket, self.current_token.clone());
+        if !self.expect_next_token(Token::RightBracket) {
+            self.report_expected_token_error(Token::RightBracket, self.next_token.clone());
             return None;
         }
 
@@ -305,10 +314,9 @@ impl<'p> Parser<'p> {
 
         let consequence = Box::new(self.parse_block_statement()?);
 
-        self.advance_tokens();
-
-        let alternative = match self.current_token.clone()? {
+        let alternative = match self.next_token.clone()? {
             Token::Else => {
+                self.advance_tokens();
                 self.advance_tokens();
                 Some(Box::new(self.parse_block_statement()?))
             }
@@ -331,18 +339,35 @@ impl<'p> Parser<'p> {
             return None;
         }
 
-        let parameters = self.parse_function_parameters()?;
+        let (parameters, parameter_types) = self.parse_function_parameters()?;
+
+        let return_type = match self.current_token.clone()? {
+            Token::Arrow => {
+                self.advance_tokens();
+                let return_type = self.parse_type_annotation()?;
+                self.advance_tokens();
+                Some(return_type)
+            }
+            _ => None,
+        };
+
         let body = Box::new(self.parse_block_statement()?);
 
-        Some(Expression::FunctionExpression { parameters, body })
+        Some(Expression::FunctionExpression {
+            parameters,
+            parameter_types,
+            return_type,
+            body,
+        })
     }
 
-    fn parse_function_parameters(&mut self) -> Option<Vec<Token>> {
+    fn parse_function_parameters(&mut self) -> Option<(Vec<Token>, Vec<Option<TypeAnnotation>>)> {
         let mut parameters = Vec::new();
+        let mut parameter_types = Vec::new();
 
         if self.expect_next_token(Token::RightParentesis) {
             self.advance_tokens();
-            return Some(parameters);
+            return Some((parameters, parameter_types));
         }
 
         self.advance_tokens();
@@ -350,6 +375,7 @@ impl<'p> Parser<'p> {
         // TODO: handle unwrap
         let identifier = self.current_token.clone().unwrap();
         parameters.push(identifier);
+        parameter_types.push(self.parse_parameter_type()?);
 
         while let Some(Token::Comma) = self.next_token {
             self.advance_tokens();
@@ -357,6 +383,7 @@ impl<'p> Parser<'p> {
             // TODO: handle unwrap
             let identifier = self.current_token.clone().unwrap();
             parameters.push(identifier);
+            parameter_types.push(self.parse_parameter_type()?);
         }
 
         if !self.expect_next_token(Token::RightParentesis) {
@@ -366,7 +393,88 @@ impl<'p> Parser<'p> {
 
         self.advance_tokens();
 
-        Some(parameters)
+        Some((parameters, parameter_types))
+    }
+
+    fn parse_parameter_type(&mut self) -> Option<Option<TypeAnnotation>> {
+        if !self.expect_next_token(Token::Colon) {
+            return Some(None);
+        }
+
+        self.advance_tokens();
+        Some(Some(self.parse_type_annotation()?))
+    }
+
+    fn parse_type_annotation(&mut self) -> Option<TypeAnnotation> {
+        match self.current_token.clone()? {
+            Token::Identifier(name) => match name.as_str() {
+                "int" => Some(TypeAnnotation::Int),
+                "bool" => Some(TypeAnnotation::Bool),
+                "string" => Some(TypeAnnotation::String),
+                _ => {
+                    self.report_error(&format!("unknown type: {}", name));
+                    None
+                }
+            },
+            Token::LeftBracket => {
+                self.advance_tokens();
+                let element_type = self.parse_type_annotation()?;
+
+                if !self.expect_next_token(Token::RightBracket) {
+                    self.report_expected_token_error(Token::RightBracket, self.next_token.clone());
+                    return None;
+                }
+
+                Some(TypeAnnotation::Array(Box::new(element_type)))
+            }
+            Token::Function => {
+                if !self.expect_next_token(Token::LeftParentesis) {
+                    self.report_expected_token_error(
+                        Token::LeftParentesis,
+                        self.next_token.clone(),
+                    );
+                    return None;
+                }
+
+                let mut parameters = Vec::new();
+
+                if !self.expect_next_token(Token::RightParentesis) {
+                    self.advance_tokens();
+                    parameters.push(self.parse_type_annotation()?);
+
+                    while let Some(Token::Comma) = self.next_token {
+                        self.advance_tokens();
+                        self.advance_tokens();
+                        parameters.push(self.parse_type_annotation()?);
+                    }
+
+                    if !self.expect_next_token(Token::RightParentesis) {
+                        self.report_expected_token_error(
+                            Token::RightParentesis,
+                            self.next_token.clone(),
+                        );
+                        return None;
+                    }
+                }
+
+                if !self.expect_next_token(Token::Arrow) {
+                    self.report_expected_token_error(Token::Arrow, self.next_token.clone());
+                    return None;
+                }
+                self.advance_tokens();
+
+                let return_type = Box::new(self.parse_type_annotation()?);
+
+                Some(TypeAnnotation::Function {
+                    parameters,
+                    return_type,
+                })
+            }
+            token => {
+                self.report_error(&format!("expected a type, got '{:?}'", token));
+                None
+            }
+        }
     }
 
     fn parse_block_statement(&mut self) -> Option<BlockStatement> {
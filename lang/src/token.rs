@@ -21,6 +21,8 @@ pub enum Token {
     If,
     Else,
     Comma,
+    Colon,
+    Arrow,
     Semicolon,
     RightParentesis,
     LeftBrace,
@@ -31,3 +33,16 @@ pub enum Token {
     Identifier(String),
     String(String),
 }
+
+/// Position of a token in the source code, both line and column start at 1.
+#[derive(Debug, Default, Eq, Clone, Copy, PartialEq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
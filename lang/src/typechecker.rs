@@ -0,0 +1,605 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+use crate::ast::{AstNode, BlockStatement, Expression, Statement, TypeAnnotation};
+use crate::builtin::BUILTIN_FUNCTIONS;
+use crate::token::Token;
+
+#[derive(Debug, Eq, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Bool,
+    String,
+    Null,
+    Array(Box<Type>),
+    Function {
+        parameters: Vec<Type>,
+        return_type: Box<Type>,
+    },
+    Variable(usize),
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "string"),
+            Type::Null => write!(f, "null"),
+            Type::Array(element_type) => write!(f, "[{}]", element_type),
+            Type::Function {
+                parameters,
+                return_type,
+            } => {
+                let parameters_str = parameters
+                    .iter()
+                    .map(|parameter| parameter.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                write!(f, "fn({}) -> {}", parameters_str, return_type)
+            }
+            Type::Variable(id) => write!(f, "t{}", id),
+        }
+    }
+}
+
+impl From<&TypeAnnotation> for Type {
+    fn from(annotation: &TypeAnnotation) -> Self {
+        match annotation {
+            TypeAnnotation::Int => Type::Int,
+            TypeAnnotation::Bool => Type::Bool,
+            TypeAnnotation::String => Type::String,
+            TypeAnnotation::Array(element_type) => {
+                Type::Array(Box::new(element_type.as_ref().into()))
+            }
+            TypeAnnotation::Function {
+                parameters,
+                return_type,
+            } => Type::Function {
+                parameters: parameters.iter().map(Type::from).collect(),
+                return_type: Box::new(return_type.as_ref().into()),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Eq, Clone, PartialEq)]
+pub struct TypeError {
+    pub message: String,
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// A type whose `variables` are generalised, so each use of the binding gets
+/// fresh copies of them.
+#[derive(Debug, Clone)]
+struct Scheme {
+    variables: Vec<usize>,
+    ty: Type,
+}
+
+/// Hindley-Milner style type inference over the ast. Bindings are kept
+/// between calls to `check`, so it can follow a REPL session.
+///
+/// Names are looked up from the innermost scope out to the bindings of the
+/// program, as evaluated functions see the bindings they are called with, and
+/// a function bound with `let` can call itself by its name.
+pub struct TypeChecker {
+    substitution: Vec<Option<Type>>,
+    scopes: Vec<HashMap<String, Scheme>>,
+    return_types: Vec<Type>,
+    errors: Vec<TypeError>,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        TypeChecker {
+            substitution: Vec::new(),
+            scopes: vec![HashMap::new()],
+            return_types: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Infers the type of the given node, reporting every type error found.
+    pub fn check(&mut self, node: &AstNode) -> Result<Type, Vec<TypeError>> {
+        self.errors.clear();
+
+        let return_type = self.fresh_variable();
+        self.return_types.push(return_type.clone());
+        let ty = self.infer_node(node);
+        self.unify(&ty, &return_type, "program result");
+        self.return_types.pop();
+
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+
+        Ok(self.resolve(&return_type))
+    }
+
+    fn infer_node(&mut self, node: &AstNode) -> Type {
+        match node {
+            AstNode::Program { statements } => self.infer_statements(statements),
+            AstNode::Statement(statement) => self.infer_statement(statement).0,
+            AstNode::Expression(expression) => self.infer_expression(expression),
+        }
+    }
+
+    fn infer_statements(&mut self, statements: &[AstNode]) -> Type {
+        let mut ty = Type::Null;
+        let mut diverges = false;
+
+        for statement in statements {
+            let (statement_type, is_return) = match statement {
+                AstNode::Statement(statement) => self.infer_statement(statement),
+                node => (self.infer_node(node), false),
+            };
+
+            if !diverges {
+                ty = statement_type;
+            }
+            diverges |= is_return;
+        }
+
+        ty
+    }
+
+    /// Returns the type of the statement and whether it leaves the function.
+    fn infer_statement(&mut self, statement: &Statement) -> (Type, bool) {
+        match statement {
+            Statement::ReturnStatement(value) => {
+                let ty = self.infer_expression(value);
+                let return_type = self
+                    .return_types
+                    .last()
+                    .cloned()
+                    .expect("return type stack is empty");
+                self.unify(&ty, &return_type, "return statement");
+
+                (self.fresh_variable(), true)
+            }
+            Statement::LetStatement {
+                name,
+                annotation,
+                value,
+            } => {
+                // the name of a function is bound while inferring its body, to
+                // a single type so recursive calls don't generalise it
+                let own_type = match (&**name, &**value) {
+                    (Expression::Identifier(name), Expression::FunctionExpression { .. }) => {
+                        let ty = self.fresh_variable();
+                        self.scope().insert(
+                            name.clone(),
+                            Scheme {
+                                variables: Vec::new(),
+                                ty: ty.clone(),
+                            },
+                        );
+                        Some(ty)
+                    }
+                    _ => None,
+                };
+
+                let ty = self.infer_expression(value);
+
+                if let Some(own_type) = own_type {
+                    self.unify(&own_type, &ty, "recursive function");
+                }
+                if let Some(annotation) = annotation {
+                    self.unify(&ty, &annotation.into(), "let statement");
+                }
+
+                if let Expression::Identifier(name) = &**name {
+                    self.scope().remove(name);
+
+                    // only syntactic functions are generalised, other values
+                    // keep a single type for every use
+                    let scheme = match **value {
+                        Expression::FunctionExpression { .. } => self.generalize(&ty),
+                        _ => Scheme {
+                            variables: Vec::new(),
+                            ty: ty.clone(),
+                        },
+                    };
+                    self.scope().insert(name.clone(), scheme);
+                }
+
+                (ty, false)
+            }
+        }
+    }
+
+    fn infer_block_statement(&mut self, block: &BlockStatement) -> Type {
+        self.infer_statements(&block.statements)
+    }
+
+    fn infer_expression(&mut self, expression: &Expression) -> Type {
+        match expression {
+            Expression::Int(_) => Type::Int,
+            Expression::Boolean(_) => Type::Bool,
+            Expression::String(_) => Type::String,
+            Expression::Identifier(name) => self.infer_identifier(name),
+            Expression::Array(elements) => {
+                let element_type = self.fresh_variable();
+
+                for element in elements {
+                    let ty = self.infer_expression(element);
+                    self.unify(&ty, &element_type, "array element");
+                }
+
+                Type::Array(Box::new(element_type))
+            }
+            Expression::Prefix { operator, right } => {
+                let ty = self.infer_expression(right);
+                let operand_type = match operator {
+                    Token::Minus => Type::Int,
+                    _ => Type::Bool,
+                };
+                self.unify(&ty, &operand_type, &format!("operand of '{:?}'", operator));
+                operand_type
+            }
+            Expression::Infix {
+                operator,
+                left,
+                right,
+            } => {
+                let left_type = self.infer_expression(left);
+                let right_type = self.infer_expression(right);
+                self.infer_infix_expression(operator, &left_type, &right_type)
+            }
+            Expression::IfExpression {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                let condition_type = self.infer_expression(condition);
+                // integers are truthy as well, like in C
+                if self.resolve(&condition_type) != Type::Int {
+                    self.unify(&condition_type, &Type::Bool, "if condition");
+                }
+
+                let consequence_type = self.infer_block_statement(consequence);
+
+                match alternative {
+                    Some(alternative) => {
+                        let alternative_type = self.infer_block_statement(alternative);
+                        self.unify(&alternative_type, &consequence_type, "else branch");
+                        consequence_type
+                    }
+                    // evaluates to null when the condition does not hold
+                    None => Type::Null,
+                }
+            }
+            Expression::FunctionExpression {
+                parameters,
+                parameter_types,
+                return_type,
+                body,
+            } => {
+                let mut scope = HashMap::new();
+                let mut types = Vec::new();
+
+                for (idx, parameter) in parameters.iter().enumerate() {
+                    let ty = match parameter_types.get(idx) {
+                        Some(Some(annotation)) => annotation.into(),
+                        _ => self.fresh_variable(),
+                    };
+
+                    if let Token::Identifier(name) = parameter {
+                        scope.insert(
+                            name.clone(),
+                            Scheme {
+                                variables: Vec::new(),
+                                ty: ty.clone(),
+                            },
+                        );
+                    }
+                    types.push(ty);
+                }
+
+                let return_type = match return_type {
+                    Some(annotation) => annotation.into(),
+                    None => self.fresh_variable(),
+                };
+
+                self.scopes.push(scope);
+                self.return_types.push(return_type.clone());
+
+                let body_type = self.infer_block_statement(body);
+                self.unify(&body_type, &return_type, "function body");
+
+                self.return_types.pop();
+                self.scopes.pop();
+
+                Type::Function {
+                    parameters: types,
+                    return_type: Box::new(return_type),
+                }
+            }
+            Expression::CallExpression {
+                function,
+                arguments,
+            } => {
+                // `len` counts the bytes of a string or the elements of an
+                // array, and takes a string unless it is given an array
+                if let (Expression::Identifier(name), [argument]) =
+                    (&**function, arguments.as_slice())
+                {
+                    if name == "len" {
+                        let argument_type = self.infer_expression(argument);
+                        if !matches!(self.resolve(&argument_type), Type::Array(_)) {
+                            self.unify(&argument_type, &Type::String, "argument of len");
+                        }
+                        return Type::Int;
+                    }
+                }
+
+                let function_type = self.infer_expression(function);
+                let argument_types: Vec<Type> = arguments
+                    .iter()
+                    .map(|argument| self.infer_expression(argument))
+                    .collect();
+
+                if let Type::Function { parameters, .. } = self.resolve(&function_type) {
+                    if parameters.len() != argument_types.len() {
+                        self.report(format!(
+                            "function of type {} expects {} argument(s), got {}",
+                            self.resolve(&function_type),
+                            parameters.len(),
+                            argument_types.len()
+                        ));
+                        return self.fresh_variable();
+                    }
+                }
+
+                let return_type = self.fresh_variable();
+                let expected = Type::Function {
+                    parameters: argument_types,
+                    return_type: Box::new(return_type.clone()),
+                };
+                self.unify(&function_type, &expected, "function call");
+
+                return_type
+            }
+        }
+    }
+
+    fn infer_identifier(&mut self, name: &str) -> Type {
+        // builtins win over bindings with the same name when evaluating
+        if let Some(builtin) = BUILTIN_FUNCTIONS.get(name) {
+            return match name {
+                "len" => Type::Function {
+                    parameters: vec![Type::String],
+                    return_type: Box::new(Type::Int),
+                },
+                "puts" => Type::Function {
+                    parameters: vec![self.fresh_variable()],
+                    return_type: Box::new(Type::Null),
+                },
+                _ => Type::Function {
+                    parameters: (0..builtin.arity).map(|_| self.fresh_variable()).collect(),
+                    return_type: Box::new(self.fresh_variable()),
+                },
+            };
+        }
+
+        let scheme = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned();
+
+        match scheme {
+            Some(scheme) => self.instantiate(&scheme),
+            None => {
+                self.report(format!("undefined identifier '{}'", name));
+                self.fresh_variable()
+            }
+        }
+    }
+
+    fn infer_infix_expression(&mut self, operator: &Token, left: &Type, right: &Type) -> Type {
+        let context = format!("operands of '{:?}'", operator);
+
+        match operator {
+            Token::Plus | Token::Equals | Token::NotEquals => {
+                self.unify(right, left, &context);
+
+                // strings support concatenation and equality, everything else
+                // works on integers only
+                let operand_type = match self.resolve(left) {
+                    Type::String => Type::String,
+                    _ => {
+                        self.unify(left, &Type::Int, &context);
+                        Type::Int
+                    }
+                };
+
+                match operator {
+                    Token::Plus => operand_type,
+                    _ => Type::Bool,
+                }
+            }
+            Token::Minus | Token::Asterisk | Token::Slash => {
+                self.unify(left, &Type::Int, &context);
+                self.unify(right, &Type::Int, &context);
+                Type::Int
+            }
+            Token::LessThan | Token::GreaterThan => {
+                self.unify(left, &Type::Int, &context);
+                self.unify(right, &Type::Int, &context);
+                Type::Bool
+            }
+            _ => {
+                self.report(format!("unsupported infix operator '{:?}'", operator));
+                self.fresh_variable()
+            }
+        }
+    }
+
+    fn scope(&mut self) -> &mut HashMap<String, Scheme> {
+        self.scopes.last_mut().expect("type scope stack is empty")
+    }
+
+    fn report(&mut self, message: String) {
+        self.errors.push(TypeError { message });
+    }
+
+    fn fresh_variable(&mut self) -> Type {
+        self.substitution.push(None);
+        Type::Variable(self.substitution.len() - 1)
+    }
+
+    /// Applies the current substitution to the whole type.
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Variable(id) => match &self.substitution[*id] {
+                Some(ty) => self.resolve(ty),
+                None => ty.clone(),
+            },
+            Type::Array(element_type) => Type::Array(Box::new(self.resolve(element_type))),
+            Type::Function {
+                parameters,
+                return_type,
+            } => Type::Function {
+                parameters: parameters.iter().map(|ty| self.resolve(ty)).collect(),
+                return_type: Box::new(self.resolve(return_type)),
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    fn unify(&mut self, actual: &Type, expected: &Type, context: &str) {
+        if !self.try_unify(actual, expected) {
+            self.report(format!(
+                "type mismatch in {}: expected {}, found {}",
+                context,
+                self.resolve(expected),
+                self.resolve(actual)
+            ));
+        }
+    }
+
+    fn try_unify(&mut self, left: &Type, right: &Type) -> bool {
+        let left = self.resolve(left);
+        let right = self.resolve(right);
+
+        match (&left, &right) {
+            (Type::Variable(left_id), Type::Variable(right_id)) if left_id == right_id => true,
+            (Type::Variable(id), ty) | (ty, Type::Variable(id)) => {
+                if self.free_variables(ty).contains(id) {
+                    return false;
+                }
+                self.substitution[*id] = Some(ty.clone());
+                true
+            }
+            (Type::Array(left_element), Type::Array(right_element)) => {
+                self.try_unify(left_element, right_element)
+            }
+            (
+                Type::Function {
+                    parameters: left_parameters,
+                    return_type: left_return,
+                },
+                Type::Function {
+                    parameters: right_parameters,
+                    return_type: right_return,
+                },
+            ) => {
+                left_parameters.len() == right_parameters.len()
+                    && left_parameters
+                        .iter()
+                        .zip(right_parameters)
+                        .all(|(left, right)| self.try_unify(left, right))
+                    && self.try_unify(left_return, right_return)
+            }
+            (left, right) => left == right,
+        }
+    }
+
+    fn free_variables(&self, ty: &Type) -> HashSet<usize> {
+        match self.resolve(ty) {
+            Type::Variable(id) => HashSet::from([id]),
+            Type::Array(element_type) => self.free_variables(&element_type),
+            Type::Function {
+                parameters,
+                return_type,
+            } => parameters
+                .iter()
+                .chain(std::iter::once(return_type.as_ref()))
+                .flat_map(|ty| self.free_variables(ty))
+                .collect(),
+            _ => HashSet::new(),
+        }
+    }
+
+    fn generalize(&self, ty: &Type) -> Scheme {
+        let bound_variables: HashSet<usize> = self
+            .scopes
+            .iter()
+            .flat_map(|scope| scope.values())
+            .flat_map(|scheme| {
+                let variables = self.free_variables(&scheme.ty);
+                variables
+                    .into_iter()
+                    .filter(|id| !scheme.variables.contains(id))
+                    .collect::<Vec<usize>>()
+            })
+            .collect();
+
+        let mut variables: Vec<usize> = self
+            .free_variables(ty)
+            .into_iter()
+            .filter(|id| !bound_variables.contains(id))
+            .collect();
+        variables.sort();
+
+        Scheme {
+            variables,
+            ty: self.resolve(ty),
+        }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let fresh: HashMap<usize, Type> = scheme
+            .variables
+            .iter()
+            .map(|id| (*id, self.fresh_variable()))
+            .collect();
+
+        replace_variables(&scheme.ty, &fresh)
+    }
+}
+
+fn replace_variables(ty: &Type, replacements: &HashMap<usize, Type>) -> Type {
+    match ty {
+        Type::Variable(id) => replacements.get(id).cloned().unwrap_or(Type::Variable(*id)),
+        Type::Array(element_type) => {
+            Type::Array(Box::new(replace_variables(element_type, replacements)))
+        }
+        Type::Function {
+            parameters,
+            return_type,
+        } => Type::Function {
+            parameters: parameters
+                .iter()
+                .map(|ty| replace_variables(ty, replacements))
+                .collect(),
+            return_type: Box::new(replace_variables(return_type, replacements)),
+        },
+        ty => ty.clone(),
+    }
+}
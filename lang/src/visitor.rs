@@ -0,0 +1,213 @@
+use crate::ast::{AstNode, BlockStatement, Expression, Statement};
+
+/// Read-only traversal over the ast. Every method defaults to walking into
+/// the children of the visited node, so implementors only override the nodes
+/// they care about and call the matching `walk_*` function to keep recursing.
+pub trait Visitor {
+    fn visit_node(&mut self, node: &AstNode) {
+        walk_node(self, node);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        walk_block_statement(self, block);
+    }
+}
+
+pub fn walk_node<V: Visitor + ?Sized>(visitor: &mut V, node: &AstNode) {
+    match node {
+        AstNode::Program { statements } => statements
+            .iter()
+            .for_each(|statement| visitor.visit_node(statement)),
+        AstNode::Statement(statement) => visitor.visit_statement(statement),
+        AstNode::Expression(expression) => visitor.visit_expression(expression),
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::ReturnStatement(value) => visitor.visit_expression(value),
+        Statement::LetStatement { name, value, .. } => {
+            visitor.visit_expression(name);
+            visitor.visit_expression(value);
+        }
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Int(_)
+        | Expression::Identifier(_)
+        | Expression::Boolean(_)
+        | Expression::String(_) => {}
+        Expression::Array(elements) => elements
+            .iter()
+            .for_each(|element| visitor.visit_expression(element)),
+        Expression::Prefix { right, .. } => visitor.visit_expression(right),
+        Expression::Infix { left, right, .. } => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        Expression::IfExpression {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            visitor.visit_expression(condition);
+            visitor.visit_block_statement(consequence);
+            if let Some(alternative) = alternative {
+                visitor.visit_block_statement(alternative);
+            }
+        }
+        Expression::FunctionExpression { body, .. } => visitor.visit_block_statement(body),
+        Expression::CallExpression {
+            function,
+            arguments,
+        } => {
+            visitor.visit_expression(function);
+            arguments
+                .iter()
+                .for_each(|argument| visitor.visit_expression(argument));
+        }
+    }
+}
+
+pub fn walk_block_statement<V: Visitor + ?Sized>(visitor: &mut V, block: &BlockStatement) {
+    block
+        .statements
+        .iter()
+        .for_each(|statement| visitor.visit_node(statement));
+}
+
+/// Tree rewriting traversal over the ast. Each method takes ownership of a
+/// node and returns its replacement, by default rebuilding the node from its
+/// folded children so implementors only override the nodes they rewrite.
+pub trait Folder {
+    fn fold_node(&mut self, node: AstNode) -> AstNode {
+        fold_node(self, node)
+    }
+
+    fn fold_statement(&mut self, statement: Statement) -> Statement {
+        fold_statement(self, statement)
+    }
+
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression(self, expression)
+    }
+
+    fn fold_block_statement(&mut self, block: BlockStatement) -> BlockStatement {
+        fold_block_statement(self, block)
+    }
+}
+
+pub fn fold_node<F: Folder + ?Sized>(folder: &mut F, node: AstNode) -> AstNode {
+    match node {
+        AstNode::Program { statements } => AstNode::Program {
+            statements: statements
+                .into_iter()
+                .map(|statement| folder.fold_node(statement))
+                .collect(),
+        },
+        AstNode::Statement(statement) => {
+            AstNode::Statement(Box::new(folder.fold_statement(*statement)))
+        }
+        AstNode::Expression(expression) => {
+            AstNode::Expression(Box::new(folder.fold_expression(*expression)))
+        }
+    }
+}
+
+pub fn fold_statement<F: Folder + ?Sized>(folder: &mut F, statement: Statement) -> Statement {
+    match statement {
+        Statement::ReturnStatement(value) => {
+            Statement::ReturnStatement(Box::new(folder.fold_expression(*value)))
+        }
+        Statement::LetStatement {
+            name,
+            annotation,
+            value,
+        } => Statement::LetStatement {
+            name: Box::new(folder.fold_expression(*name)),
+            annotation,
+            value: Box::new(folder.fold_expression(*value)),
+        },
+    }
+}
+
+pub fn fold_expression<F: Folder + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    match expression {
+        Expression::Int(_)
+        | Expression::Identifier(_)
+        | Expression::Boolean(_)
+        | Expression::String(_) => expression,
+        Expression::Array(elements) => Expression::Array(
+            elements
+                .into_iter()
+                .map(|element| folder.fold_expression(element))
+                .collect(),
+        ),
+        Expression::Prefix { operator, right } => Expression::Prefix {
+            operator,
+            right: Box::new(folder.fold_expression(*right)),
+        },
+        Expression::Infix {
+            operator,
+            left,
+            right,
+        } => Expression::Infix {
+            operator,
+            left: Box::new(folder.fold_expression(*left)),
+            right: Box::new(folder.fold_expression(*right)),
+        },
+        Expression::IfExpression {
+            condition,
+            consequence,
+            alternative,
+        } => Expression::IfExpression {
+            condition: Box::new(folder.fold_expression(*condition)),
+            consequence: Box::new(folder.fold_block_statement(*consequence)),
+            alternative: alternative.map(|block| Box::new(folder.fold_block_statement(*block))),
+        },
+        Expression::FunctionExpression {
+            parameters,
+            parameter_types,
+            return_type,
+            body,
+        } => Expression::FunctionExpression {
+            parameters,
+            parameter_types,
+            return_type,
+            body: Box::new(folder.fold_block_statement(*body)),
+        },
+        Expression::CallExpression {
+            function,
+            arguments,
+        } => Expression::CallExpression {
+            function: Box::new(folder.fold_expression(*function)),
+            arguments: arguments
+                .into_iter()
+                .map(|argument| folder.fold_expression(argument))
+                .collect(),
+        },
+    }
+}
+
+pub fn fold_block_statement<F: Folder + ?Sized>(
+    folder: &mut F,
+    block: BlockStatement,
+) -> BlockStatement {
+    BlockStatement {
+        statements: block
+            .statements
+            .into_iter()
+            .map(|statement| folder.fold_node(statement))
+            .collect(),
+    }
+}
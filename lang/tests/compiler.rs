@@ -0,0 +1,331 @@
+use kl_rs::evaluator::{Evaluator, Object};
+use kl_rs::{ast::AstNode, compiler, lexer::Lexer, parser::Parser};
+use kvm::{Instruction, Kvm};
+
+fn parse(code: &str) -> AstNode {
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    assert_eq!(parser.errors.len(), 0);
+    program
+}
+
+/// The type and printed form of the value left on top of the stack.
+fn run(instructions: Vec<Instruction>) -> (&'static str, String) {
+    let mut vm = Kvm::new();
+    vm.load_program_from_vec(instructions);
+    vm.execute_program().expect("program should run on kvm");
+    let value = vm.get_stack().last().expect("program should leave a value");
+
+    (value.type_name(), vm.get_heap().display(value))
+}
+
+fn expected_value(code: &str) -> (&'static str, String) {
+    let object = match Evaluator::new().eval(parse(code)) {
+        Object::Return(value) => *value,
+        object => object,
+    };
+
+    let type_name = match object {
+        Object::Integer(_) => "int",
+        Object::Boolean(_) => "bool",
+        Object::String(_) => "string",
+        Object::Array(_) => "array",
+        Object::Null => "null",
+        object => panic!("unexpected value: {:?}", object),
+    };
+
+    (type_name, object.inspect())
+}
+
+#[test]
+fn given_arithmetic_expressions_it_should_match_the_evaluator() {
+    let test_codes = [
+        "1 + 2 * 3",
+        "10 - 4 - 3",
+        "10 + (4 - 3)",
+        "100 / (10 - 5) * 2",
+        "(20 / 4) / 5",
+        "-(7 - 10) * 2",
+        "(5 + 10 * 2 + 15 / 3) * 2 + -10",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_comparisons_it_should_match_the_evaluator() {
+    let test_codes = [
+        "1 < 2",
+        "2 < 1",
+        "2 < 2",
+        "3 > 1",
+        "1 > 3",
+        "4 == 4",
+        "4 != 4",
+        "!true",
+        "!(1 > 2)",
+        "\"a\" == \"a\"",
+        "(\"a\" + \"b\") != \"ab\"",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_string_results_it_should_push_them_on_the_stack() {
+    let test_codes = [
+        "\"hello\" + \" world\"",
+        "let name = \"kl\"; if (true) { name } else { \"rs\" }",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_let_bindings_it_should_match_the_evaluator() {
+    let test_codes = [
+        "let a = 5; let b = a * 2; b - a",
+        "let a = 3; let b = 4; a * a + b * b",
+        "let name = \"kl\"; let same = name == \"kl\"; same",
+        "let a = 1; let a = a + 1; a",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_if_expressions_it_should_match_the_evaluator() {
+    let test_codes = [
+        "if (1 < 2) { 10 } else { 20 }",
+        "if (1 > 2) { 10 } else { 20 }",
+        "let x = 0; if (x) { 1 } else { 2 }",
+        "let x = -3; if (x) { 1 } else { 2 }",
+        "let a = 5; let b = if (a > 2) { let c = a * 2; c + 1 } else { 0 }; b * 2",
+        "if (true) { return 7; } else { 8 }",
+        "let a = 2; if (a == 2) { return 20; }; 5",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_if_statements_it_should_discard_the_value_of_their_branches() {
+    let test_codes = [
+        "if (true) { let q = 9; q }; 1",
+        "let x = 3; if (x > 2) { x * 2 }; x",
+        "if (false) { 1 } else { \"a\" }; 2",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+    assert!(compiler::compile(&parse("if (true) { 1 }")).is_err());
+}
+
+#[test]
+fn given_function_calls_it_should_match_the_evaluator() {
+    let test_codes = [
+        "let double = fn(x) { x * 2 }; double(21)",
+        "let sub = fn(a, b) { a - b }; sub(10, 3) * 2",
+        "let max = fn(a, b) { if (a > b) { a } else { b } }; max(3, 8) + max(9, 4)",
+        "let sign = fn(x) { if (x < 0) { return 0; } else { 1 } }; sign(5) + sign(0 - 5)",
+        "let isnot = fn(b: bool) { !b }; isnot(false)",
+        "let area = fn(w, h) { let a = w * h; a }; let w = 2; area(w, 5) + w",
+        "fn(x) { x + 1 }(4)",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_invalid_function_calls_it_should_return_an_error() {
+    let test_codes = [
+        "let f = fn(x) { x }; f(1, 2)",
+        "let f = fn(x) { x }; f(true)",
+        "let f = fn(x) { \"foo\" }; f(1)",
+        "let f = fn(x) { if (x) { return 1; } else { false } }; f(1)",
+    ];
+
+    test_codes
+        .iter()
+        .for_each(|code| assert!(compiler::compile(&parse(code)).is_err()));
+}
+
+#[test]
+fn given_return_statements_it_should_skip_the_remaining_code() {
+    let code = "let a = 1; return 2; a + 10";
+
+    assert_eq!(
+        run(compiler::compile(&parse(code)).unwrap()),
+        ("int", "2".to_string())
+    );
+}
+
+#[test]
+fn given_unsupported_expressions_it_should_return_an_error() {
+    let test_codes = ["[1, true]", "foo", "len(1)", "\"a\" - \"b\""];
+
+    test_codes
+        .iter()
+        .for_each(|code| assert!(compiler::compile(&parse(code)).is_err()));
+}
+
+#[test]
+fn given_puts_calls_it_should_print_the_value() {
+    let test_codes = ["puts(1 + 2)", "puts(1 < 2)", "puts(\"hello\" + \" world\")"];
+    let expected_instructions = [
+        vec![
+            Instruction::Push(1),
+            Instruction::Push(2),
+            Instruction::Add,
+            Instruction::Print,
+            Instruction::PushNull,
+            Instruction::Halt,
+        ],
+        vec![
+            Instruction::Push(1),
+            Instruction::Push(2),
+            Instruction::Lt,
+            Instruction::Print,
+            Instruction::PushNull,
+            Instruction::Halt,
+        ],
+        vec![
+            Instruction::PushStr("hello world".to_string()),
+            Instruction::Print,
+            Instruction::PushNull,
+            Instruction::Halt,
+        ],
+    ];
+
+    test_codes.iter().enumerate().for_each(|(idx, code)| {
+        let instructions = compiler::compile(&parse(code)).unwrap();
+        assert_eq!(instructions, expected_instructions[idx]);
+        assert_eq!(run(instructions), ("null", "null".to_string()));
+    });
+}
+
+#[test]
+fn given_arrays_it_should_match_the_evaluator() {
+    let test_codes = [
+        "[1, 2 + 3, 4 * 5]",
+        "[]",
+        "[[1, 2], [3]]",
+        "let a = [true, false]; a",
+        "let pair = fn(a: [int], b: int) -> [[int]] { [a, [b]] }; pair([1, 2, 3], 7)",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_len_calls_it_should_match_the_evaluator() {
+    let test_codes = [
+        "len([1, 2, 3])",
+        "len([])",
+        "len(\"hello\")",
+        "let a = [[1], [2, 3]]; len(a) + len([4])",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_recursive_functions_it_should_match_the_evaluator() {
+    let test_codes = [
+        "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(5)",
+        "let fib = fn(n) { if (n < 2) { n } else { let a = fib(n - 1); a + fib(n - 2) } }; fib(6)",
+        "let even = fn(n) -> bool { if (n == 0) { true } else { !even(n - 1) } }; even(7)",
+        "let limit = 3; let count = fn(n) { if (n < limit) { count(n + 1) } else { n } }; count(0)",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_bindings_of_the_main_program_it_should_make_them_visible_in_functions() {
+    let test_codes = [
+        "let base = 10; let add = fn(x) { x + base }; add(5)",
+        "let double = fn(x) { x * 2 }; let quad = fn(x) { double(double(x)) }; quad(3)",
+        "let yes = true; let pick = fn(x) { if (yes) { x } else { 0 } }; pick(4)",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert_eq!(
+            run(compiler::compile(&parse(code)).unwrap()),
+            expected_value(code)
+        )
+    });
+}
+
+#[test]
+fn given_a_recursive_function_returning_another_value_it_should_return_an_error() {
+    let code = "let f = fn(n) { if (n < 1) { true } else { f(n - 1) } }; f(3)";
+
+    assert!(compiler::compile(&parse(code)).is_err());
+}
+
+#[test]
+fn given_expression_statements_it_should_only_keep_the_last_value() {
+    let code = "let x = 1; puts(x); x + 1; let f = fn(y) { y * 2; y }; f(x); if (x > 0) { x * 2; x } else { 0 }";
+
+    let mut vm = Kvm::new();
+    vm.load_program_from_vec(compiler::compile(&parse(code)).unwrap());
+    vm.execute_program().unwrap();
+
+    // the binding of `x` and the value of the program
+    assert_eq!(vm.get_stack().len(), 2);
+    assert_eq!(
+        run(compiler::compile(&parse(code)).unwrap()),
+        expected_value(code)
+    );
+}
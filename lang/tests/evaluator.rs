@@ -3,8 +3,8 @@ use kl_rs::{ast::AstNode, lexer::Lexer, parser::Parser};
 
 #[test]
 fn given_an_integer_expression_it_should_evaluate_to_the_right_object() {
-    let test_codes = vec!["5", "10", "20"];
-    let expected_objects = vec![Object::Integer(5), Object::Integer(10), Object::Integer(20)];
+    let test_codes = ["5", "10", "20"];
+    let expected_objects = [Object::Integer(5), Object::Integer(10), Object::Integer(20)];
 
     test_codes.iter().enumerate().for_each(|(idx, code)| {
         let lexer = Lexer::new(code);
@@ -24,8 +24,8 @@ fn given_an_integer_expression_it_should_evaluate_to_the_right_object() {
 
 #[test]
 fn given_boolean_expressions_it_should_evaluate_to_the_right_object() {
-    let test_codes = vec!["true", "false"];
-    let expected_objects = vec![Object::Boolean(true), Object::Boolean(false)];
+    let test_codes = ["true", "false"];
+    let expected_objects = [Object::Boolean(true), Object::Boolean(false)];
 
     test_codes.iter().enumerate().for_each(|(idx, code)| {
         let lexer = Lexer::new(code);
@@ -45,8 +45,8 @@ fn given_boolean_expressions_it_should_evaluate_to_the_right_object() {
 
 #[test]
 fn given_prefix_expressions_it_should_evaluate_correctly() {
-    let test_codes = vec!["!true", "!false", "!!!!true", "-10", "!20"];
-    let expected_objects = vec![
+    let test_codes = ["!true", "!false", "!!!!true", "-10", "!20"];
+    let expected_objects = [
         Object::Boolean(false),
         Object::Boolean(true),
         Object::Boolean(true),
@@ -72,7 +72,7 @@ fn given_prefix_expressions_it_should_evaluate_correctly() {
 
 #[test]
 fn given_if_else_expressions_it_should_evaluate_correctly() {
-    let test_codes = vec![
+    let test_codes = [
         "if (1 < 2) { 10 } else { 20 };",
         "if (true) { 10 } else { 20 };",
         "if (false) { 10 } else { 20 };",
@@ -80,7 +80,7 @@ fn given_if_else_expressions_it_should_evaluate_correctly() {
         "if (0) { 10 } else { 20 };",
         "if (false) { 10 };",
     ];
-    let expected_objects = vec![
+    let expected_objects = [
         Object::Integer(10),
         Object::Integer(10),
         Object::Integer(20),
@@ -107,11 +107,11 @@ fn given_if_else_expressions_it_should_evaluate_correctly() {
 
 #[test]
 fn given_return_statements_it_should_evaluate_correctly() {
-    let test_codes = vec![
+    let test_codes = [
         "if (true) { if (true) { return 10; }}; 20;",
         "return 20; 10;",
     ];
-    let expected_objects = vec![
+    let expected_objects = [
         Object::Return(Box::new(Object::Integer(10))),
         Object::Return(Box::new(Object::Integer(20))),
     ];
@@ -169,3 +169,80 @@ fn given_a_string_expression_when_calling_len_it_should_evaluate_correctly() {
 
     assert_eq!(evaluated_obj, expected_obj);
 }
+
+#[test]
+fn given_an_array_expression_when_calling_len_it_should_evaluate_correctly() {
+    let code = "len([1, 2 + 3, 4])";
+    let expected_obj = Object::Integer(3);
+
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+    let parsed_program = parser.parse_program();
+    let node = match parsed_program {
+        AstNode::Program { statements } => statements.first().unwrap().clone(),
+        _ => panic!("Unexpected AstNode!"),
+    };
+
+    let evaluator = Evaluator::new();
+    let evaluated_obj = evaluator.eval(node);
+
+    assert_eq!(evaluated_obj, expected_obj);
+}
+
+#[test]
+fn given_string_infix_expressions_it_should_evaluate_correctly() {
+    let test_codes = [
+        "\"kevin\" + \" language\"",
+        "\"foo\" == \"foo\"",
+        "\"foo\" != \"foo\"",
+        "\"foo\" - \"bar\"",
+    ];
+    let expected_objects = [
+        Object::String("kevin language".to_string()),
+        Object::Boolean(true),
+        Object::Boolean(false),
+        Object::Null,
+    ];
+
+    test_codes.iter().enumerate().for_each(|(idx, code)| {
+        let lexer = Lexer::new(code);
+        let mut parser = Parser::new(lexer);
+        let parsed_program = parser.parse_program();
+        let node = match parsed_program {
+            AstNode::Program { statements } => statements.first().unwrap().clone(),
+            _ => panic!("Unexpected AstNode!"),
+        };
+
+        let evaluator = Evaluator::new();
+        let evaluated_obj = evaluator.eval(node);
+
+        assert_eq!(evaluated_obj, *expected_objects.get(idx).unwrap());
+    })
+}
+
+#[test]
+fn given_functions_using_outer_bindings_it_should_evaluate_correctly() {
+    let test_codes = [
+        "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(5)",
+        "let base = 10; let add = fn(x) { x + base }; add(5)",
+        "let double = fn(x) { x * 2 }; let quad = fn(x) { double(double(x)) }; quad(3)",
+        "let x = 1; let f = fn(x) { x * 2 }; x + f(4)",
+    ];
+    let expected_objects = [
+        Object::Integer(120),
+        Object::Integer(15),
+        Object::Integer(12),
+        Object::Integer(9),
+    ];
+
+    test_codes.iter().enumerate().for_each(|(idx, code)| {
+        let lexer = Lexer::new(code);
+        let mut parser = Parser::new(lexer);
+        let parsed_program = parser.parse_program();
+
+        let evaluator = Evaluator::new();
+        let evaluated_obj = evaluator.eval(parsed_program);
+
+        assert_eq!(evaluated_obj, *expected_objects.get(idx).unwrap());
+    })
+}
@@ -1,4 +1,7 @@
-use kl_rs::{lexer::Lexer, token::Token};
+use kl_rs::{
+    lexer::Lexer,
+    token::{Span, Token},
+};
 
 #[test]
 fn given_code_with_single_characters_it_should_parse_correctly() {
@@ -36,7 +39,7 @@ fn given_code_with_single_characters_it_should_parse_correctly() {
 fn given_code_with_keywords_it_should_parse_correctly() {
     let code = "fn let if else true false return";
 
-    let expected_tokens = vec![
+    let expected_tokens = [
         Token::Function,
         Token::Let,
         Token::If,
@@ -102,3 +105,84 @@ fn given_code_with_comments_it_should_parse_correctly() {
 
     assert!(token == expected_token);
 }
+
+#[test]
+fn given_code_with_multiple_lines_it_should_report_token_spans() {
+    let code = "let foo = 1;\n  foo";
+    let expected_spans = [
+        Span { line: 1, column: 1 },
+        Span { line: 1, column: 5 },
+        Span { line: 1, column: 9 },
+        Span {
+            line: 1,
+            column: 11,
+        },
+        Span {
+            line: 1,
+            column: 12,
+        },
+        Span { line: 2, column: 3 },
+    ];
+
+    let mut lexer = Lexer::new(code);
+
+    expected_spans.iter().for_each(|expected_span| {
+        lexer.next().unwrap();
+        assert_eq!(lexer.span(), *expected_span);
+    });
+}
+
+#[test]
+fn given_code_with_type_annotations_it_should_parse_correctly() {
+    let code = "a: int -> - >";
+    let expected_tokens = [
+        Token::Identifier("a".to_string()),
+        Token::Colon,
+        Token::Identifier("int".to_string()),
+        Token::Arrow,
+        Token::Minus,
+        Token::GreaterThan,
+        Token::Eof,
+    ];
+
+    let mut lexer = Lexer::new(code);
+
+    expected_tokens.iter().for_each(|expected_token| {
+        assert_eq!(lexer.next().unwrap(), *expected_token);
+    });
+}
+
+#[test]
+fn given_non_ascii_strings_it_should_count_columns_in_chars() {
+    let code = "let s = \"héllo ✓\";\nlen(s)";
+    let expected = [
+        (Token::Let, Span { line: 1, column: 1 }),
+        (
+            Token::Identifier("s".to_string()),
+            Span { line: 1, column: 5 },
+        ),
+        (Token::Assign, Span { line: 1, column: 7 }),
+        (
+            Token::String("héllo ✓".to_string()),
+            Span { line: 1, column: 9 },
+        ),
+        (
+            Token::Semicolon,
+            Span {
+                line: 1,
+                column: 18,
+            },
+        ),
+        (
+            Token::Identifier("len".to_string()),
+            Span { line: 2, column: 1 },
+        ),
+    ];
+
+    let mut lexer = Lexer::new(code);
+
+    expected.iter().for_each(|(expected_token, expected_span)| {
+        assert_eq!(lexer.next().unwrap(), *expected_token);
+        assert_eq!(lexer.span(), *expected_span);
+    });
+}
@@ -0,0 +1,152 @@
+use kl_rs::linter::{Linter, Rule};
+use kl_rs::token::Span;
+
+fn lint_rules(code: &str) -> Vec<(Rule, Span)> {
+    Linter::new()
+        .lint(code)
+        .expect("code should parse")
+        .into_iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.span))
+        .collect()
+}
+
+#[test]
+fn given_an_unused_let_binding_it_should_report_it() {
+    let code = "let foo = 10;\nlet bar = 20;\nbar;";
+
+    let diagnostics = lint_rules(code);
+
+    assert_eq!(
+        diagnostics,
+        [(Rule::UnusedBinding, Span { line: 1, column: 5 })]
+    );
+}
+
+#[test]
+fn given_shadowed_names_it_should_report_them() {
+    let code = "let foo = 1;\nlet foo = foo + 1;\nlet len = fn(a, a) { a };\nlen(\"a\") + foo;";
+
+    let diagnostics = lint_rules(code);
+
+    assert_eq!(
+        diagnostics,
+        [
+            (Rule::ShadowedName, Span { line: 2, column: 5 }),
+            (Rule::ShadowedName, Span { line: 3, column: 5 }),
+            (Rule::UnusedBinding, Span { line: 3, column: 5 }),
+            (
+                Rule::ShadowedName,
+                Span {
+                    line: 3,
+                    column: 17
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn given_code_after_a_return_it_should_report_it_as_unreachable() {
+    let code = "let f = fn(a) { return 1; a };\nf(1);";
+
+    let diagnostics = lint_rules(code);
+
+    assert_eq!(
+        diagnostics,
+        [(
+            Rule::UnreachableCode,
+            Span {
+                line: 1,
+                column: 17
+            }
+        )]
+    );
+}
+
+#[test]
+fn given_calls_to_undefined_functions_it_should_report_them() {
+    let code = "let f = fn(a) { g(a) };\nlet g = fn(a) { a };\nf(1);\ng(2);\nh(3);";
+
+    let diagnostics = lint_rules(code);
+
+    assert_eq!(
+        diagnostics,
+        [
+            (
+                Rule::UndefinedIdentifier,
+                Span {
+                    line: 1,
+                    column: 17
+                }
+            ),
+            (Rule::UndefinedIdentifier, Span { line: 5, column: 1 }),
+        ]
+    );
+}
+
+#[test]
+fn given_a_recursive_function_it_should_not_report_its_own_call() {
+    let code = "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } };\nfact(5);";
+
+    assert_eq!(lint_rules(code), []);
+}
+
+#[test]
+fn given_a_binding_used_only_inside_a_function_it_should_not_report_it() {
+    let code = "let g = 1;\nlet f = fn() { g + 1 };\nf();";
+
+    assert_eq!(lint_rules(code), []);
+}
+
+#[test]
+fn given_builtin_calls_with_the_wrong_number_of_arguments_it_should_report_them() {
+    let code = "len(\"a\");\nlen(\"a\", \"b\");\nlen();";
+
+    let diagnostics = lint_rules(code);
+
+    assert_eq!(
+        diagnostics,
+        [
+            (Rule::BuiltinArity, Span { line: 2, column: 1 }),
+            (Rule::BuiltinArity, Span { line: 3, column: 1 }),
+        ]
+    );
+}
+
+#[test]
+fn given_constant_comparisons_it_should_report_them() {
+    let code = "let a = 1;\n1 < 2;\na == a;\na > 1;\n\"a\" != \"b\";";
+
+    let diagnostics = lint_rules(code);
+
+    assert_eq!(
+        diagnostics,
+        [
+            (Rule::ConstantComparison, Span { line: 2, column: 3 }),
+            (Rule::ConstantComparison, Span { line: 3, column: 3 }),
+            (Rule::ConstantComparison, Span { line: 5, column: 5 }),
+        ]
+    );
+}
+
+#[test]
+fn given_constant_comparisons_without_a_boolean_result_it_should_not_report_them() {
+    assert_eq!(lint_rules("\"a\" < \"b\";"), []);
+}
+
+#[test]
+fn given_allowed_rules_it_should_suppress_their_diagnostics() {
+    let code = "let foo = 10;\nmissing(foo);\nlet bar = 1;";
+
+    let mut linter = Linter::new();
+    linter.allow(Rule::UnusedBinding);
+    let diagnostics = linter.lint(code).unwrap();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].rule, Rule::UndefinedIdentifier);
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "2:1: undefined-identifier: call to undefined function 'missing'"
+    );
+    assert_eq!("unused-binding".parse::<Rule>(), Ok(Rule::UnusedBinding));
+}
@@ -0,0 +1,148 @@
+use kl_rs::evaluator::{Evaluator, Object};
+use kl_rs::{
+    ast::{AstNode, Expression, Statement},
+    lexer::Lexer,
+    optimizer,
+    parser::Parser,
+    token::Token,
+};
+
+fn parse(code: &str) -> AstNode {
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    assert_eq!(parser.errors.len(), 0);
+    program
+}
+
+#[test]
+fn given_constant_expressions_it_should_fold_them() {
+    let test_codes = [
+        "2 * 60 * 60",
+        "-(5 - 10)",
+        "\"foo\" + \"bar\"",
+        "1 < 2",
+        "3 == 4",
+        "\"a\" != \"b\"",
+        "!true",
+    ];
+    let expected_expressions = [
+        Expression::Int(7200),
+        Expression::Int(5),
+        Expression::String("foobar".to_string()),
+        Expression::Boolean(true),
+        Expression::Boolean(false),
+        Expression::Boolean(true),
+        Expression::Boolean(false),
+    ];
+
+    test_codes.iter().enumerate().for_each(|(idx, code)| {
+        let optimized = optimizer::optimize(parse(code));
+        let expected_program = AstNode::Program {
+            statements: vec![AstNode::Expression(Box::new(
+                expected_expressions[idx].clone(),
+            ))],
+        };
+
+        assert_eq!(optimized, expected_program);
+    })
+}
+
+#[test]
+fn given_expressions_that_fail_at_runtime_it_should_not_fold_them() {
+    let test_codes = ["10 / 0", "2147483647 + 1", "x + 1"];
+
+    test_codes.iter().for_each(|code| {
+        let program = parse(code);
+        assert_eq!(optimizer::optimize(program.clone()), program);
+    })
+}
+
+#[test]
+fn given_if_expressions_with_constant_conditions_it_should_remove_dead_branches() {
+    let test_codes = [
+        "let foo = if (true) { 10 } else { 1 / 0 };",
+        "let foo = if (1 > 2) { 10 } else { 20 };",
+    ];
+    let expected_values = [Expression::Int(10), Expression::Int(20)];
+
+    test_codes.iter().enumerate().for_each(|(idx, code)| {
+        let optimized = optimizer::optimize(parse(code));
+        let expected_program = AstNode::Program {
+            statements: vec![AstNode::Statement(Box::new(Statement::LetStatement {
+                name: Box::new(Expression::Identifier("foo".to_string())),
+                annotation: None,
+                value: Box::new(expected_values[idx].clone()),
+            }))],
+        };
+
+        assert_eq!(optimized, expected_program);
+    })
+}
+
+#[test]
+fn given_an_always_taken_branch_it_should_splice_it_into_the_enclosing_block() {
+    let code = "if (0) { x } else { let foo = 1; foo + x };";
+
+    let optimized = optimizer::optimize(parse(code));
+    let expected_program = AstNode::Program {
+        statements: vec![
+            AstNode::Statement(Box::new(Statement::LetStatement {
+                name: Box::new(Expression::Identifier("foo".to_string())),
+                annotation: None,
+                value: Box::new(Expression::Int(1)),
+            })),
+            AstNode::Expression(Box::new(Expression::Infix {
+                operator: Token::Plus,
+                left: Box::new(Expression::Identifier("foo".to_string())),
+                right: Box::new(Expression::Identifier("x".to_string())),
+            })),
+        ],
+    };
+
+    assert_eq!(optimized, expected_program);
+}
+
+#[test]
+fn given_statements_after_a_return_it_should_remove_them() {
+    let code = "return 10; 1 / 0; x;";
+
+    let optimized = optimizer::optimize(parse(code));
+    let expected_program = AstNode::Program {
+        statements: vec![AstNode::Statement(Box::new(Statement::ReturnStatement(
+            Box::new(Expression::Int(10)),
+        )))],
+    };
+
+    assert_eq!(optimized, expected_program);
+}
+
+#[test]
+fn given_programs_it_should_evaluate_the_same_before_and_after_optimizing() {
+    let test_codes = [
+        "let a = 2 * 3; a + 4 * 5",
+        "if (false) { 10 }",
+        "if (\"str\") { 10 } else { 20 }",
+        "let f = fn(x) { x * (1 + 1) }; f(21)",
+        "if (true) { if (true) { return 10; }; 30 }; 20;",
+        "\"kl\" + \"-rs\"",
+    ];
+    let expected_objects = [
+        Object::Integer(26),
+        Object::Null,
+        Object::Integer(20),
+        Object::Integer(42),
+        Object::Return(Box::new(Object::Integer(10))),
+        Object::String("kl-rs".to_string()),
+    ];
+
+    test_codes.iter().enumerate().for_each(|(idx, code)| {
+        let program = parse(code);
+
+        let evaluated_obj = Evaluator::new().eval(program.clone());
+        let optimized_obj = Evaluator::new().eval(optimizer::optimize(program));
+
+        assert_eq!(evaluated_obj, expected_objects[idx]);
+        assert_eq!(optimized_obj, expected_objects[idx]);
+    })
+}
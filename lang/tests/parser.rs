@@ -1,5 +1,5 @@
 use kl_rs::{
-    ast::{AstNode, BlockStatement, Expression, Statement},
+    ast::{AstNode, BlockStatement, Expression, Statement, TypeAnnotation},
     lexer::Lexer,
     parser::Parser,
     token::Token,
@@ -32,6 +32,7 @@ fn given_let_statements_with_single_integers_shold_parse_correctly() {
 
                 let expected_statement = AstNode::Statement(Box::new(Statement::LetStatement {
                     name: Box::new(Expression::Identifier(expected_identifier.to_string())),
+                    annotation: None,
                     value: Box::new(Expression::Int(expected_int.to_string().parse().unwrap())),
                 }));
 
@@ -144,8 +145,8 @@ fn given_a_prefix_expression_it_should_parse_correctly() {
     let lexer = Lexer::new(code);
     let mut parser = Parser::new(lexer);
 
-    let expected_operators = vec![Token::Minus, Token::Bang];
-    let expected_values = vec!["5", "20"];
+    let expected_operators = [Token::Minus, Token::Bang];
+    let expected_values = ["5", "20"];
 
     let parsed_program = parser.parse_program();
 
@@ -181,11 +182,11 @@ fn given_a_prefix_expression_it_should_parse_correctly() {
 
 #[test]
 fn given_infix_expressions_it_should_parse_correctly() {
-    let infix_statements = vec![
+    let infix_statements = [
         "5 + 6;", "10 - 5;", "2 < 3;", "2 > 3;", "4 * 5;", "5 / 7;", "8 == 9;", "4 != 2;",
     ];
 
-    let expected_operators = vec![
+    let expected_operators = [
         Token::Plus,
         Token::Minus,
         Token::LessThan,
@@ -196,7 +197,7 @@ fn given_infix_expressions_it_should_parse_correctly() {
         Token::NotEquals,
     ];
 
-    let expected_literals = vec![
+    let expected_literals = [
         ("5", "6"),
         ("10", "5"),
         ("2", "3"),
@@ -221,8 +222,8 @@ fn given_infix_expressions_it_should_parse_correctly() {
 
 #[test]
 fn given_boolean_expression_it_should_parse_correctly() {
-    let test_cases = vec!["true;", "false;", "!true;"];
-    let expected_expressions = vec![
+    let test_cases = ["true;", "false;", "!true;"];
+    let expected_expressions = [
         Expression::Boolean(true),
         Expression::Boolean(false),
         Expression::Prefix {
@@ -239,7 +240,7 @@ fn given_boolean_expression_it_should_parse_correctly() {
 
 #[test]
 fn given_a_grouped_expression_it_should_parse_correctly() {
-    let test_cases = vec![
+    let test_cases = [
         ("(1 + (2 + 3)) + 4", "((1 + (2 + 3)) + 4)"),
         ("-(5 + 5)", "(-(5 + 5))"),
     ];
@@ -316,6 +317,28 @@ fn given_an_if_expression_it_should_parse_correctly() {
     }
 }
 
+#[test]
+fn given_an_if_expression_followed_by_other_statements_it_should_parse_them() {
+    let code = "if (x) { 1 }; 2";
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+
+    let parsed_program = parser.parse_program();
+
+    assert_eq!(parser.errors.len(), 0);
+    match parsed_program {
+        AstNode::Program { statements } => {
+            assert_eq!(statements.len(), 2);
+
+            match &statements[1] {
+                AstNode::Expression(expression) => assert_eq!(**expression, Expression::Int(2)),
+                _ => panic!("Unexpected statement!"),
+            }
+        }
+        _ => panic!("Unexpeced AstNode!"),
+    }
+}
+
 #[test]
 fn given_an_if_else_expression_it_should_parse_correctly() {
     let code = "if (x < y) { x } else { y }";
@@ -366,6 +389,8 @@ fn given_a_function_expression_it_should_parse_correctly() {
             Token::Identifier("a".to_string()),
             Token::Identifier("b".to_string()),
         ],
+        parameter_types: vec![None, None],
+        return_type: None,
         body: Box::new(BlockStatement {
             statements: vec![AstNode::Expression(Box::new(Expression::Infix {
                 operator: Token::Plus,
@@ -554,3 +579,123 @@ fn assert_infix_expression(code: &str, expected_operator: Token, expected_litera
         _ => panic!("Unexpected AstNode!"),
     }
 }
+
+#[test]
+fn given_arrays_followed_by_other_tokens_it_should_parse_them() {
+    let code = "let a = [[1, 2], []]; f([3], a)";
+    let expected_value = Expression::Array(vec![
+        Expression::Array(vec![Expression::Int(1), Expression::Int(2)]),
+        Expression::Array(vec![]),
+    ]);
+    let expected_arguments = vec![
+        Expression::Array(vec![Expression::Int(3)]),
+        Expression::Identifier("a".to_string()),
+    ];
+
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+    let parsed_program = parser.parse_program();
+
+    assert_eq!(parser.errors.len(), 0);
+
+    match parsed_program {
+        AstNode::Program { statements } => {
+            assert_eq!(statements.len(), 2);
+
+            match &statements[0] {
+                AstNode::Statement(statement) => match &**statement {
+                    Statement::LetStatement { value, .. } => assert_eq!(**value, expected_value),
+                    _ => panic!("Unexpected Statement!"),
+                },
+                _ => panic!("Unexpected AstNode!"),
+            }
+
+            match &statements[1] {
+                AstNode::Expression(expression) => match &**expression {
+                    Expression::CallExpression { arguments, .. } => {
+                        assert_eq!(*arguments, expected_arguments)
+                    }
+                    _ => panic!("Unexpected expression!"),
+                },
+                _ => panic!("Unexpected AstNode!"),
+            }
+        }
+        _ => panic!("Unexpected AstNode!"),
+    }
+}
+
+#[test]
+fn given_let_statements_with_type_annotations_it_should_parse_correctly() {
+    let code = "let foo: int = 10; let bar: fn([string], bool) -> int = baz;";
+    let expected_annotations = [
+        TypeAnnotation::Int,
+        TypeAnnotation::Function {
+            parameters: vec![
+                TypeAnnotation::Array(Box::new(TypeAnnotation::String)),
+                TypeAnnotation::Bool,
+            ],
+            return_type: Box::new(TypeAnnotation::Int),
+        },
+    ];
+
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+    let parsed_program = parser.parse_program();
+
+    assert_eq!(parser.errors.len(), 0);
+
+    match parsed_program {
+        AstNode::Program { statements } => {
+            assert_eq!(statements.len(), 2);
+
+            statements
+                .iter()
+                .enumerate()
+                .for_each(|(idx, statement)| match statement {
+                    AstNode::Statement(statement) => match **statement {
+                        Statement::LetStatement { ref annotation, .. } => {
+                            assert_eq!(*annotation, Some(expected_annotations[idx].clone()))
+                        }
+                        _ => panic!("Unexpected Statement!"),
+                    },
+                    _ => panic!("Unexpected AstNode!"),
+                })
+        }
+        _ => panic!("Unexpected AstNode!"),
+    }
+}
+
+#[test]
+fn given_a_function_expression_with_type_annotations_it_should_parse_correctly() {
+    let code = "fn(a: int, b) -> bool { a };";
+    let expected_expression = Expression::FunctionExpression {
+        parameters: vec![
+            Token::Identifier("a".to_string()),
+            Token::Identifier("b".to_string()),
+        ],
+        parameter_types: vec![Some(TypeAnnotation::Int), None],
+        return_type: Some(TypeAnnotation::Bool),
+        body: Box::new(BlockStatement {
+            statements: vec![AstNode::Expression(Box::new(Expression::Identifier(
+                "a".to_string(),
+            )))],
+        }),
+    };
+
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+    let parsed_program = parser.parse_program();
+
+    assert_eq!(parser.errors.len(), 0);
+
+    match parsed_program {
+        AstNode::Program { statements } => {
+            assert_eq!(statements.len(), 1);
+            assert_eq!(
+                *statements.first().unwrap(),
+                AstNode::Expression(Box::new(expected_expression))
+            );
+        }
+        _ => panic!("Unexpected AstNode!"),
+    }
+}
@@ -0,0 +1,162 @@
+use kl_rs::typechecker::{Type, TypeChecker};
+use kl_rs::{ast::AstNode, lexer::Lexer, parser::Parser};
+
+fn check(code: &str) -> Result<Type, Vec<String>> {
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+    assert_eq!(parser.errors, Vec::<String>::new());
+
+    TypeChecker::new()
+        .check(&program)
+        .map_err(|errors| errors.iter().map(|err| err.to_string()).collect())
+}
+
+#[test]
+fn given_well_typed_expressions_it_should_infer_their_types() {
+    let test_codes = [
+        "1 + 2 * 3",
+        "1 < 2",
+        "\"foo\" + \"bar\"",
+        "\"foo\" == \"bar\"",
+        "!true",
+        "if (1) { \"yes\" } else { \"no\" }",
+        "if (true) { 1 }",
+        "len(\"kevin\")",
+        "len([1, 2])",
+        "let count = fn(a: [bool]) { len(a) }; count",
+        "let add = fn(a, b) { a + b }; add(1, 2)",
+        "let greet = fn(name) { \"hi \" + name }; greet",
+    ];
+    let expected_types = [
+        Type::Int,
+        Type::Bool,
+        Type::String,
+        Type::Bool,
+        Type::Bool,
+        Type::String,
+        Type::Null,
+        Type::Int,
+        Type::Int,
+        Type::Function {
+            parameters: vec![Type::Array(Box::new(Type::Bool))],
+            return_type: Box::new(Type::Int),
+        },
+        Type::Int,
+        Type::Function {
+            parameters: vec![Type::String],
+            return_type: Box::new(Type::String),
+        },
+    ];
+
+    test_codes.iter().enumerate().for_each(|(idx, code)| {
+        assert_eq!(check(code), Ok(expected_types[idx].clone()), "{}", code);
+    })
+}
+
+#[test]
+fn given_a_generic_function_it_should_instantiate_it_at_each_use() {
+    let code = "let id = fn(x) { x }; let a = id(1); let b = id(\"b\"); b";
+
+    assert_eq!(check(code), Ok(Type::String));
+}
+
+#[test]
+fn given_return_statements_it_should_unify_them_with_the_function_result() {
+    let code = "let f = fn(a) { return 1; a }; f(2)";
+
+    assert_eq!(check(code), Ok(Type::Int));
+    assert!(check("let f = fn(a) -> string { return 1; }; f").is_err());
+}
+
+#[test]
+fn given_a_recursive_function_it_should_infer_its_type() {
+    let code = "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(5)";
+
+    assert_eq!(check(code), Ok(Type::Int));
+}
+
+#[test]
+fn given_a_function_reading_a_global_binding_it_should_infer_its_type() {
+    let code = "let g = 1; let f = fn() { g + 1 }; f()";
+
+    assert_eq!(check(code), Ok(Type::Int));
+}
+
+#[test]
+fn given_type_annotations_it_should_check_them() {
+    let test_codes = [
+        "let x: int = 1; x",
+        "let f = fn(xs: [string]) { xs }; f",
+        "let f = fn(a: int, b: int) -> bool { a < b }; f",
+        "let apply: fn(fn(int) -> int, int) -> int = fn(f, x) { f(x) }; apply",
+    ];
+    let expected_types = [
+        Type::Int,
+        Type::Function {
+            parameters: vec![Type::Array(Box::new(Type::String))],
+            return_type: Box::new(Type::Array(Box::new(Type::String))),
+        },
+        Type::Function {
+            parameters: vec![Type::Int, Type::Int],
+            return_type: Box::new(Type::Bool),
+        },
+        Type::Function {
+            parameters: vec![
+                Type::Function {
+                    parameters: vec![Type::Int],
+                    return_type: Box::new(Type::Int),
+                },
+                Type::Int,
+            ],
+            return_type: Box::new(Type::Int),
+        },
+    ];
+
+    test_codes.iter().enumerate().for_each(|(idx, code)| {
+        assert_eq!(check(code), Ok(expected_types[idx].clone()), "{}", code);
+    })
+}
+
+#[test]
+fn given_ill_typed_programs_it_should_report_type_errors() {
+    let test_codes = [
+        "let x: int = \"a\";",
+        "1 + true",
+        "true == false",
+        "-\"a\"",
+        "if (\"a\") { 1 } else { 2 }",
+        "if (true) { 1 } else { \"a\" }",
+        "let f = fn(a: int) -> string { a }; f",
+        "let f = fn(a) { a }; f(1, 2)",
+        "len(1)",
+        "len(true)",
+        "undefined",
+        "let f = fn(n) { if (n < 2) { 1 } else { f(true) } }; f",
+    ];
+
+    test_codes.iter().for_each(|code| {
+        assert!(check(code).is_err(), "{}", code);
+    })
+}
+
+#[test]
+fn given_a_type_error_it_should_describe_the_mismatch() {
+    assert_eq!(
+        check("let x: int = \"a\";"),
+        Err(vec![
+            "type mismatch in let statement: expected int, found string".to_string()
+        ])
+    );
+}
+
+#[test]
+fn given_consecutive_checks_it_should_keep_previous_bindings() {
+    let mut type_checker = TypeChecker::new();
+    let first = Parser::new(Lexer::new("let x = \"kl\";")).parse_program();
+    let second = Parser::new(Lexer::new("x + \"-rs\"")).parse_program();
+
+    assert_eq!(type_checker.check(&first), Ok(Type::String));
+    assert_eq!(type_checker.check(&second), Ok(Type::String));
+    assert!(matches!(second, AstNode::Program { .. }));
+}
@@ -0,0 +1,73 @@
+use kl_rs::{
+    ast::Expression,
+    lexer::Lexer,
+    parser::Parser,
+    visitor::{self, Folder, Visitor},
+};
+
+#[derive(Default)]
+struct IdentifierCollector {
+    identifiers: Vec<String>,
+}
+
+impl Visitor for IdentifierCollector {
+    fn visit_expression(&mut self, expression: &Expression) {
+        if let Expression::Identifier(name) = expression {
+            self.identifiers.push(name.clone());
+        }
+        visitor::walk_expression(self, expression);
+    }
+}
+
+struct IntDoubler;
+
+impl Folder for IntDoubler {
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        match expression {
+            Expression::Int(value) => Expression::Int(value * 2),
+            expression => visitor::fold_expression(self, expression),
+        }
+    }
+}
+
+#[test]
+fn given_a_program_when_visiting_it_should_reach_every_nested_expression() {
+    let code = "let foo = fn(a) { baz(a) }; if (a > bar) { foo } else { -qux };";
+
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    let mut collector = IdentifierCollector::default();
+    collector.visit_node(&program);
+
+    assert_eq!(
+        collector.identifiers,
+        ["foo", "baz", "a", "a", "bar", "foo", "qux"]
+    );
+}
+
+#[test]
+fn given_a_program_when_folding_it_should_rewrite_nested_expressions() {
+    let code = "let foo = 1 + 2;";
+
+    let lexer = Lexer::new(code);
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program();
+
+    let folded = IntDoubler.fold_node(program);
+
+    let expected_program = Parser::new(Lexer::new("let foo = 2 + 4;")).parse_program();
+    assert_eq!(folded, expected_program);
+}
+
+#[test]
+fn given_a_folder_without_overrides_it_should_keep_the_tree_unchanged() {
+    struct Identity;
+    impl Folder for Identity {}
+
+    let code = "let add = fn(a, b) { a + b }; add(1, -2); if (!true) { \"x\" };";
+    let program = Parser::new(Lexer::new(code)).parse_program();
+
+    assert_eq!(Identity.fold_node(program.clone()), program);
+}